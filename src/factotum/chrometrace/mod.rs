@@ -0,0 +1,95 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::task_list::Task;
+use factotum::factfile::Task as FactfileTask;
+use factotum::runrecord;
+use chrono::{DateTime, Timelike, UTC};
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::BTreeMap;
+
+fn to_micros(time: &DateTime<UTC>) -> i64 {
+    time.timestamp() * 1_000_000 + (time.nanosecond() / 1_000) as i64
+}
+
+// the format is the "Trace Event Format" read by chrome://tracing (and Perfetto)
+pub fn to_trace_json(job_name: &str, tasks: &Vec<&Task<&FactfileTask>>) -> Json {
+    let mut ran = tasks.iter()
+        .filter_map(|t| match (&t.run_started, &t.run_result) {
+            (&Some(ref started), &Some(ref r)) => {
+                let duration = r.duration.as_secs() as i64 * 1_000_000 +
+                               (r.duration.subsec_nanos() / 1_000) as i64;
+                Some((to_micros(started), duration, t))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    ran.sort_by(|a, b| (a.0, &a.2.name).cmp(&(b.0, &b.2.name)));
+
+    let mut process_name = BTreeMap::new();
+    process_name.insert("name".to_string(), job_name.to_json());
+    let mut metadata = BTreeMap::new();
+    metadata.insert("name".to_string(), "process_name".to_json());
+    metadata.insert("ph".to_string(), "M".to_json());
+    metadata.insert("pid".to_string(), 1u32.to_json());
+    metadata.insert("args".to_string(), Json::Object(process_name));
+    let mut events = vec![Json::Object(metadata)];
+
+    // each task goes on the first track that's free when it starts, so overlapping tasks
+    // are shown side by side and the number of tracks is the most that ran at once
+    let mut track_ends: Vec<i64> = vec![];
+    for &(start, duration, task) in ran.iter() {
+        let track = match track_ends.iter().position(|&end| end <= start) {
+            Some(idx) => idx,
+            None => {
+                track_ends.push(0);
+                track_ends.len() - 1
+            }
+        };
+        track_ends[track] = start + duration;
+
+        let mut args = BTreeMap::new();
+        args.insert("state".to_string(), runrecord::state_name(&task.state).to_json());
+        if let Some(ref r) = task.run_result {
+            args.insert("returnCode".to_string(), r.return_code.to_json());
+        }
+
+        let mut event = BTreeMap::new();
+        event.insert("name".to_string(), task.name.to_json());
+        event.insert("cat".to_string(), "task".to_json());
+        event.insert("ph".to_string(), "X".to_json());
+        event.insert("ts".to_string(), start.to_json());
+        event.insert("dur".to_string(), duration.to_json());
+        event.insert("pid".to_string(), 1u32.to_json());
+        event.insert("tid".to_string(), (track + 1).to_json());
+        event.insert("args".to_string(), Json::Object(args));
+        events.push(Json::Object(event));
+    }
+
+    let mut trace = BTreeMap::new();
+    trace.insert("traceEvents".to_string(), Json::Array(events));
+    trace.insert("displayTimeUnit".to_string(), "ms".to_json());
+    Json::Object(trace)
+}
+
+pub fn write_trace(path: &str,
+                   job_name: &str,
+                   tasks: &Vec<&Task<&FactfileTask>>)
+                   -> Result<(), String> {
+    let trace = to_trace_json(job_name, tasks);
+    runrecord::write_file_atomically(path, trace.to_string().as_bytes())
+}
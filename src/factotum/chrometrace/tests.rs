@@ -0,0 +1,79 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use factotum::tests::make_task;
+use factotum::executor::task_list::{Task, State};
+use factotum::executor::execution_strategy::RunResult;
+use factotum::factfile::Task as FactfileTask;
+use chrono::{Duration as ChronoDuration, TimeZone, UTC};
+use std::time::Duration;
+
+fn ran_task<'a>(spec: &'a FactfileTask,
+                start_ms: i64,
+                duration_ms: u64)
+                -> Task<&'a FactfileTask> {
+    let mut task = Task::<&FactfileTask>::new(spec.name.clone(), spec);
+    task.state = State::Success;
+    task.run_started = Some(UTC.ymd(2016, 1, 1).and_hms(0, 0, 0) +
+                            ChronoDuration::milliseconds(start_ms));
+    task.run_result = Some(RunResult {
+        duration: Duration::from_millis(duration_ms),
+        ..Default::default()
+    });
+    task
+}
+
+fn get_events(trace: &Json) -> Vec<(String, i64, i64, u64)> {
+    trace.find("traceEvents")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|e| e.find("ph").unwrap().as_string() == Some("X"))
+        .map(|e| {
+            (e.find("name").unwrap().as_string().unwrap().to_string(),
+             e.find("ts").unwrap().as_i64().unwrap(),
+             e.find("dur").unwrap().as_i64().unwrap(),
+             e.find("tid").unwrap().as_u64().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn overlapping_tasks_are_on_separate_tracks() {
+    let apple_spec = make_task("apple", &vec![]);
+    let turnip_spec = make_task("turnip", &vec![]);
+    let potato_spec = make_task("potato", &vec!["apple", "turnip"]);
+    let skipped_spec = make_task("skipped", &vec!["potato"]);
+
+    let apple = ran_task(&apple_spec, 0, 2000);
+    let turnip = ran_task(&turnip_spec, 0, 500);
+    let potato = ran_task(&potato_spec, 2000, 1000);
+    let mut skipped = Task::<&FactfileTask>::new("skipped", &skipped_spec);
+    skipped.state = State::Skipped("for some reason".to_string());
+
+    let trace = to_trace_json("my job", &vec![&potato, &turnip, &apple, &skipped]);
+    let start = 1451606400000000;
+
+    assert_eq!(get_events(&trace),
+               vec![("apple".to_string(), start, 2000000, 1),
+                    ("turnip".to_string(), start, 500000, 2),
+                    ("potato".to_string(), start + 2000000, 1000000, 1)]);
+
+    let metadata = &trace.find("traceEvents").unwrap().as_array().unwrap()[0];
+    assert_eq!(metadata.find("name").unwrap().as_string(), Some("process_name"));
+    assert_eq!(metadata.find_path(&["args", "name"]).unwrap().as_string(),
+               Some("my job"));
+}
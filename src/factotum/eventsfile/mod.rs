@@ -0,0 +1,88 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::{ExecutionState, ExecutionUpdate};
+use factotum::webhook::jobcontext::JobContext;
+use factotum::webhook::jobupdate::JobUpdate;
+use factotum::webhook::strip_task_output;
+use std::fs::File;
+use std::io::Write;
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+
+#[derive(Debug, PartialEq)]
+pub struct EventsFileResult {
+    pub events_received: u32,
+    pub events_written: u32,
+}
+
+// the same self-describing JSON the webhook posts, one update per line
+pub fn to_event_line(job_context: &JobContext,
+                     update: &ExecutionUpdate,
+                     max_stdouterr_size: usize,
+                     include_output: bool)
+                     -> String {
+    let job_update = if include_output {
+        JobUpdate::new(job_context, update, &max_stdouterr_size)
+    } else {
+        JobUpdate::new(job_context, &strip_task_output(update), &max_stdouterr_size)
+    };
+    format!("{}\n", job_update.as_self_desc_json())
+}
+
+pub fn create_events_file(path: &str) -> Result<File, String> {
+    File::create(path).map_err(|e| format!("couldn't create the events file '{}': {}", path, e))
+}
+
+// each line is written (and flushed) as it arrives, so a crash leaves whole lines behind
+pub fn write_events_file(mut file: File,
+                         path: String,
+                         job_context: JobContext,
+                         max_stdouterr_size: usize,
+                         include_output: bool,
+                         updates_channel: Receiver<ExecutionUpdate>)
+                         -> JoinHandle<EventsFileResult> {
+    thread::spawn(move || {
+        let mut events_received = 0;
+        let mut events_written = 0;
+        let mut failing = false;
+
+        for update in updates_channel.iter() {
+            events_received += 1;
+
+            let line = to_event_line(&job_context, &update, max_stdouterr_size, include_output);
+            match file.write_all(line.as_bytes()).and_then(|_| file.flush()) {
+                Ok(_) => events_written += 1,
+                Err(e) => {
+                    if !failing {
+                        warn!("Couldn't write events to '{}': {}", path, e);
+                    }
+                    failing = true;
+                }
+            }
+
+            if update.execution_state == ExecutionState::Finished {
+                break;
+            }
+        }
+
+        EventsFileResult {
+            events_received: events_received,
+            events_written: events_written,
+        }
+    })
+}
@@ -0,0 +1,64 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use factotum::eventsfile::*;
+use factotum::executor::{ExecutionState, ExecutionUpdate, JobTransition, Transition};
+use factotum::webhook::jobcontext::JobContext;
+use rustc_serialize::json::Json;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::sync::mpsc;
+
+fn make_update(from: Option<ExecutionState>, to: ExecutionState) -> ExecutionUpdate {
+    ExecutionUpdate::new(to.clone(), vec![], Transition::Job(JobTransition::new(from, to)))
+}
+
+#[test]
+fn updates_are_written_as_json_lines() {
+    let path = env::temp_dir().join(format!("factotum-events-{}.ndjson", ::uuid::Uuid::new_v4()));
+    let path_str = path.to_str().unwrap().to_string();
+
+    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+    let handle = write_events_file(create_events_file(&path_str).unwrap(),
+                                   path_str.clone(),
+                                   JobContext::new("hello", "world", None),
+                                   10_000,
+                                   true,
+                                   rx);
+    tx.send(make_update(None, ExecutionState::Started)).unwrap();
+    tx.send(make_update(Some(ExecutionState::Running), ExecutionState::Finished)).unwrap();
+
+    assert_eq!(handle.join().unwrap(),
+               EventsFileResult {
+                   events_received: 2,
+                   events_written: 2,
+               });
+
+    let mut contents = String::new();
+    File::open(&path).unwrap().read_to_string(&mut contents).unwrap();
+    fs::remove_file(&path).ok();
+
+    let lines = contents.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 2);
+    for line in lines.iter() {
+        assert!(Json::from_str(line).is_ok());
+    }
+    assert!(lines[1].contains("\"runState\":\"SUCCEEDED\""));
+}
+
+#[test]
+fn events_file_must_be_creatable() {
+    assert!(create_events_file("/nonexistent/factotum-events.ndjson").is_err());
+}
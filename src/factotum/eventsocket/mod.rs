@@ -0,0 +1,104 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::{ExecutionState, ExecutionUpdate};
+use factotum::webhook::jobcontext::JobContext;
+use factotum::webhook::jobupdate::JobUpdate;
+use std::sync::mpsc::Receiver;
+use std::thread::{self, JoinHandle};
+#[cfg(unix)]
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+#[cfg(unix)]
+type Connection = UnixStream;
+#[cfg(not(unix))]
+type Connection = ();
+
+#[derive(Debug, PartialEq)]
+pub struct EventSocketResult {
+    pub events_received: u32,
+    pub events_sent: u32,
+}
+
+#[cfg(unix)]
+fn write_event(path: &str, connection: &mut Option<Connection>, line: &str) -> Result<(), String> {
+    if connection.is_none() {
+        *connection = Some(try!(UnixStream::connect(path).map_err(|e| e.to_string())));
+    }
+    let result = connection.as_mut()
+        .unwrap()
+        .write_all(line.as_bytes())
+        .map_err(|e| e.to_string());
+    if result.is_err() {
+        // reconnect for the next event, the collector may have been restarted
+        *connection = None;
+    }
+    result
+}
+
+#[cfg(not(unix))]
+fn write_event(_: &str, _: &mut Option<Connection>, _: &str) -> Result<(), String> {
+    Err("unix domain sockets aren't supported on this platform".to_string())
+}
+
+pub fn to_event_line(job_context: &JobContext, update: &ExecutionUpdate) -> String {
+    // the collector is local, so task output is sent in full
+    let job_update = JobUpdate::new(job_context, update, &usize::max_value());
+    format!("{}\n", job_update.as_self_desc_json())
+}
+
+// events that can't be sent (e.g. nothing is listening yet) are dropped, the job carries on
+pub fn connect_event_socket(path: String,
+                            job_context: JobContext,
+                            updates_channel: Receiver<ExecutionUpdate>)
+                            -> JoinHandle<EventSocketResult> {
+    thread::spawn(move || {
+        let mut connection = None;
+        let mut events_received = 0;
+        let mut events_sent = 0;
+        let mut unavailable = false;
+
+        for update in updates_channel.iter() {
+            events_received += 1;
+
+            match write_event(&path, &mut connection, &to_event_line(&job_context, &update)) {
+                Ok(_) => {
+                    events_sent += 1;
+                    unavailable = false;
+                }
+                Err(msg) => {
+                    // once per outage, rather than for every event
+                    if !unavailable {
+                        warn!("Couldn't send events to the socket '{}': {}", path, msg);
+                    }
+                    unavailable = true;
+                }
+            }
+
+            if update.execution_state == ExecutionState::Finished {
+                break;
+            }
+        }
+
+        EventSocketResult {
+            events_received: events_received,
+            events_sent: events_sent,
+        }
+    })
+}
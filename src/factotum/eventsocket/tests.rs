@@ -0,0 +1,69 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use factotum::eventsocket::*;
+use factotum::executor::{ExecutionState, ExecutionUpdate, JobTransition, Transition};
+use factotum::webhook::jobcontext::JobContext;
+use std::sync::mpsc;
+
+fn make_update(from: Option<ExecutionState>, to: ExecutionState) -> ExecutionUpdate {
+    ExecutionUpdate::new(to.clone(), vec![], Transition::Job(JobTransition::new(from, to)))
+}
+
+#[test]
+#[cfg(unix)]
+fn updates_are_streamed_as_json_lines() {
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixListener;
+
+    let path = env::temp_dir().join(format!("factotum-events-{}.sock", ::uuid::Uuid::new_v4()));
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+    let handle = connect_event_socket(path.to_str().unwrap().to_string(),
+                                      JobContext::new("hello", "world", None),
+                                      rx);
+    tx.send(make_update(None, ExecutionState::Started)).unwrap();
+    tx.send(make_update(Some(ExecutionState::Running), ExecutionState::Finished)).unwrap();
+
+    let (stream, _) = listener.accept().unwrap();
+    let lines = BufReader::new(stream).lines().map(|l| l.unwrap()).collect::<Vec<String>>();
+    fs::remove_file(&path).ok();
+
+    assert_eq!(handle.join().unwrap(),
+               EventSocketResult {
+                   events_received: 2,
+                   events_sent: 2,
+               });
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains("\"runState\":\"SUCCEEDED\""));
+}
+
+#[test]
+fn a_missing_socket_doesnt_stop_the_updates() {
+    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+    let handle = connect_event_socket("/nonexistent/factotum.sock".to_string(),
+                                      JobContext::new("hello", "world", None),
+                                      rx);
+    tx.send(make_update(None, ExecutionState::Started)).unwrap();
+    tx.send(make_update(Some(ExecutionState::Running), ExecutionState::Finished)).unwrap();
+
+    assert_eq!(handle.join().unwrap(),
+               EventSocketResult {
+                   events_received: 2,
+                   events_sent: 0,
+               });
+}
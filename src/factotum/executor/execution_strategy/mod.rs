@@ -16,14 +16,38 @@
 mod tests;
 use std::process::Command;
 use std::time::{Instant, Duration};
+use std::str;
+use std::collections::BTreeMap;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Default)]
 pub struct RunResult {
     pub duration: Duration,
     pub task_execution_error: Option<String>,
     pub stdout: Option<String>,
     pub stderr: Option<String>,
     pub return_code: i32,
+    pub host: Option<String>,
+    pub raw_output: Option<RawOutput>,
+    // the values the task wrote for its declared outputs
+    pub outputs: BTreeMap<String, String>,
+}
+
+impl RunResult {
+    // sizes of the output as it's kept, so after any --max-local-output-size truncation
+    pub fn stdout_bytes(&self) -> usize {
+        self.stdout.as_ref().map_or(0, |o| o.len())
+    }
+
+    pub fn stderr_bytes(&self) -> usize {
+        self.stderr.as_ref().map_or(0, |e| e.len())
+    }
+}
+
+// only kept when the output isn't valid UTF-8, so it can be decoded as the task declares
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RawOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
 }
 
 pub fn simulation_text(name: &str, command: &Command) -> String {
@@ -59,6 +83,9 @@ pub fn execute_simulation(name: &str, command: &mut Command) -> RunResult {
         stdout: Some(simulation_text(name, &command)),
         stderr: None,
         return_code: 0,
+        host: None,
+        raw_output: None,
+        outputs: BTreeMap::new(),
     }
 }
 
@@ -73,6 +100,16 @@ pub fn execute_os(name: &str, command: &mut Command) -> RunResult {
             let task_stdout: String = String::from_utf8_lossy(&r.stdout).trim_right().into();
             let task_stderr: String = String::from_utf8_lossy(&r.stderr).trim_right().into();
 
+            let raw_output = if str::from_utf8(&r.stdout).is_ok() &&
+                                str::from_utf8(&r.stderr).is_ok() {
+                None
+            } else {
+                Some(RawOutput {
+                    stdout: r.stdout,
+                    stderr: r.stderr,
+                })
+            };
+
             info!("task '{}' stdout:\n'{}'", name, task_stdout);
             info!("task '{}' stderr:\n'{}'", name, task_stderr);
 
@@ -93,6 +130,9 @@ pub fn execute_os(name: &str, command: &mut Command) -> RunResult {
                 stdout: task_stdout_opt,
                 stderr: task_stderr_opt,
                 return_code: return_code,
+                host: None,
+                raw_output: raw_output,
+                outputs: BTreeMap::new(),
             }
         }
         Err(message) => {
@@ -102,6 +142,9 @@ pub fn execute_os(name: &str, command: &mut Command) -> RunResult {
                 stdout: None,
                 stderr: None,
                 return_code: -1,
+                host: None,
+                raw_output: None,
+                outputs: BTreeMap::new(),
             }
         }
     }
@@ -133,3 +133,39 @@ fn os_execution_good() {
     assert_eq!(result.stdout.unwrap(), "echo is a shell builtin");
     assert_eq!(result.task_execution_error, None);
 }
+
+#[test]
+fn os_execution_keeps_non_utf8_output() {
+    let mut command: Command = Command::new("sh");
+    command.arg("-c");
+    command.arg("printf 'caf\\351'; exit 3");
+    let result = execute_os("hello-world", &mut command);
+
+    assert_eq!(result.return_code, 3);
+    assert_eq!(result.stdout.unwrap(), "caf\u{FFFD}");
+    let raw_output = result.raw_output.unwrap();
+    assert_eq!(raw_output.stdout, vec![b'c', b'a', b'f', 0xe9]);
+    assert!(raw_output.stderr.is_empty());
+}
+
+#[test]
+fn os_execution_drops_raw_utf8_output() {
+    let mut command: Command = Command::new("sh");
+    command.arg("-c");
+    command.arg("printf 'café'");
+    let result = execute_os("hello-world", &mut command);
+
+    assert_eq!(result.stdout.unwrap(), "café");
+    assert_eq!(result.raw_output, None);
+}
+
+#[test]
+fn run_result_output_sizes_are_in_bytes() {
+    let result = RunResult {
+        stdout: Some("café".to_string()),
+        stderr: None,
+        ..Default::default()
+    };
+    assert_eq!(result.stdout_bytes(), 5);
+    assert_eq!(result.stderr_bytes(), 0);
+}
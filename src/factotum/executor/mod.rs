@@ -19,12 +19,27 @@ mod tests;
 
 use factotum::executor::task_list::*;
 use factotum::executor::execution_strategy::*;
-use chrono::UTC;
+use chrono::{DateTime, UTC};
 use factotum::factfile::Task as FactfileTask;
-use factotum::factfile::Factfile;
-use std::process::Command;
+use factotum::factfile::{AddressSelection, Factfile, HostSelection, NoopScope, OutputEncoding};
+use factotum::runrecord::StateFile;
+use factotum::webhook::jobupdate::tail_n_chars;
+use std::process::{Command, Stdio};
 use std::thread;
 use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use rand::{self, Rng, SeedableRng, StdRng};
+use rustc_serialize::json::Json;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::net::{IpAddr, ToSocketAddrs};
+use uuid::Uuid;
+use libc;
 
 pub fn get_task_execution_list(factfile: &Factfile,
                                start_from: Option<String>)
@@ -116,6 +131,35 @@ pub enum Transition {
 
 pub type TaskSnapshot = Vec<Task<FactfileTask>>;
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExecutionType {
+    Executed,
+    Resumed,
+    Skipped,
+}
+
+impl ExecutionType {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ExecutionType::Executed => "executed",
+            ExecutionType::Resumed => "resumed",
+            ExecutionType::Skipped => "skipped",
+        }
+    }
+}
+
+// tasks that haven't finished yet don't have an execution type
+pub fn get_execution_type<T>(task: &Task<T>) -> Option<ExecutionType> {
+    match (&task.state, &task.run_result) {
+        (_, &Some(_)) |
+        (&State::Failed(_), &None) => Some(ExecutionType::Executed),
+        // only tasks completed in a previous run succeed without running
+        (&State::Success, &None) => Some(ExecutionType::Resumed),
+        (&State::Skipped(_), &None) => Some(ExecutionType::Skipped),
+        _ => None,
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum ExecutionState {
     Started,
@@ -123,11 +167,21 @@ pub enum ExecutionState {
     Finished,
 }
 
+// the updates that open and close a run, as distinct from every other job transition
+#[derive(Debug, PartialEq, Clone)]
+pub enum JobEvent {
+    Started,
+    Finished,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct ExecutionUpdate {
     pub execution_state: ExecutionState,
     pub task_snapshot: TaskSnapshot,
     pub transition: Transition,
+    pub run_label: Option<String>,
+    pub execution_types: BTreeMap<String, ExecutionType>,
+    pub duration_alerts: BTreeMap<String, f64>,
 }
 
 impl ExecutionUpdate {
@@ -135,14 +189,61 @@ impl ExecutionUpdate {
                task_snapshot: TaskSnapshot,
                transition: Transition)
                -> Self {
+        let execution_types = task_snapshot.iter()
+            .filter_map(|task| get_execution_type(task).map(|t| (task.name.clone(), t)))
+            .collect();
         ExecutionUpdate {
             execution_state: execution_state,
             task_snapshot: task_snapshot,
             transition: transition,
+            run_label: None,
+            execution_types: execution_types,
+            duration_alerts: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_run_label(mut self, run_label: &Option<String>) -> Self {
+        self.run_label = run_label.clone();
+        self
+    }
+
+    pub fn with_duration_alerts(mut self, alert_percent: f64) -> Self {
+        self.duration_alerts = get_duration_alerts(&self.task_snapshot, alert_percent);
+        self
+    }
+
+    pub fn get_job_event(&self) -> Option<JobEvent> {
+        match self.transition {
+            Transition::Job(ref j) if j.from.is_none() && j.to == ExecutionState::Started => {
+                Some(JobEvent::Started)
+            }
+            Transition::Job(ref j) if j.to == ExecutionState::Finished => Some(JobEvent::Finished),
+            _ => None,
         }
     }
 }
 
+// how much longer (or, when negative, shorter) than expected a task took, as a percentage
+pub fn get_duration_variance(expected_seconds: u64, actual: &Duration) -> f64 {
+    let actual_seconds = actual.as_secs() as f64 + actual.subsec_nanos() as f64 / 1e9;
+    (actual_seconds - expected_seconds as f64) / expected_seconds as f64 * 100.0
+}
+
+// the tasks that ran further from their expectedDurationSeconds than the alert percentage
+pub fn get_duration_alerts(tasks: &TaskSnapshot, alert_percent: f64) -> BTreeMap<String, f64> {
+    tasks.iter()
+        .filter_map(|task| {
+            match (task.task_spec.expected_duration_seconds, &task.run_result) {
+                (Some(expected), &Some(ref result)) => {
+                    Some((task.name.clone(), get_duration_variance(expected, &result.duration)))
+                }
+                _ => None,
+            }
+        })
+        .filter(|&(_, variance)| variance.abs() > alert_percent)
+        .collect()
+}
+
 pub fn get_task_snapshot(tasklist: &TaskList<&FactfileTask>) -> TaskSnapshot {
     tasklist.tasks
         .iter()
@@ -160,6 +261,314 @@ pub fn get_task_snapshot(tasklist: &TaskList<&FactfileTask>) -> TaskSnapshot {
         .collect()
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExecutionOptions {
+    pub completed_tasks: Vec<String>,
+    pub state_file: Option<StateFile>,
+    pub shuffle_seed: Option<usize>,
+    pub abort_file: Option<String>,
+    pub workdir: Option<String>,
+    pub run_label: Option<String>,
+    pub no_skip_downstream: bool,
+    pub max_local_output_size: Option<usize>,
+    pub task_approval: Option<TaskApprover>,
+    pub ignore_not_before: bool,
+    pub tag_limits: HashMap<String, usize>,
+    pub duration_alert_percent: f64,
+    pub skip_tasks: Vec<String>,
+    pub poll_interval: Duration,
+    pub tail_task: Option<String>,
+    pub verbose: bool,
+    pub secret_values: Vec<String>,
+    pub jitter_seed: Option<usize>,
+    pub max_executions: Option<usize>,
+}
+
+impl Default for ExecutionOptions {
+    fn default() -> Self {
+        ExecutionOptions {
+            completed_tasks: vec![],
+            state_file: None,
+            shuffle_seed: None,
+            abort_file: None,
+            workdir: None,
+            run_label: None,
+            no_skip_downstream: false,
+            max_local_output_size: None,
+            task_approval: None,
+            ignore_not_before: false,
+            tag_limits: HashMap::new(),
+            duration_alert_percent: DEFAULT_DURATION_ALERT_PERCENT,
+            skip_tasks: vec![],
+            poll_interval: Duration::from_millis(DEFAULT_POLL_INTERVAL_MS),
+            tail_task: None,
+            verbose: false,
+            secret_values: vec![],
+            jitter_seed: None,
+            max_executions: None,
+        }
+    }
+}
+
+// shared by every task in a run, so retries count towards the limit as well as first attempts
+#[derive(Debug, Clone)]
+pub struct ExecutionBudget {
+    limit: usize,
+    used: Arc<AtomicUsize>,
+    denied: Arc<AtomicBool>,
+}
+
+impl ExecutionBudget {
+    pub fn new(limit: usize) -> Self {
+        ExecutionBudget {
+            limit: limit,
+            used: Arc::new(AtomicUsize::new(0)),
+            denied: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // false (and nothing taken) once the limit has been reached
+    pub fn try_take(&self) -> bool {
+        loop {
+            let used = self.used.load(Ordering::SeqCst);
+            if used >= self.limit {
+                self.denied.store(true, Ordering::SeqCst);
+                return false;
+            }
+            if self.used
+                .compare_exchange(used, used + 1, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok() {
+                return true;
+            }
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.used.load(Ordering::SeqCst) >= self.limit
+    }
+
+    // whether a task or a retry didn't run because of the limit
+    pub fn was_denied(&self) -> bool {
+        self.denied.load(Ordering::SeqCst)
+    }
+}
+
+impl PartialEq for ExecutionBudget {
+    fn eq(&self, other: &ExecutionBudget) -> bool {
+        self.limit == other.limit &&
+        self.used.load(Ordering::SeqCst) == other.used.load(Ordering::SeqCst)
+    }
+}
+
+// a limit is on a tag ("resource"), or a tag with a particular value ("resource:db")
+pub fn get_matching_tag_limits(tags: &BTreeMap<String, String>,
+                               tag_limits: &HashMap<String, usize>)
+                               -> Vec<String> {
+    tag_limits.keys()
+        .filter(|limit| match limit.find(':') {
+            Some(idx) => {
+                tags.get(&limit[..idx]).map_or(false, |value| value == &limit[idx + 1..])
+            }
+            None => tags.contains_key(limit.as_str()),
+        })
+        .cloned()
+        .collect()
+}
+
+#[derive(Default)]
+struct LimiterState {
+    running: HashMap<String, usize>,
+    held_mutexes: HashSet<String>,
+}
+
+// while it runs, a task holds a slot in every limit that matches its tags, and its mutex
+struct TagLimiter {
+    limits: HashMap<String, usize>,
+    state: Mutex<LimiterState>,
+    slot_freed: Condvar,
+}
+
+// the slots go back even if the task's thread panics, or the tasks waiting on them never start
+struct TagLimiterGuard {
+    limiter: Arc<TagLimiter>,
+    limits: Vec<String>,
+    mutex: Option<String>,
+}
+
+impl Drop for TagLimiterGuard {
+    fn drop(&mut self) {
+        self.limiter.release(&self.limits, &self.mutex);
+    }
+}
+
+impl TagLimiter {
+    fn new(limits: HashMap<String, usize>) -> Self {
+        TagLimiter {
+            limits: limits,
+            state: Mutex::new(LimiterState::default()),
+            slot_freed: Condvar::new(),
+        }
+    }
+
+    // all the slots are taken at once, so tasks with overlapping tags can't deadlock
+    fn acquire(limiter: &Arc<TagLimiter>,
+               limits: Vec<String>,
+               mutex: Option<String>)
+               -> TagLimiterGuard {
+        limiter.take(&limits, &mutex);
+        TagLimiterGuard {
+            limiter: limiter.clone(),
+            limits: limits,
+            mutex: mutex,
+        }
+    }
+
+    fn take(&self, limits: &Vec<String>, mutex: &Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        while self.is_busy(&state, limits, mutex) {
+            state = self.slot_freed.wait(state).unwrap();
+        }
+        for limit in limits.iter() {
+            *state.running.entry(limit.clone()).or_insert(0) += 1;
+        }
+        if let Some(ref m) = *mutex {
+            state.held_mutexes.insert(m.clone());
+        }
+    }
+
+    fn is_busy(&self, state: &LimiterState, limits: &Vec<String>, mutex: &Option<String>) -> bool {
+        limits.iter().any(|l| state.running.get(l).cloned().unwrap_or(0) >= self.limits[l]) ||
+        mutex.as_ref().map_or(false, |m| state.held_mutexes.contains(m))
+    }
+
+    fn release(&self, limits: &Vec<String>, mutex: &Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        for limit in limits.iter() {
+            if let Some(count) = state.running.get_mut(limit) {
+                *count -= 1;
+            }
+        }
+        if let Some(ref m) = *mutex {
+            state.held_mutexes.remove(m);
+        }
+        self.slot_freed.notify_all();
+    }
+}
+
+pub fn get_task_start_order(task_count: usize, shuffle_rng: Option<&mut StdRng>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..task_count).collect();
+    if let Some(rng) = shuffle_rng {
+        rng.shuffle(&mut order);
+    }
+    order
+}
+
+// keeps the end of the output, as that's usually where the errors are
+pub fn truncate_run_output(result: &mut RunResult, max_size: usize) {
+    result.stdout = result.stdout.as_ref().map(|o| tail_n_chars(o, max_size).to_string());
+    result.stderr = result.stderr.as_ref().map(|e| tail_n_chars(e, max_size).to_string());
+}
+
+// output that isn't valid UTF-8 is shown with replacement characters unless the task declares
+// its encoding - either way the raw bytes aren't kept past this, as they're never truncated
+pub fn decode_run_output(result: &mut RunResult, encoding: &OutputEncoding) {
+    let raw_output = result.raw_output.take();
+    if *encoding != OutputEncoding::Latin1 {
+        return;
+    }
+    if let Some(raw) = raw_output {
+        result.stdout = decode_latin1(&raw.stdout);
+        result.stderr = decode_latin1(&raw.stderr);
+    }
+}
+
+fn decode_latin1(bytes: &[u8]) -> Option<String> {
+    let decoded = bytes.iter().map(|&b| b as char).collect::<String>();
+    let trimmed = decoded.trim_right();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
+    }
+}
+
+pub const ABORTED_MESSAGE: &'static str = "the job was aborted";
+pub const OPERATOR_ABORTED_MESSAGE: &'static str = "the job was aborted (by the operator)";
+pub const OPERATOR_SKIP_MESSAGE: &'static str = "operator skip";
+pub const CLI_SKIP_MESSAGE: &'static str = "cli skip";
+pub const PRECONDITION_FAILED_MESSAGE: &'static str = "precondition failed";
+pub const UNFINISHED_TASK_MESSAGE: &'static str = "internal error - the task never reached a \
+                                                   final state";
+pub const MAX_EXECUTIONS_MESSAGE: &'static str = "the job reached its maximum number of task \
+                                                  executions";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TaskApproval {
+    Run,
+    Skip,
+    Abort,
+}
+
+// asked before each task starts, with the task name and the command it will run
+pub type TaskApprover = fn(String, String) -> TaskApproval;
+
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 500;
+pub const DEFAULT_DURATION_ALERT_PERCENT: f64 = 50.0;
+
+// sets `aborted` as soon as the file at `path` exists, and gives up once `finished` is set
+// finished tasks report to the scheduler through a channel - the abort file is the only thing
+// polled
+fn watch_abort_file(path: String,
+                    poll_interval: Duration,
+                    aborted: Arc<AtomicBool>,
+                    finished: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        while !finished.load(Ordering::SeqCst) {
+            if Path::new(&path).exists() {
+                warn!("the abort file '{}' exists, no further tasks will be started", path);
+                aborted.store(true, Ordering::SeqCst);
+                return;
+            }
+            thread::sleep(poll_interval);
+        }
+    });
+}
+
+// job updates always go out, but task updates only for the tasks that want notifications
+fn send_task_transitions(send: &mpsc::Sender<ExecutionUpdate>,
+                         tasklist: &TaskList<&FactfileTask>,
+                         transitions: TaskTransitions,
+                         options: &ExecutionOptions) {
+    let notify = tasklist.tasks
+        .iter()
+        .flat_map(|tg| tg.iter())
+        .filter(|t| t.task_spec.notify)
+        .map(|t| t.name.clone())
+        .collect::<Vec<String>>();
+    let transitions = transitions.into_iter()
+        .filter(|t| notify.contains(&t.task_name))
+        .collect::<TaskTransitions>();
+    if transitions.is_empty() {
+        return;
+    }
+
+    let update = ExecutionUpdate::new(ExecutionState::Running,
+                                      get_task_snapshot(tasklist),
+                                      Transition::Task(transitions));
+    send.send(update.with_run_label(&options.run_label)
+            .with_duration_alerts(options.duration_alert_percent))
+        .unwrap();
+}
+
+fn update_state_file(options: &ExecutionOptions,
+                     factfile: &Factfile,
+                     tasklist: &TaskList<&FactfileTask>) {
+    if let Some(ref state_file) = options.state_file {
+        let tasks = tasklist.tasks.iter().flat_map(|tg| tg.iter()).collect();
+        state_file.update(&factfile.raw, &tasks);
+    }
+}
+
 pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
                                start_from: Option<String>,
                                strategy: F,
@@ -167,9 +576,34 @@ pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
                                -> TaskList<&'a FactfileTask>
     where F: Fn(&str, &mut Command) -> RunResult + Send + Sync + 'static + Copy
 {
+    execute_factfile_with_options(factfile,
+                                  start_from,
+                                  strategy,
+                                  progress_channel,
+                                  ExecutionOptions::default())
+}
+
+pub fn execute_factfile_with_options<'a, F>(factfile: &'a Factfile,
+                                            start_from: Option<String>,
+                                            strategy: F,
+                                            progress_channel: Option<mpsc::Sender<ExecutionUpdate>>,
+                                            options: ExecutionOptions)
+                                            -> TaskList<&'a FactfileTask>
+    where F: Fn(&str, &mut Command) -> RunResult + Send + Sync + 'static + Copy
+{
 
     let mut tasklist = get_task_execution_list(factfile, start_from);
 
+    // tasks that completed in a previous run are marked as successful up front, so they're not re-run
+    for completed in options.completed_tasks.iter() {
+        if let Some(task) = tasklist.get_task_by_name(completed) {
+            info!("Task '{}' completed in a previous run, it will not be re-run", completed);
+            task.state = State::Success;
+        }
+    }
+
+    update_state_file(&options, factfile, &tasklist);
+
     // notify the progress channel
     if let Some(ref send) = progress_channel {
         let update =
@@ -177,32 +611,268 @@ pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
                                  get_task_snapshot(&tasklist),
                                  Transition::Job(JobTransition::new(None,
                                                                     ExecutionState::Started)));
-        send.send(update).unwrap();
+        send.send(update.with_run_label(&options.run_label)).unwrap();
     }
 
+    let mut next_hosts = HashMap::new();
+
+    // with no_skip_downstream, the failed upstream tasks of every task that runs anyway
+    let mut failed_upstream: HashMap<String, Vec<String>> = HashMap::new();
+
+    let mut shuffle_rng: Option<StdRng> = options.shuffle_seed.map(|seed| {
+        info!("Independent tasks will be started in a random order (seed {})", seed);
+        SeedableRng::from_seed(&[seed][..])
+    });
+
+    let aborted = Arc::new(AtomicBool::new(false));
+    let finished = Arc::new(AtomicBool::new(false));
+    if let Some(ref abort_file) = options.abort_file {
+        info!("Watching for the abort file '{}'", abort_file);
+        watch_abort_file(abort_file.clone(),
+                         options.poll_interval,
+                         aborted.clone(),
+                         finished.clone());
+    }
+
+    let mut operator_aborted = false;
+
+    let tag_limiter = Arc::new(TagLimiter::new(options.tag_limits.clone()));
+
+    let execution_budget = options.max_executions.map(ExecutionBudget::new);
+    let budget_exhausted = || execution_budget.as_ref().map_or(false, |b| b.is_exhausted());
+
     for task_grp_idx in 0..tasklist.tasks.len() {
+        if aborted.load(Ordering::SeqCst) || operator_aborted || budget_exhausted() {
+            // tasks that are already running have finished, everything still waiting is skipped
+            let skip_message = if operator_aborted {
+                OPERATOR_ABORTED_MESSAGE.to_string()
+            } else if !aborted.load(Ordering::SeqCst) {
+                MAX_EXECUTIONS_MESSAGE.to_string()
+            } else {
+                tasklist.stopped_by = Some(StopReason::AbortFile);
+                format!("{} (the abort file '{}' appeared)",
+                        ABORTED_MESSAGE,
+                        options.abort_file.as_ref().unwrap())
+            };
+            let mut abort_transitions = vec![];
+            for task in tasklist.tasks.iter_mut().flat_map(|tg| tg.iter_mut()) {
+                if task.state == State::Waiting {
+                    task.state = State::Skipped(skip_message.clone());
+                    abort_transitions.push(TaskTransition::new(&task.name,
+                                                               State::Waiting,
+                                                               task.state.clone()));
+                }
+            }
+
+            update_state_file(&options, factfile, &tasklist);
+
+            if let Some(ref send) = progress_channel {
+                send_task_transitions(send, &tasklist, abort_transitions, &options);
+            }
+            break;
+        }
+
         // everything in a task "group" gets run together
-        let (tx, rx) = mpsc::channel::<(usize, RunResult)>();
+        let (tx, rx) = mpsc::channel::<(usize, RunResult, Option<String>, DateTime<UTC>)>();
+
+        let upstream_outputs = tasklist.tasks
+            .iter()
+            .flat_map(|tg| tg.iter())
+            .filter_map(|t| t.run_result.as_ref().map(|r| (t.name.clone(), r.outputs.clone())))
+            .filter(|&(_, ref outputs)| !outputs.is_empty())
+            .collect::<HashMap<String, BTreeMap<String, String>>>();
+
+        // tasks skipped before they started, with why their dependents are skipped too
+        let mut skipped_before_start = vec![];
+        let mut pre_start_transitions = vec![];
 
         {
             let ref mut task_group = tasklist.tasks[task_grp_idx];
-            for (idx, task) in task_group.into_iter().enumerate() {
+            let start_order = get_task_start_order(task_group.len(), shuffle_rng.as_mut());
+            for idx in start_order {
+                let ref mut task = task_group[idx];
 
-                if task.state == State::Waiting {
+                if task.state == State::Waiting && operator_aborted {
+                    task.state = State::Skipped(OPERATOR_ABORTED_MESSAGE.to_string());
+                    pre_start_transitions.push(TaskTransition::new(&task.name,
+                                                                   State::Waiting,
+                                                                   task.state.clone()));
+                } else if task.state == State::Waiting && options.skip_tasks.contains(&task.name) {
+                    info!("Task '{}' was skipped from the command line", task.name);
+                    task.state = State::Skipped(CLI_SKIP_MESSAGE.to_string());
+                    skipped_before_start.push((task.name.clone(),
+                                               "was skipped from the command line"));
+                    pre_start_transitions.push(TaskTransition::new(&task.name,
+                                                                   State::Waiting,
+                                                                   task.state.clone()));
+                } else if task.state == State::Waiting {
+                    let host = select_host(task.task_spec, &mut next_hosts);
+                    let mut command = get_task_command(task.task_spec, &host);
+                    if options.tail_task.as_ref() == Some(&task.name) {
+                        // followed live on the console, so it isn't captured for the summary
+                        command.stdout(Stdio::inherit());
+                        command.stderr(Stdio::inherit());
+                    }
+                    let mut task_env = get_task_inputs(task.task_spec, &upstream_outputs);
+                    let outputs_file = if task.task_spec.outputs.is_empty() {
+                        None
+                    } else {
+                        let path = env::temp_dir()
+                            .join(format!("factotum-outputs-{}.env", Uuid::new_v4()));
+                        task_env.insert(OUTPUTS_FILE_ENV_VAR.to_string(),
+                                        path.display().to_string());
+                        Some(path)
+                    };
+                    for (name, value) in task_env.iter() {
+                        command.env(name, value);
+                    }
+                    // tasks on remote hosts run in the ssh login directory, while the hooks run
+                    // locally in the same directory as a local task
+                    let working_dir = if host.is_none() {
+                        get_task_working_dir(task.task_spec, &options.workdir)
+                    } else {
+                        None
+                    };
+                    if let Some(ref dir) = working_dir {
+                        command.current_dir(dir);
+                    }
+
+                    let approval = match options.task_approval {
+                        Some(approve) => approve(task.name.clone(), format!("{:?}", command)),
+                        None => TaskApproval::Run,
+                    };
+
+                    if approval != TaskApproval::Run {
+                        task.state = if approval == TaskApproval::Skip {
+                            info!("Task '{}' was skipped by the operator", task.name);
+                            skipped_before_start.push((task.name.clone(),
+                                                       "was skipped by the operator"));
+                            State::Skipped(OPERATOR_SKIP_MESSAGE.to_string())
+                        } else {
+                            warn!("The job was aborted by the operator at task '{}'", task.name);
+                            operator_aborted = true;
+                            State::Skipped(OPERATOR_ABORTED_MESSAGE.to_string())
+                        };
+                        pre_start_transitions.push(TaskTransition::new(&task.name,
+                                                                       State::Waiting,
+                                                                       task.state.clone()));
+                        continue;
+                    }
+
+                    if let Some(ref precondition) = task.task_spec.precondition {
+                        // preconditions are meant to be quick checks, so they hold up the
+                        // tasks that start after this one
+                        if !run_task_hook(&task.name,
+                                          "precondition",
+                                          precondition,
+                                          &working_dir,
+                                          strategy) {
+                            info!("Task '{}' was skipped as its precondition failed", task.name);
+                            task.state = State::Skipped(PRECONDITION_FAILED_MESSAGE.to_string());
+                            skipped_before_start.push((task.name.clone(),
+                                                       "was skipped as its precondition failed"));
+                            pre_start_transitions.push(TaskTransition::new(&task.name,
+                                                                           State::Waiting,
+                                                                           task.state.clone()));
+                            continue;
+                        }
+                    }
+
+                    if !execution_budget.as_ref().map_or(true, |b| b.try_take()) {
+                        warn!("Task '{}' wasn't started as {}", task.name, MAX_EXECUTIONS_MESSAGE);
+                        task.state = State::Skipped(MAX_EXECUTIONS_MESSAGE.to_string());
+                        pre_start_transitions.push(TaskTransition::new(&task.name,
+                                                                       State::Waiting,
+                                                                       task.state.clone()));
+                        continue;
+                    }
+
+                    if let Some(failed) = failed_upstream.get(&task.name) {
+                        warn!("Running task '{}' even though upstream task(s) {} failed",
+                              task.name,
+                              failed.join(", "));
+                    }
                     info!("Running task '{}'!", task.name);
+                    if options.verbose {
+                        print!("{}",
+                               get_task_launch_details(&task.name,
+                                                       &command,
+                                                       &get_task_env(task.task_spec, &task_env),
+                                                       &options.secret_values));
+                    }
                     task.state = State::Running;
-                    task.run_started = Some(UTC::now());
+                    let now = UTC::now();
+                    let not_before = if options.ignore_not_before {
+                        None
+                    } else {
+                        task.task_spec.not_before
+                    };
+                    task.run_started = match not_before {
+                        Some(time) if time > now => Some(time),
+                        _ => Some(now),
+                    };
                     {
                         let tx = tx.clone();
-                        let args = format_args(&task.task_spec.command, &task.task_spec.arguments);
                         let task_name = task.name.to_string();
+                        let retry_policy = RetryPolicy {
+                            jitter_seed: options.jitter_seed,
+                            execution_budget: execution_budget.clone(),
+                            ..RetryPolicy::from_task(task.task_spec)
+                        };
+                        let run_as = get_run_as_description(task.task_spec);
+                        let on_success = task.task_spec.on_success.clone();
+                        let on_failure = task.task_spec.on_failure.clone();
+                        // so a cleanup can use the same relative paths as the task
+                        let hook_dir = working_dir.clone();
+                        let limiter = tag_limiter.clone();
+                        let limited_by = get_matching_tag_limits(&task.task_spec.tags,
+                                                                 &options.tag_limits);
+                        let mutex = task.task_spec.mutex.clone();
+                        let declared_outputs = task.task_spec.outputs.clone();
 
                         thread::spawn(move || {
-                            let mut command = Command::new("sh");
-                            command.arg("-c");
-                            command.arg(args);
-                            let task_result = strategy(&task_name, &mut command);
-                            tx.send((idx, task_result)).unwrap();
+                            let wait = not_before.and_then(|t| (t - UTC::now()).to_std().ok());
+                            if let Some(wait) = wait {
+                                info!("Task '{}' is waiting {:?} for its notBefore time",
+                                      task_name,
+                                      wait);
+                                thread::sleep(wait);
+                            }
+                            let slots = TagLimiter::acquire(&limiter, limited_by, mutex);
+                            // a task held back by a limit or mutex only starts now
+                            let started = UTC::now();
+                            let (mut task_result, retry_note) =
+                                run_with_retries(&task_name, &mut command, strategy, &retry_policy);
+                            task_result.host = host;
+                            if let Some(path) = outputs_file {
+                                task_result.outputs =
+                                    read_task_outputs(&task_name, &path, &declared_outputs);
+                                fs::remove_file(&path).ok();
+                            }
+                            if let (Some(err), Some(who)) = (task_result.task_execution_error
+                                                                 .clone(),
+                                                             run_as) {
+                                // most likely we weren't allowed to switch to the requested identity
+                                task_result.task_execution_error =
+                                    Some(format!("{} (while starting the task as {})", err, who));
+                            }
+                            let succeeded = task_result.task_execution_error.is_none() &&
+                                            retry_policy.accepted_codes
+                                .contains(&task_result.return_code);
+                            let hook = if succeeded {
+                                on_success.map(|h| ("onSuccess", h))
+                            } else {
+                                on_failure.map(|h| ("onFailure", h))
+                            };
+                            if let Some((hook_name, hook_command)) = hook {
+                                run_task_hook(&task_name,
+                                              hook_name,
+                                              &hook_command,
+                                              &hook_dir,
+                                              strategy);
+                            }
+                            drop(slots);
+                            tx.send((idx, task_result, retry_note, started)).unwrap();
                         });
                     }
                 } else {
@@ -212,6 +882,31 @@ pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
             }
         }
 
+        for &(ref skipped_name, reason) in skipped_before_start.iter() {
+            let skip_list = tasklist.get_descendants(skipped_name);
+            for task in tasklist.tasks.iter_mut().flat_map(|tg| tg.iter_mut()) {
+                if skip_list.contains(&task.name) {
+                    let skip_message = if let State::Skipped(ref msg) = task.state {
+                        format!("{}, the task '{}' {}", msg, skipped_name, reason)
+                    } else {
+                        format!("the task '{}' {}", skipped_name, reason)
+                    };
+                    let prev_state = task.state.clone();
+                    task.state = State::Skipped(skip_message);
+                    pre_start_transitions.push(TaskTransition::new(&task.name,
+                                                                   prev_state,
+                                                                   task.state.clone()));
+                }
+            }
+        }
+
+        if !pre_start_transitions.is_empty() {
+            update_state_file(&options, factfile, &tasklist);
+            if let Some(ref send) = progress_channel {
+                send_task_transitions(send, &tasklist, pre_start_transitions, &options);
+            }
+        }
+
         let expected_count = tasklist.tasks[task_grp_idx]
             .iter()
             .filter(|t| t.state == State::Running)
@@ -224,12 +919,14 @@ pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
                 let update = ExecutionUpdate::new(ExecutionState::Running, 
                                           get_task_snapshot(&tasklist),
                                           Transition::Job( JobTransition::new(Some(ExecutionState::Started), ExecutionState::Running) ));
-                send.send(update).unwrap();
+                send.send(update.with_run_label(&options.run_label)).unwrap();
             }
         }
 
         if expected_count > 0 {
 
+            update_state_file(&options, factfile, &tasklist);
+
             if let Some(ref send) = progress_channel {
                 let running_task_transitions = tasklist.tasks[task_grp_idx]
                     .iter()
@@ -241,21 +938,42 @@ pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
                     })
                     .collect::<Vec<TaskTransition>>();
 
-                let update = ExecutionUpdate::new(ExecutionState::Running,
-                                                  get_task_snapshot(&tasklist),
-                                                  Transition::Task(running_task_transitions));
-
-                send.send(update).unwrap();
+                send_task_transitions(send,
+                                      &tasklist,
+                                      running_task_transitions,
+                                      &options);
             }
 
             for _ in 0..expected_count {
-                let (idx, task_result) = rx.recv().unwrap();
+                let (idx, mut task_result, retry_note, started) = rx.recv().unwrap();
+                tasklist.tasks[task_grp_idx][idx].run_started = Some(started);
+
+                decode_run_output(&mut task_result,
+                                  &tasklist.tasks[task_grp_idx][idx].task_spec.output_encoding);
+
+                if let Some(max_size) = options.max_local_output_size {
+                    truncate_run_output(&mut task_result, max_size);
+                }
 
                 info!("'{}' returned {} in {:?}",
                       tasklist.tasks[task_grp_idx][idx].name,
                       task_result.return_code,
                       task_result.duration);
 
+                if let Some(expected) = tasklist.tasks[task_grp_idx][idx]
+                    .task_spec
+                    .expected_duration_seconds {
+                    let variance = get_duration_variance(expected, &task_result.duration);
+                    if variance.abs() > options.duration_alert_percent {
+                        warn!("'{}' took {:?}, {:.0}% {} than its expected {}s",
+                              tasklist.tasks[task_grp_idx][idx].name,
+                              task_result.duration,
+                              variance.abs(),
+                              if variance > 0.0 { "longer" } else { "shorter" },
+                              expected);
+                    }
+                }
+
                 let mut additional_transitions = vec![];
 
                 if tasklist.tasks[task_grp_idx][idx]
@@ -266,20 +984,34 @@ pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
                     // if the return code is in the terminate early list, prune the sub-tree (set to skipped) return early term
                     tasklist.tasks[task_grp_idx][idx].state = State::SuccessNoop;
 
-                    let skip_list =
-                        tasklist.get_descendants(&tasklist.tasks[task_grp_idx][idx].name);
+                    let job_scope = tasklist.tasks[task_grp_idx][idx].task_spec.noop_scope ==
+                                    NoopScope::Job;
+                    let skip_list = if job_scope {
+                        tasklist.tasks
+                            .iter()
+                            .flat_map(|tg| tg.iter())
+                            .filter(|t| t.state == State::Waiting)
+                            .map(|t| t.name.clone())
+                            .collect()
+                    } else {
+                        tasklist.get_descendants(&tasklist.tasks[task_grp_idx][idx].name)
+                    };
 
                     let cause_task = tasklist.tasks[task_grp_idx][idx].name.clone();
+                    let cause = if job_scope {
+                        format!("the task '{}' requested early termination of the job",
+                                &cause_task)
+                    } else {
+                        format!("the task '{}' requested early termination", &cause_task)
+                    };
 
                     for mut task in tasklist.tasks.iter_mut().flat_map(|tg| tg.iter_mut()) {
                         // all the tasks
                         if skip_list.contains(&task.name) {
                             let skip_message = if let State::Skipped(ref msg) = task.state {
-                                format!("{}, the task '{}' requested early termination",
-                                        msg,
-                                        &cause_task)
+                                format!("{}, {}", msg, cause)
                             } else {
-                                format!("the task '{}' requested early termination", &cause_task)
+                                cause.clone()
                             };
                             let prev_state = task.state.clone();
                             task.state = State::Skipped(skip_message);
@@ -305,36 +1037,56 @@ pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
                         .map(|code| code.to_string())
                         .collect::<Vec<String>>()
                         .join(",");
-                    let err_msg = format!("the task exited with a value not specified in \
-                                           continue_job - {} (task expects one of the following \
-                                           return codes to continue [{}])",
-                                          task_result.return_code,
-                                          expected_codes);
+                    let mut err_msg = format!("the task exited with a value not specified in \
+                                               continue_job - {} (task expects one of the \
+                                               following return codes to continue [{}])",
+                                              task_result.return_code,
+                                              expected_codes);
+                    if let Some(note) = retry_note {
+                        err_msg.push_str(&format!(", {}", note));
+                    }
+                    if let Some(failed) = failed_upstream
+                        .get(&tasklist.tasks[task_grp_idx][idx].name) {
+                        err_msg.push_str(&format!(" (it ran even though upstream task(s) {} \
+                                                   failed)",
+                                                  failed.join(", ")));
+                    }
                     tasklist.tasks[task_grp_idx][idx].state = State::Failed(err_msg);
                     let skip_list =
                         tasklist.get_descendants(&tasklist.tasks[task_grp_idx][idx].name);
 
                     let cause_task = tasklist.tasks[task_grp_idx][idx].name.clone();
 
-                    for mut task in tasklist.tasks.iter_mut().flat_map(|tg| tg.iter_mut()) {
-                        // all the tasks
-                        if skip_list.contains(&task.name) {
-                            let skip_message = if let State::Skipped(ref msg) = task.state {
-                                format!("{}, the task '{}' failed", msg, cause_task)
-                            } else {
-                                format!("the task '{}' failed", cause_task)
-                            };
-                            let prev_state = task.state.clone();
-                            task.state = State::Skipped(skip_message);
-                            let skip_transition =
-                                TaskTransition::new(&task.name, prev_state, task.state.clone());
-                            additional_transitions.push(skip_transition);
+                    if options.no_skip_downstream {
+                        // descendants still run, they just remember why they might not work
+                        for descendant in skip_list.iter() {
+                            failed_upstream.entry(descendant.clone())
+                                .or_insert(vec![])
+                                .push(format!("'{}'", cause_task));
+                        }
+                    } else {
+                        for mut task in tasklist.tasks.iter_mut().flat_map(|tg| tg.iter_mut()) {
+                            // all the tasks
+                            if skip_list.contains(&task.name) {
+                                let skip_message = if let State::Skipped(ref msg) = task.state {
+                                    format!("{}, the task '{}' failed", msg, cause_task)
+                                } else {
+                                    format!("the task '{}' failed", cause_task)
+                                };
+                                let prev_state = task.state.clone();
+                                task.state = State::Skipped(skip_message);
+                                let skip_transition =
+                                    TaskTransition::new(&task.name, prev_state, task.state.clone());
+                                additional_transitions.push(skip_transition);
+                            }
                         }
                     }
                 }
 
                 tasklist.tasks[task_grp_idx][idx].run_result = Some(task_result);
 
+                update_state_file(&options, factfile, &tasklist);
+
                 if let Some(ref send) = progress_channel {
                     let exec_task_transition =
                         TaskTransition::new(&tasklist.tasks[task_grp_idx][idx].name,
@@ -342,26 +1094,503 @@ pub fn execute_factfile<'a, F>(factfile: &'a Factfile,
                                             tasklist.tasks[task_grp_idx][idx].state.clone());
                     additional_transitions.insert(0, exec_task_transition);
 
-                    let update = ExecutionUpdate::new(ExecutionState::Running,
-                                                      get_task_snapshot(&tasklist),
-                                                      Transition::Task(additional_transitions));
-                    send.send(update).unwrap();
+                    send_task_transitions(send,
+                                          &tasklist,
+                                          additional_transitions,
+                                          &options);
                 }
 
             }
         }
     }
 
+    finished.store(true, Ordering::SeqCst);
+
+    if operator_aborted {
+        tasklist.stopped_by = Some(StopReason::Operator);
+    }
+    if execution_budget.as_ref().map_or(false, |b| b.was_denied()) {
+        tasklist.stopped_by = Some(StopReason::MaxExecutions);
+    }
+
+    fail_unfinished_tasks(&mut tasklist);
+
     if let Some(ref send) = progress_channel {
         let update = ExecutionUpdate::new(ExecutionState::Finished, 
                                           get_task_snapshot(&tasklist),
                                           Transition::Job( JobTransition::new(Some(ExecutionState::Running), ExecutionState::Finished) ));
-        send.send(update).unwrap();
+        send.send(update.with_run_label(&options.run_label)
+                .with_duration_alerts(options.duration_alert_percent))
+            .unwrap();
     }
 
     tasklist
 }
 
+// every task should have run or been skipped by now - anything else is a scheduler bug, and is
+// failed so the run can't look like it succeeded
+pub fn fail_unfinished_tasks<T>(tasklist: &mut TaskList<T>) -> Vec<String> {
+    let mut unfinished = vec![];
+    for task in tasklist.tasks.iter_mut().flat_map(|tg| tg.iter_mut()) {
+        if task.state == State::Waiting || task.state == State::Running {
+            task.state = State::Failed(format!("{} (it was still {:?})",
+                                               UNFINISHED_TASK_MESSAGE,
+                                               task.state));
+            unfinished.push(task.name.clone());
+        }
+    }
+    if !unfinished.is_empty() {
+        error!("The job ended with tasks that never reached a final state: {}",
+               unfinished.join(", "));
+    }
+    unfinished
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RetryPolicy {
+    pub retry_count: u32,
+    pub retry_delay: Duration,
+    pub max_total_duration: Option<Duration>,
+    pub accepted_codes: Vec<i32>,
+    pub retry_on: Option<Vec<i32>>,
+    pub retry_jitter_percent: u32,
+    pub jitter_seed: Option<usize>,
+    pub execution_budget: Option<ExecutionBudget>,
+}
+
+impl RetryPolicy {
+    pub fn from_task(task: &FactfileTask) -> Self {
+        let mut accepted_codes = task.on_result.continue_job.clone();
+        accepted_codes.extend(task.on_result.terminate_job.iter());
+        RetryPolicy {
+            retry_count: task.retry_count,
+            retry_delay: Duration::from_secs(task.retry_delay_seconds),
+            max_total_duration: task.max_total_duration_seconds.map(Duration::from_secs),
+            accepted_codes: accepted_codes,
+            retry_on: task.retry_on.clone(),
+            retry_jitter_percent: task.retry_jitter_percent,
+            jitter_seed: None,
+            execution_budget: None,
+        }
+    }
+}
+
+// jitter only ever adds to the delay, so a retry never comes sooner than retryDelaySeconds
+pub fn get_retry_delay(delay: Duration, jitter_percent: u32, rng: &mut StdRng) -> Duration {
+    if jitter_percent == 0 {
+        return delay;
+    }
+    let delay_ms = delay.as_secs() * 1000 + (delay.subsec_nanos() / 1_000_000) as u64;
+    let fraction = rng.gen_range(0.0, jitter_percent as f64 / 100.0);
+    delay + Duration::from_millis((delay_ms as f64 * fraction) as u64)
+}
+
+pub fn run_with_retries<F>(task_name: &str,
+                           command: &mut Command,
+                           strategy: F,
+                           policy: &RetryPolicy)
+                           -> (RunResult, Option<String>)
+    where F: Fn(&str, &mut Command) -> RunResult
+{
+    let started = Instant::now();
+    let mut total_run_time = Duration::new(0, 0);
+    let mut attempt = 1;
+
+    // with a seed, each task gets its own (repeatable) sequence of jitter
+    let name_seed = task_name.bytes()
+        .fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+    let seed = policy.jitter_seed.unwrap_or_else(|| rand::random::<usize>());
+    let mut jitter_rng: StdRng = SeedableRng::from_seed(&[seed, name_seed][..]);
+
+    loop {
+        let mut task_result = strategy(task_name, command);
+        total_run_time = total_run_time + task_result.duration;
+        task_result.duration = total_run_time;
+
+        if policy.accepted_codes.contains(&task_result.return_code) ||
+           task_result.task_execution_error.is_some() {
+            return (task_result, None);
+        }
+
+        // without retryOn, any failure is retried
+        if let Some(ref retry_on) = policy.retry_on {
+            if !retry_on.contains(&task_result.return_code) {
+                let note = if policy.retry_count > 0 {
+                    Some(format!("not retried as {} isn't one of the retryOn codes [{}]",
+                                 task_result.return_code,
+                                 retry_on.iter()
+                                     .map(|code| code.to_string())
+                                     .collect::<Vec<String>>()
+                                     .join(",")))
+                } else {
+                    None
+                };
+                return (task_result, note);
+            }
+        }
+
+        if attempt > policy.retry_count {
+            let note = if policy.retry_count > 0 {
+                Some(format!("giving up after {} attempts (retryCount {})",
+                             attempt,
+                             policy.retry_count))
+            } else {
+                None
+            };
+            return (task_result, note);
+        }
+
+        let retry_delay = get_retry_delay(policy.retry_delay,
+                                          policy.retry_jitter_percent,
+                                          &mut jitter_rng);
+
+        if let Some(max_total) = policy.max_total_duration {
+            let elapsed = started.elapsed();
+            if elapsed + retry_delay >= max_total {
+                // the time budget is checked before each retry, so it always wins over retryCount
+                return (task_result,
+                        Some(format!("no more retries after {} attempts as the task would \
+                                      exceed its maxTotalDurationSeconds of {}s (this limit \
+                                      includes the retryDelaySeconds between attempts, and takes \
+                                      precedence over retryCount)",
+                                     attempt,
+                                     max_total.as_secs())));
+            }
+        }
+
+        if !policy.execution_budget.as_ref().map_or(true, |b| b.try_take()) {
+            return (task_result,
+                    Some(format!("no more retries after {} attempts as {}",
+                                 attempt,
+                                 MAX_EXECUTIONS_MESSAGE)));
+        }
+
+        warn!("task '{}' returned {}, retrying in {:?} (attempt {} of {})",
+              task_name,
+              task_result.return_code,
+              retry_delay,
+              attempt + 1,
+              policy.retry_count + 1);
+        thread::sleep(retry_delay);
+        attempt += 1;
+    }
+}
+
+pub fn run_task_hook<F>(task_name: &str,
+                        hook_name: &str,
+                        hook_command: &str,
+                        working_dir: &Option<PathBuf>,
+                        strategy: F)
+                        -> bool
+    where F: Fn(&str, &mut Command) -> RunResult
+{
+    info!("running the {} hook for task '{}'", hook_name, task_name);
+    let mut command = Command::new("sh");
+    command.arg("-c");
+    command.arg(hook_command);
+    if let Some(ref dir) = *working_dir {
+        command.current_dir(dir);
+    }
+
+    let hook_result = strategy(&format!("{} ({})", task_name, hook_name), &mut command);
+
+    if let Some(ref stdout) = hook_result.stdout {
+        info!("{} hook for task '{}' stdout:\n{}", hook_name, task_name, stdout);
+    }
+    if let Some(ref stderr) = hook_result.stderr {
+        info!("{} hook for task '{}' stderr:\n{}", hook_name, task_name, stderr);
+    }
+
+    // hooks are side effects only, so a broken hook never changes the outcome of the task
+    if let Some(ref err) = hook_result.task_execution_error {
+        warn!("the {} hook for task '{}' couldn't be run: {}",
+              hook_name,
+              task_name,
+              err);
+        false
+    } else if hook_result.return_code != 0 {
+        warn!("the {} hook for task '{}' exited with {}",
+              hook_name,
+              task_name,
+              hook_result.return_code);
+        false
+    } else {
+        true
+    }
+}
+
+pub fn select_host(task: &FactfileTask,
+                   next_hosts: &mut HashMap<Vec<String>, usize>)
+                   -> Option<String> {
+    if task.hosts.is_empty() {
+        return None;
+    }
+
+    let idx = match task.host_selection {
+        HostSelection::Random => rand::thread_rng().gen_range(0, task.hosts.len()),
+        HostSelection::RoundRobin => {
+            // tasks sharing the same hosts take turns, so work is spread across the run
+            let next = next_hosts.entry(task.hosts.clone()).or_insert(0);
+            let idx = *next % task.hosts.len();
+            *next += 1;
+            idx
+        }
+    };
+
+    info!("Task '{}' will run on host '{}'", task.name, task.hosts[idx]);
+    if task.address_selection == AddressSelection::Hostname {
+        return Some(task.hosts[idx].clone());
+    }
+
+    match resolve_host(&task.hosts[idx], &task.address_selection) {
+        Ok(address) => {
+            info!("Task '{}' will connect to '{}'", task.name, address);
+            Some(address)
+        }
+        Err(msg) => {
+            // ssh gets the name instead, and can report the lookup failure itself
+            warn!("Task '{}' couldn't resolve host '{}': {}", task.name, task.hosts[idx], msg);
+            Some(task.hosts[idx].clone())
+        }
+    }
+}
+
+// a "user@" prefix is kept in front of the chosen address
+pub fn resolve_host(host: &str, selection: &AddressSelection) -> Result<String, String> {
+    let (user, name) = match host.rfind('@') {
+        Some(at) => host.split_at(at + 1),
+        None => ("", host),
+    };
+
+    let mut addrs = try!((name, 22).to_socket_addrs().map_err(|e| e.to_string()))
+        .map(|a| a.ip())
+        .collect::<Vec<IpAddr>>();
+    addrs.dedup();
+    if addrs.is_empty() {
+        return Err("no addresses were found".to_string());
+    }
+
+    let idx = match *selection {
+        AddressSelection::Random => rand::thread_rng().gen_range(0, addrs.len()),
+        _ => 0,
+    };
+    Ok(format!("{}{}", user, addrs[idx]))
+}
+
+pub fn get_task_working_dir(task: &FactfileTask, workdir: &Option<String>) -> Option<PathBuf> {
+    match (workdir.as_ref(), task.cwd.as_ref()) {
+        (Some(root), Some(cwd)) => Some(Path::new(root).join(cwd)),
+        (Some(root), None) => Some(PathBuf::from(root)),
+        (None, Some(cwd)) => Some(PathBuf::from(cwd)),
+        (None, None) => None,
+    }
+}
+
+const STDIN_ENV_VAR: &'static str = "FACTOTUM_TASK_STDIN";
+pub const OUTPUTS_FILE_ENV_VAR: &'static str = "FACTOTUM_OUTPUTS";
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", "'\\''"))
+}
+
+// the environment a task's command starts with - factotum's own, plus any stdin it's given
+// and the extra variables factotum sets for it
+pub fn get_task_env(task: &FactfileTask,
+                    extra: &BTreeMap<String, String>)
+                    -> Vec<(String, String)> {
+    let mut extra = extra.clone();
+    if let Some(ref input) = task.stdin {
+        extra.insert(STDIN_ENV_VAR.to_string(), input.clone());
+    }
+    let mut vars = env::vars()
+        .filter(|&(ref name, _)| !extra.contains_key(name))
+        .collect::<Vec<(String, String)>>();
+    vars.extend(extra.into_iter());
+    vars.sort();
+    vars
+}
+
+// each task only sees the outputs of the tasks it depends on directly
+pub fn get_task_inputs(task: &FactfileTask,
+                       outputs: &HashMap<String, BTreeMap<String, String>>)
+                       -> BTreeMap<String, String> {
+    let mut inputs = BTreeMap::new();
+    for dep in task.depends_on.iter() {
+        if let Some(dep_outputs) = outputs.get(dep) {
+            inputs.extend(dep_outputs.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+    inputs
+}
+
+// the task appends NAME=value lines to the file; anything it didn't declare is ignored
+pub fn read_task_outputs(task_name: &str,
+                         path: &Path,
+                         declared: &Vec<String>)
+                         -> BTreeMap<String, String> {
+    let mut contents = String::new();
+    if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+        warn!("couldn't read the outputs of task '{}' from '{}': {}",
+              task_name,
+              path.display(),
+              e);
+    }
+
+    let mut outputs = BTreeMap::new();
+    for line in contents.lines().map(|l| l.trim_right_matches('\r')) {
+        if let Some(idx) = line.find('=') {
+            let name = line[..idx].trim();
+            if declared.iter().any(|d| d == name) {
+                outputs.insert(name.to_string(), line[idx + 1..].to_string());
+            } else if !name.is_empty() {
+                info!("ignoring the undeclared output '{}' of task '{}'", name, task_name);
+            }
+        }
+    }
+    for name in declared.iter().filter(|d| !outputs.contains_key(*d)) {
+        warn!("the task '{}' didn't write its output '{}'", task_name, name);
+    }
+    outputs
+}
+
+pub fn get_task_launch_details(task_name: &str,
+                               command: &Command,
+                               env: &Vec<(String, String)>,
+                               secrets: &Vec<String>)
+                               -> String {
+    let mut details = format!("Task '{}' is starting with the command {}\n",
+                              task_name,
+                              mask_secrets(&format!("{:?}", command), secrets));
+    details.push_str(&format!("Task '{}' environment:\n", task_name));
+    for &(ref name, ref value) in env.iter() {
+        details.push_str(&format!("  {}={}\n", name, mask_secrets(value, secrets)));
+    }
+    details
+}
+
+pub fn mask_secrets(text: &str, secrets: &Vec<String>) -> String {
+    // longest first, so a secret that contains another is masked whole
+    let mut secrets = secrets.clone();
+    secrets.sort_by(|a, b| b.len().cmp(&a.len()));
+
+    let mut masked = text.to_string();
+    // an empty secret would match between every character
+    for secret in secrets.iter().filter(|s| !s.is_empty()) {
+        // in JSON (or a quoted command), the value may have been escaped
+        let escaped = Json::String(secret.clone()).to_string();
+        masked = masked.replace(&escaped[1..escaped.len() - 1], "***").replace(secret, "***");
+    }
+    masked
+}
+
+pub fn get_task_command(task: &FactfileTask, host: &Option<String>) -> Command {
+    let args = format_args(&task.command, &task.arguments);
+
+    let target = if let Some(ref h) = *host {
+        vec!["ssh".to_string(), h.clone(), args]
+    } else {
+        vec!["sh".to_string(), "-c".to_string(), args]
+    };
+
+    let mut command = if let Some(ref input) = task.stdin {
+        // the input is fed in by a wrapping shell, so it's there again if the task is retried
+        let quoted_target = target.iter().map(|a| shell_quote(a)).collect::<Vec<String>>();
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command.arg(format!("printf '%s' \"${}\" | {}", STDIN_ENV_VAR, quoted_target.join(" ")));
+        command.env(STDIN_ENV_VAR, input);
+        command
+    } else {
+        let mut command = Command::new(&target[0]);
+        command.args(&target[1..]);
+        command
+    };
+
+    // so tools that read stdin when they find it don't wait forever for input that won't come
+    if task.inherit_stdin {
+        command.stdin(Stdio::inherit());
+    } else {
+        command.stdin(Stdio::null());
+    }
+
+    // streams that aren't captured are discarded, and show up as None in the RunResult
+    if !task.capture_stdout {
+        command.stdout(Stdio::null());
+    }
+    if !task.capture_stderr {
+        command.stderr(Stdio::null());
+    }
+
+    set_run_as(&mut command, task);
+
+    command
+}
+
+#[cfg(unix)]
+fn set_run_as(command: &mut Command, task: &FactfileTask) {
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::os::unix::process::CommandExt;
+
+    let uid = match task.run_as_user {
+        Some(uid) => uid,
+        None => {
+            if let Some(gid) = task.run_as_group {
+                command.gid(gid);
+            }
+            return;
+        }
+    };
+
+    // without runAsGroup the task gets the user's own groups, not whatever factotum runs with
+    let passwd = unsafe { libc::getpwuid(uid) };
+    let user = if passwd.is_null() {
+        None
+    } else {
+        let name = unsafe { CStr::from_ptr((*passwd).pw_name) }.to_owned();
+        Some((name, unsafe { (*passwd).pw_gid }))
+    };
+    let gid = task.run_as_group.or(user.as_ref().map(|&(_, gid)| gid));
+    let supplementary_from: Option<CString> = match (task.run_as_group, user) {
+        (None, Some((name, _))) => Some(name),
+        _ => None,
+    };
+
+    // the groups have to be set while still privileged, so before the user is switched
+    command.before_exec(move || {
+        let groups_set = match (&supplementary_from, gid) {
+            (&Some(ref name), Some(gid)) => unsafe { libc::initgroups(name.as_ptr(), gid) },
+            _ => unsafe { libc::setgroups(0, ::std::ptr::null()) },
+        };
+        if groups_set != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if let Some(gid) = gid {
+            if unsafe { libc::setgid(gid) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        if unsafe { libc::setuid(uid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    });
+}
+
+#[cfg(not(unix))]
+fn set_run_as(_: &mut Command, _: &FactfileTask) {
+    // run_as_user/run_as_group are rejected by the parser on these platforms
+}
+
+fn get_run_as_description(task: &FactfileTask) -> Option<String> {
+    match (task.run_as_user, task.run_as_group) {
+        (Some(uid), Some(gid)) => Some(format!("uid {} and gid {}", uid, gid)),
+        (Some(uid), None) => Some(format!("uid {}", uid)),
+        (None, Some(gid)) => Some(format!("gid {}", gid)),
+        (None, None) => None,
+    }
+}
+
 pub fn format_args(command: &str, args: &Vec<String>) -> String {
     let arg_str = args.iter()
         .map(|s| format!("\"{}\"", s))
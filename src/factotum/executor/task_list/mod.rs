@@ -29,6 +29,14 @@ pub enum State {
     Skipped(String),
 }
 
+// why a job stopped before all of its tasks had a chance to run
+#[derive(Clone, PartialEq, Debug)]
+pub enum StopReason {
+    AbortFile,
+    Operator,
+    MaxExecutions,
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Task<T> {
     pub name: String,
@@ -55,6 +63,7 @@ pub type TaskGroup<T> = Vec<Task<T>>;
 #[derive(Clone, Debug)]
 pub struct TaskList<T> {
     pub tasks: Vec<TaskGroup<T>>,
+    pub stopped_by: Option<StopReason>,
     edges: HashMap<String, Vec<String>>,
 }
 
@@ -62,6 +71,7 @@ impl<T> TaskList<T> {
     pub fn new() -> Self {
         TaskList {
             tasks: vec![],
+            stopped_by: None,
             edges: HashMap::new(),
         }
     }
@@ -15,6 +15,7 @@
 use factotum::tests::make_task;
 use factotum::factfile::*;
 use factotum::executor::*;
+use factotum::executor::execution_strategy::RunResult;
 
 #[test]
 fn get_task_execution_list_good() {
@@ -102,6 +103,7 @@ fn get_task_snapshot_clones() {
         stdout: Some("hello world".to_string()),
         duration: Duration::seconds(0).to_std().ok().unwrap(),
         task_execution_error: None,
+        ..Default::default()
     });
 
     let snapshot = get_task_snapshot(&tl);
@@ -711,4 +713,1184 @@ fn execute_sends_noop_skipped_messages() {
                Transition::Task(expected_second_task_transition));
 }
 
+#[test]
+fn execute_with_completed_tasks_skips_them() {
+    use factotum::executor::task_list::State;
+    use factotum::factfile::Task as FactfileTask;
+
+    let mut ff = Factfile::new("N/A", "test");
+
+    let tasks: Vec<FactfileTask> = vec![make_task("apple", &vec![]),
+                                        make_task("turnip", &vec!["apple"]),
+                                        make_task("egg", &vec!["turnip"])];
+
+    for mut task in tasks.into_iter() {
+        task.on_result.continue_job.push(0);
+        ff.add_task_obj(&task);
+    }
+
+    let options = ExecutionOptions {
+        completed_tasks: vec!["apple".to_string()],
+        ..Default::default()
+    };
+
+    let result = execute_factfile_with_options(&ff,
+                                               None,
+                                               execution_strategy::execute_simulation,
+                                               None,
+                                               options);
+
+    let apple = &result.tasks[0][0];
+    assert_eq!(apple.name, "apple");
+    assert_eq!(apple.state, State::Success);
+    assert!(apple.run_result.is_none());
+
+    for task in result.tasks.iter().skip(1).flat_map(|tg| tg.iter()) {
+        assert_eq!(task.state, State::Success);
+        assert!(task.run_result.is_some());
+    }
+}
+
+#[test]
+fn execute_writes_state_file() {
+    use factotum::executor::task_list::State;
+    use factotum::runrecord::{self, StateFile};
+    use std::env;
+    use std::fs;
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    ff.add_task_obj(&apple);
+
+    let path = env::temp_dir().join("factotum-execute-state-file-test.json");
+    let path_str = path.to_str().unwrap().to_string();
+
+    let options = ExecutionOptions {
+        state_file: Some(StateFile {
+            path: path_str.clone(),
+            factfile: "test.factfile".to_string(),
+        }),
+        ..Default::default()
+    };
+
+    let result = execute_factfile_with_options(&ff,
+                                               None,
+                                               execution_strategy::execute_simulation,
+                                               None,
+                                               options);
+    assert_eq!(result.tasks[0][0].state, State::Success);
+
+    let record = runrecord::read_record(&path_str).unwrap();
+    assert_eq!(record.factfile, "test.factfile");
+    assert_eq!(record.succeeded_tasks(), vec!["apple"]);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn task_start_order_shuffles_reproducibly() {
+    use rand::{SeedableRng, StdRng};
+
+    assert_eq!(get_task_start_order(4, None), vec![0, 1, 2, 3]);
+
+    let mut rng_a: StdRng = SeedableRng::from_seed(&[42][..]);
+    let mut rng_b: StdRng = SeedableRng::from_seed(&[42][..]);
+    let order_a = get_task_start_order(20, Some(&mut rng_a));
+    assert_eq!(order_a, get_task_start_order(20, Some(&mut rng_b)));
+
+    let mut sorted = order_a.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..20).collect::<Vec<usize>>());
+}
+
+#[test]
+fn shuffled_execution_has_same_outcome() {
+    use factotum::executor::task_list::TaskList;
+    use factotum::factfile::Task as FactfileTask;
+
+    let mut ff = Factfile::new("N/A", "test");
+    for task in vec![make_task("apple", &vec![]),
+                     make_task("turnip", &vec![]),
+                     make_task("orange", &vec!["apple"]),
+                     make_task("egg", &vec!["apple", "turnip"])]
+        .into_iter() {
+        let mut task = task;
+        task.on_result.continue_job.push(0);
+        ff.add_task_obj(&task);
+    }
+
+    let states = |tl: TaskList<&FactfileTask>| {
+        tl.tasks
+            .iter()
+            .flat_map(|tg| tg.iter())
+            .map(|t| (t.name.clone(), t.state.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let normal = execute_factfile(&ff, None, execution_strategy::execute_simulation, None);
+    let shuffled = execute_factfile_with_options(&ff,
+                                                 None,
+                                                 execution_strategy::execute_simulation,
+                                                 None,
+                                                 ExecutionOptions {
+                                                     shuffle_seed: Some(7),
+                                                     ..Default::default()
+                                                 });
+
+    assert_eq!(states(normal), states(shuffled));
+}
+
+#[test]
+fn task_hooks_run_after_task_settles() {
+    use factotum::executor::task_list::State;
+    use factotum::factfile::Task as FactfileTask;
+    use std::env;
+    use std::fs;
+
+    let success_file = env::temp_dir().join("factotum-hook-success-test");
+    let failure_file = env::temp_dir().join("factotum-hook-failure-test");
+    let _ = fs::remove_file(&success_file);
+    let _ = fs::remove_file(&failure_file);
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut good: FactfileTask = make_task("good", &vec![]);
+    good.command = "true".to_string();
+    good.on_result.continue_job.push(0);
+    good.on_success = Some(format!("touch {}", success_file.display()));
+    good.on_failure = Some("exit 1".to_string());
+    ff.add_task_obj(&good);
+
+    let mut bad: FactfileTask = make_task("bad", &vec![]);
+    bad.command = "false".to_string();
+    bad.on_result.continue_job.push(0);
+    bad.on_failure = Some(format!("touch {}", failure_file.display()));
+    ff.add_task_obj(&bad);
+
+    let result = execute_factfile(&ff, None, execution_strategy::execute_os, None);
+
+    for task in result.tasks.iter().flat_map(|tg| tg.iter()) {
+        match task.name.as_ref() {
+            "good" => assert_eq!(task.state, State::Success),
+            _ => {
+                if let State::Failed(_) = task.state {
+                } else {
+                    panic!("the 'bad' task should have failed")
+                }
+            }
+        }
+    }
+
+    assert!(success_file.exists());
+    assert!(failure_file.exists());
+    fs::remove_file(&success_file).unwrap();
+    fs::remove_file(&failure_file).unwrap();
+}
+
+#[test]
+fn failing_hook_only_warns() {
+    use factotum::executor::execution_strategy::execute_os;
+
+    assert!(run_task_hook("task", "onSuccess", "exit 0", &None, execute_os));
+    assert!(!run_task_hook("task", "onSuccess", "exit 3", &None, execute_os));
+}
+
+#[test]
+fn hook_runs_in_the_task_working_dir() {
+    use factotum::executor::execution_strategy::execute_os;
+
+    let dir = env::temp_dir();
+    let cleaned_up = dir.join("factotum-hook-cwd-test");
+    fs::File::create(&cleaned_up).unwrap();
+
+    assert!(run_task_hook("task",
+                          "onFailure",
+                          "rm factotum-hook-cwd-test",
+                          &Some(dir.clone()),
+                          execute_os));
+    assert!(!cleaned_up.exists());
+}
+
+fn real_preconditions(name: &str, cmd: &mut ::std::process::Command) -> RunResult {
+    use factotum::executor::execution_strategy::execute_os;
+
+    if name.ends_with("(precondition)") {
+        execute_os(name, cmd)
+    } else {
+        RunResult { return_code: 0, ..Default::default() }
+    }
+}
+
+#[test]
+fn precondition_runs_in_the_task_working_dir() {
+    use factotum::executor::task_list::State;
+
+    let dir = env::temp_dir().join("factotum-precondition-cwd-test");
+    fs::create_dir_all(&dir).unwrap();
+    fs::File::create(dir.join("ready")).unwrap();
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    apple.cwd = Some(dir.display().to_string());
+    apple.precondition = Some("test -f ready".to_string());
+    ff.add_task_obj(&apple);
+
+    let result = execute_factfile(&ff, None, real_preconditions, None);
+
+    assert_eq!(result.tasks[0][0].state, State::Success);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn select_host_round_robin_and_random() {
+    use std::collections::HashMap;
+
+    let mut next_hosts = HashMap::new();
+
+    let no_hosts = make_task("local", &vec![]);
+    assert_eq!(select_host(&no_hosts, &mut next_hosts), None);
+
+    let mut a = make_task("a", &vec![]);
+    a.hosts = vec!["one".to_string(), "two".to_string()];
+    let mut b = make_task("b", &vec![]);
+    b.hosts = a.hosts.clone();
+
+    assert_eq!(select_host(&a, &mut next_hosts), Some("one".to_string()));
+    assert_eq!(select_host(&b, &mut next_hosts), Some("two".to_string()));
+    assert_eq!(select_host(&a, &mut next_hosts), Some("one".to_string()));
+
+    let mut c = make_task("c", &vec![]);
+    c.hosts = vec!["three".to_string()];
+    c.host_selection = HostSelection::Random;
+    assert_eq!(select_host(&c, &mut next_hosts), Some("three".to_string()));
+}
+
+#[test]
+fn resolve_host_keeps_the_user() {
+    use std::net::IpAddr;
+
+    assert_eq!(resolve_host("127.0.0.1", &AddressSelection::Random),
+               Ok("127.0.0.1".to_string()));
+
+    let address = resolve_host("deploy@localhost", &AddressSelection::First).unwrap();
+    assert!(address.starts_with("deploy@"));
+    assert!(address["deploy@".len()..].parse::<IpAddr>().is_ok());
+}
+
+#[test]
+fn get_task_command_uses_ssh_for_hosts() {
+    let mut task = make_task("hello", &vec![]);
+    task.command = "echo".to_string();
+    task.arguments = vec!["hi".to_string()];
+
+    let local = get_task_command(&task, &None);
+    assert_eq!(format!("{:?}", local), r#""sh" "-c" "echo \"hi\"""#);
+
+    let remote = get_task_command(&task, &Some("worker-1".to_string()));
+    assert_eq!(format!("{:?}", remote), r#""ssh" "worker-1" "echo \"hi\"""#);
+}
+
+#[test]
+fn task_launch_details_mask_secrets() {
+    use std::collections::BTreeMap;
+
+    let mut task = make_task("login", &vec![]);
+    task.command = "login".to_string();
+    task.arguments = vec!["--password".to_string(), "hunter2".to_string()];
+
+    let details = get_task_launch_details("login",
+                                          &get_task_command(&task, &None),
+                                          &vec![("PASS".to_string(), "hunter2".to_string()),
+                                                ("USER".to_string(), "me".to_string())],
+                                          &vec!["hunter2".to_string()]);
+    assert_eq!(details,
+               "Task 'login' is starting with the command \"sh\" \"-c\" \
+                \"login \\\"--password\\\" \\\"***\\\"\"\n\
+                Task 'login' environment:\n  \
+                PASS=***\n  \
+                USER=me\n");
+
+    task.stdin = Some("user".to_string());
+    let env = get_task_env(&task, &BTreeMap::new());
+    assert!(env.contains(&("FACTOTUM_TASK_STDIN".to_string(), "user".to_string())));
+}
+
+#[test]
+fn uncaptured_streams_are_none() {
+    use factotum::executor::execution_strategy::execute_os;
+
+    let mut task = make_task("noisy", &vec![]);
+    task.command = "echo out; echo err 1>&2".to_string();
+
+    let captured = execute_os("noisy", &mut get_task_command(&task, &None));
+    assert_eq!(captured.stdout, Some("out".to_string()));
+    assert_eq!(captured.stderr, Some("err".to_string()));
+
+    task.capture_stdout = false;
+    let no_stdout = execute_os("noisy", &mut get_task_command(&task, &None));
+    assert_eq!(no_stdout.stdout, None);
+    assert_eq!(no_stdout.stderr, Some("err".to_string()));
+
+    task.capture_stdout = true;
+    task.capture_stderr = false;
+    let no_stderr = execute_os("noisy", &mut get_task_command(&task, &None));
+    assert_eq!(no_stderr.stdout, Some("out".to_string()));
+    assert_eq!(no_stderr.stderr, None);
+}
+
+#[test]
+fn stdin_is_empty_by_default() {
+    use factotum::executor::execution_strategy::execute_os;
+
+    let mut task = make_task("reader", &vec![]);
+    task.command = "if read line; then echo \"read $line\"; else echo \"no input\"; fi"
+        .to_string();
+
+    let result = execute_os("reader", &mut get_task_command(&task, &None));
+    assert_eq!(result.stdout, Some("no input".to_string()));
+    assert_eq!(result.return_code, 0);
+}
+
+fn always_fails(_: &str, _: &mut ::std::process::Command) -> RunResult {
+    RunResult { return_code: 1, ..Default::default() }
+}
+
+#[test]
+fn run_with_retries_gives_up_after_retry_count() {
+    use std::process::Command;
+    use std::time::Duration;
+
+    let policy = RetryPolicy {
+        retry_count: 2,
+        retry_delay: Duration::from_secs(0),
+        max_total_duration: None,
+        accepted_codes: vec![0],
+        retry_on: None,
+        retry_jitter_percent: 0,
+        jitter_seed: None,
+        execution_budget: None,
+    };
+    let (result, note) = run_with_retries("flaky", &mut Command::new("true"), always_fails, &policy);
+    assert_eq!(result.return_code, 1);
+    assert_eq!(note,
+               Some("giving up after 3 attempts (retryCount 2)".to_string()));
+
+    let no_retries = RetryPolicy { retry_count: 0, ..policy.clone() };
+    assert_eq!(run_with_retries("flaky", &mut Command::new("true"), always_fails, &no_retries).1,
+               None);
+
+    let succeeds = RetryPolicy { accepted_codes: vec![1], ..policy };
+    assert_eq!(run_with_retries("flaky", &mut Command::new("true"), always_fails, &succeeds).1,
+               None);
+}
+
+#[test]
+fn retry_jitter_only_adds_to_the_delay() {
+    use rand::{SeedableRng, StdRng};
+    use std::time::Duration;
+
+    let delay = Duration::from_secs(10);
+    let mut rng: StdRng = SeedableRng::from_seed(&[42][..]);
+    assert_eq!(get_retry_delay(delay, 0, &mut rng), delay);
+
+    for _ in 0..100 {
+        let jittered = get_retry_delay(delay, 50, &mut rng);
+        assert!(jittered >= delay && jittered < Duration::from_secs(15));
+    }
+
+    let mut rng_a: StdRng = SeedableRng::from_seed(&[7][..]);
+    let mut rng_b: StdRng = SeedableRng::from_seed(&[7][..]);
+    assert_eq!(get_retry_delay(delay, 50, &mut rng_a),
+               get_retry_delay(delay, 50, &mut rng_b));
+}
+
+#[test]
+fn run_with_retries_stops_at_max_total_duration() {
+    use std::process::Command;
+    use std::time::Duration;
+
+    let policy = RetryPolicy {
+        retry_count: 10,
+        retry_delay: Duration::from_secs(5),
+        max_total_duration: Some(Duration::from_secs(3)),
+        accepted_codes: vec![0],
+        retry_on: None,
+        retry_jitter_percent: 0,
+        jitter_seed: None,
+        execution_budget: None,
+    };
+    let (result, note) = run_with_retries("flaky", &mut Command::new("true"), always_fails, &policy);
+    assert_eq!(result.return_code, 1);
+    assert!(note.unwrap().starts_with("no more retries after 1 attempts as the task would exceed \
+                                       its maxTotalDurationSeconds of 3s"));
+}
+
+#[test]
+fn run_with_retries_only_retries_retry_on_codes() {
+    use std::process::Command;
+    use std::time::Duration;
+
+    let policy = RetryPolicy {
+        retry_count: 2,
+        retry_delay: Duration::from_secs(0),
+        max_total_duration: None,
+        accepted_codes: vec![0],
+        retry_on: Some(vec![75]),
+        retry_jitter_percent: 0,
+        jitter_seed: None,
+        execution_budget: None,
+    };
+    let (result, note) = run_with_retries("deterministic",
+                                          &mut Command::new("true"),
+                                          always_fails,
+                                          &policy);
+    assert_eq!(result.return_code, 1);
+    assert_eq!(note,
+               Some("not retried as 1 isn't one of the retryOn codes [75]".to_string()));
+
+    let retries_one = RetryPolicy { retry_on: Some(vec![1, 75]), ..policy };
+    assert_eq!(run_with_retries("flaky", &mut Command::new("true"), always_fails, &retries_one).1,
+               Some("giving up after 3 attempts (retryCount 2)".to_string()));
+}
+
 // todo write test for rejecting non "shell" execution types
+
+#[test]
+fn abort_file_skips_tasks_not_yet_started() {
+    use factotum::executor::task_list::State;
+    use factotum::executor::execution_strategy::execute_os;
+    use factotum::factfile::Task as FactfileTask;
+    use std::env;
+    use std::fs;
+
+    let abort_file = env::temp_dir().join("factotum-abort-file-test");
+    let _ = fs::remove_file(&abort_file);
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut first: FactfileTask = make_task("first", &vec![]);
+    // the abort file appears while the first task is running
+    first.command = format!("touch {} && sleep 1", abort_file.display());
+    first.on_result.continue_job.push(0);
+    ff.add_task_obj(&first);
+    let mut second: FactfileTask = make_task("second", &vec!["first"]);
+    second.command = "true".to_string();
+    second.on_result.continue_job.push(0);
+    ff.add_task_obj(&second);
+
+    let options = ExecutionOptions {
+        abort_file: Some(abort_file.to_str().unwrap().to_string()),
+        ..Default::default()
+    };
+    let result = execute_factfile_with_options(&ff, None, execute_os, None, options);
+    fs::remove_file(&abort_file).unwrap();
+
+    assert_eq!(result.tasks[0][0].state, State::Success);
+    match result.tasks[1][0].state {
+        State::Skipped(ref msg) => assert!(msg.starts_with(ABORTED_MESSAGE)),
+        ref other => panic!("expected the task to be skipped, got {:?}", other),
+    }
+    assert_eq!(result.stopped_by, Some(StopReason::AbortFile));
+}
+
+#[test]
+fn max_executions_counts_retries_and_skips_the_rest() {
+    use factotum::executor::task_list::State;
+    use factotum::executor::execution_strategy::execute_simulation;
+    use factotum::factfile::Task as FactfileTask;
+    use std::process::Command;
+
+    let mut ff = Factfile::new("N/A", "test");
+    for (name, deps) in vec![("a", vec![]), ("b", vec!["a"]), ("c", vec!["b"])] {
+        let mut task: FactfileTask = make_task(name, &deps);
+        task.on_result.continue_job.push(0);
+        ff.add_task_obj(&task);
+    }
+
+    let options = ExecutionOptions { max_executions: Some(2), ..Default::default() };
+    let result = execute_factfile_with_options(&ff, None, execute_simulation, None, options);
+
+    assert_eq!(result.tasks[0][0].state, State::Success);
+    assert_eq!(result.tasks[1][0].state, State::Success);
+    assert_eq!(result.tasks[2][0].state,
+               State::Skipped(MAX_EXECUTIONS_MESSAGE.to_string()));
+    assert_eq!(result.stopped_by, Some(StopReason::MaxExecutions));
+
+    let budget = ExecutionBudget::new(1);
+    let policy = RetryPolicy {
+        retry_count: 5,
+        execution_budget: Some(budget.clone()),
+        ..RetryPolicy::from_task(&make_task("flaky", &vec![]))
+    };
+    let (_, note) = run_with_retries("flaky", &mut Command::new("true"), always_fails, &policy);
+    assert_eq!(note,
+               Some(format!("no more retries after 2 attempts as {}", MAX_EXECUTIONS_MESSAGE)));
+    assert!(budget.was_denied());
+    assert!(budget.is_exhausted());
+}
+
+#[test]
+fn unfinished_tasks_are_failed() {
+    use factotum::executor::task_list::{State, Task, TaskList};
+
+    let mut tasklist = TaskList::<()>::new();
+    let mut done = Task::new("done", ());
+    done.state = State::Success;
+    let mut skipped = Task::new("skipped", ());
+    skipped.state = State::Skipped("the task 'x' failed".to_string());
+    tasklist.add_group(vec![done, skipped]).unwrap();
+    tasklist.add_group(vec![Task::new("stuck", ())]).unwrap();
+
+    assert_eq!(fail_unfinished_tasks(&mut tasklist), vec!["stuck"]);
+    assert_eq!(tasklist.tasks[0][0].state, State::Success);
+    assert_eq!(tasklist.tasks[1][0].state,
+               State::Failed(format!("{} (it was still Waiting)", UNFINISHED_TASK_MESSAGE)));
+}
+
+#[test]
+fn task_working_dir_is_relative_to_workdir() {
+    use std::path::PathBuf;
+
+    let mut task = make_task("apple", &vec![]);
+    assert_eq!(get_task_working_dir(&task, &None), None);
+    assert_eq!(get_task_working_dir(&task, &Some("/srv/project".to_string())),
+               Some(PathBuf::from("/srv/project")));
+
+    task.cwd = Some("scripts".to_string());
+    assert_eq!(get_task_working_dir(&task, &None),
+               Some(PathBuf::from("scripts")));
+    assert_eq!(get_task_working_dir(&task, &Some("/srv/project".to_string())),
+               Some(PathBuf::from("/srv/project/scripts")));
+
+    task.cwd = Some("/tmp".to_string());
+    assert_eq!(get_task_working_dir(&task, &Some("/srv/project".to_string())),
+               Some(PathBuf::from("/tmp")));
+}
+
+#[test]
+fn task_stdin_is_piped_to_the_command() {
+    use factotum::executor::execution_strategy::execute_os;
+
+    let mut task = make_task("reader", &vec![]);
+    task.command = "tr".to_string();
+    task.arguments = vec!["a-z".to_string(), "A-Z".to_string()];
+    task.stdin = Some("it's a\n\"test\"".to_string());
+
+    let mut command = get_task_command(&task, &None);
+    assert_eq!(execute_os("reader", &mut command).stdout,
+               Some("IT'S A\n\"TEST\"".to_string()));
+    // running it again gets the same input, as a retry would
+    assert_eq!(execute_os("reader", &mut command).stdout,
+               Some("IT'S A\n\"TEST\"".to_string()));
+}
+
+fn only_egg_succeeds(name: &str, _: &mut ::std::process::Command) -> RunResult {
+    RunResult { return_code: if name == "egg" { 0 } else { 1 }, ..Default::default() }
+}
+
+#[test]
+fn no_skip_downstream_runs_dependents_of_failed_tasks() {
+    use factotum::executor::task_list::State;
+    use factotum::factfile::Task as FactfileTask;
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple: FactfileTask = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    ff.add_task_obj(&apple);
+    let mut turnip: FactfileTask = make_task("turnip", &vec!["apple"]);
+    turnip.on_result.continue_job.push(0);
+    ff.add_task_obj(&turnip);
+    let mut egg: FactfileTask = make_task("egg", &vec!["turnip"]);
+    egg.on_result.continue_job.push(0);
+    ff.add_task_obj(&egg);
+
+    let options = ExecutionOptions { no_skip_downstream: true, ..Default::default() };
+    let result = execute_factfile_with_options(&ff, None, only_egg_succeeds, None, options);
+
+    match result.tasks[1][0].state {
+        State::Failed(ref msg) => {
+            assert!(msg.ends_with("(it ran even though upstream task(s) 'apple' failed)"))
+        }
+        ref other => panic!("turnip should have run and failed, got {:?}", other),
+    }
+    assert_eq!(result.tasks[2][0].state, State::Success);
+    assert!(result.tasks[2][0].run_result.is_some());
+}
+
+#[test]
+fn silenced_tasks_send_no_task_updates() {
+    use factotum::executor::task_list::State;
+    use factotum::factfile::Task as FactfileTask;
+    use std::sync::mpsc;
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple: FactfileTask = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    ff.add_task_obj(&apple);
+    let mut turnip: FactfileTask = make_task("turnip", &vec![]);
+    turnip.on_result.continue_job.push(0);
+    turnip.notify = false;
+    ff.add_task_obj(&turnip);
+
+    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+    let result = execute_factfile(&ff, None, execution_strategy::execute_simulation, Some(tx));
+
+    let updates = rx.iter().collect::<Vec<ExecutionUpdate>>();
+    let task_names = updates.iter()
+        .filter_map(|u| match u.transition {
+            Transition::Task(ref transitions) => Some(transitions),
+            _ => None,
+        })
+        .flat_map(|transitions| transitions.iter().map(|t| t.task_name.clone()))
+        .collect::<Vec<String>>();
+
+    assert!(task_names.contains(&"apple".to_string()));
+    assert!(!task_names.contains(&"turnip".to_string()));
+    assert_eq!(updates.first().unwrap().execution_state, ExecutionState::Started);
+    assert_eq!(updates.last().unwrap().execution_state, ExecutionState::Finished);
+    // the silenced task still ran
+    assert_eq!(result.tasks[0][1].state, State::Success);
+}
+
+fn noisy_success(_name: &str, _cmd: &mut ::std::process::Command) -> RunResult {
+    RunResult {
+        stdout: Some("lots of output, then the end".to_string()),
+        stderr: Some("warning: the end".to_string()),
+        return_code: 0,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn local_output_is_truncated_to_its_tail() {
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    ff.add_task_obj(&apple);
+
+    let options = ExecutionOptions { max_local_output_size: Some(7), ..Default::default() };
+    let result = execute_factfile_with_options(&ff, None, noisy_success, None, options);
+
+    let run_result = result.tasks[0][0].run_result.as_ref().unwrap();
+    assert_eq!(run_result.stdout, Some("the end".to_string()));
+    assert_eq!(run_result.stderr, Some("the end".to_string()));
+}
+
+fn skip_apple(task_name: String, _command: String) -> TaskApproval {
+    if task_name == "apple" { TaskApproval::Skip } else { TaskApproval::Run }
+}
+
+#[test]
+fn operator_skips_propagate_to_dependents() {
+    use factotum::executor::task_list::State;
+
+    let mut ff = Factfile::new("N/A", "test");
+    let tasks = [("apple", vec![]), ("turnip", vec![]), ("egg", vec!["apple"])];
+    for &(name, ref deps) in tasks.iter() {
+        let mut task = make_task(name, deps);
+        task.on_result.continue_job.push(0);
+        ff.add_task_obj(&task);
+    }
+
+    let options = ExecutionOptions { task_approval: Some(skip_apple), ..Default::default() };
+    let result = execute_factfile_with_options(&ff,
+                                               None,
+                                               execution_strategy::execute_simulation,
+                                               None,
+                                               options);
+
+    assert_eq!(result.tasks[0][0].state, State::Skipped(OPERATOR_SKIP_MESSAGE.to_string()));
+    assert_eq!(result.tasks[0][1].state, State::Success);
+    assert_eq!(result.tasks[1][0].state,
+               State::Skipped("the task 'apple' was skipped by the operator".to_string()));
+}
+
+fn abort_at_once(_task_name: String, _command: String) -> TaskApproval {
+    TaskApproval::Abort
+}
+
+#[test]
+fn operator_abort_skips_everything_left() {
+    use factotum::executor::task_list::State;
+
+    let mut ff = Factfile::new("N/A", "test");
+    ff.add_task_obj(&make_task("apple", &vec![]));
+    ff.add_task_obj(&make_task("turnip", &vec![]));
+    ff.add_task_obj(&make_task("egg", &vec!["apple"]));
+
+    let options = ExecutionOptions { task_approval: Some(abort_at_once), ..Default::default() };
+    let result = execute_factfile_with_options(&ff,
+                                               None,
+                                               execution_strategy::execute_simulation,
+                                               None,
+                                               options);
+
+    for task in result.tasks.iter().flat_map(|tg| tg.iter()) {
+        assert_eq!(task.state, State::Skipped(OPERATOR_ABORTED_MESSAGE.to_string()));
+    }
+    assert_eq!(result.stopped_by, Some(StopReason::Operator));
+}
+
+#[test]
+fn noop_scope_decides_what_an_early_finish_skips() {
+    use factotum::executor::task_list::State;
+
+    for &(scope, potato_state) in [(NoopScope::Branch, State::Success),
+                                   (NoopScope::Job,
+                                    State::Skipped("the task 'apple' requested early \
+                                                    termination of the job"
+                                        .to_string()))]
+        .iter() {
+        let mut ff = Factfile::new("N/A", "test");
+        let mut apple = make_task("apple", &vec![]);
+        apple.on_result.terminate_job.push(0);
+        apple.noop_scope = scope.clone();
+        ff.add_task_obj(&apple);
+        for &(name, dep) in [("turnip", None), ("egg", Some("apple")), ("potato", Some("turnip"))]
+            .iter() {
+            let mut task = make_task(name, &dep.into_iter().collect());
+            task.on_result.continue_job.push(0);
+            ff.add_task_obj(&task);
+        }
+
+        let result = execute_factfile(&ff, None, execution_strategy::execute_simulation, None);
+
+        let egg = result.tasks[1].iter().find(|t| t.name == "egg").unwrap();
+        let potato = result.tasks[1].iter().find(|t| t.name == "potato").unwrap();
+        assert_eq!(result.tasks[0].iter().find(|t| t.name == "turnip").unwrap().state,
+                   State::Success);
+        assert!(match egg.state {
+            State::Skipped(_) => true,
+            _ => false,
+        });
+        assert_eq!(potato.state, potato_state.clone());
+    }
+}
+
+fn preconditions_fail(name: &str, _cmd: &mut ::std::process::Command) -> RunResult {
+    let return_code = if name.ends_with("(precondition)") { 1 } else { 0 };
+    RunResult { return_code: return_code, ..Default::default() }
+}
+
+#[test]
+fn failed_preconditions_skip_the_task_and_its_dependents() {
+    use factotum::executor::task_list::State;
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    apple.precondition = Some("test -f /nonexistent".to_string());
+    ff.add_task_obj(&apple);
+    let mut turnip = make_task("turnip", &vec![]);
+    turnip.on_result.continue_job.push(0);
+    ff.add_task_obj(&turnip);
+    let mut egg = make_task("egg", &vec!["apple"]);
+    egg.on_result.continue_job.push(0);
+    ff.add_task_obj(&egg);
+
+    let result = execute_factfile(&ff, None, preconditions_fail, None);
+
+    assert_eq!(result.tasks[0][0].state,
+               State::Skipped(PRECONDITION_FAILED_MESSAGE.to_string()));
+    assert!(result.tasks[0][0].run_result.is_none());
+    assert_eq!(result.tasks[0][1].state, State::Success);
+    assert_eq!(result.tasks[1][0].state,
+               State::Skipped("the task 'apple' was skipped as its precondition failed"
+                   .to_string()));
+}
+
+fn latin1_output(_name: &str, _cmd: &mut ::std::process::Command) -> RunResult {
+    use factotum::executor::execution_strategy::RawOutput;
+
+    RunResult {
+        stdout: Some("caf\u{FFFD}".to_string()),
+        raw_output: Some(RawOutput {
+            stdout: vec![b'c', b'a', b'f', 0xe9, b'\n'],
+            stderr: vec![],
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn output_is_decoded_with_the_declared_encoding() {
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    let mut turnip = apple.clone();
+    turnip.name = "turnip".to_string();
+    turnip.output_encoding = OutputEncoding::Latin1;
+    ff.add_task_obj(&apple);
+    ff.add_task_obj(&turnip);
+
+    let result = execute_factfile(&ff, None, latin1_output, None);
+
+    for task in result.tasks[0].iter() {
+        let run_result = task.run_result.as_ref().unwrap();
+        let expected = if task.name == "turnip" { "café" } else { "caf\u{FFFD}" };
+        assert_eq!(run_result.stdout, Some(expected.to_string()));
+        assert_eq!(run_result.stderr, None);
+        assert_eq!(run_result.return_code, 0);
+        assert_eq!(run_result.raw_output, None);
+    }
+}
+
+fn quick_success(_name: &str, _cmd: &mut ::std::process::Command) -> RunResult {
+    RunResult { return_code: 0, ..Default::default() }
+}
+
+#[test]
+fn tasks_wait_for_their_not_before_time() {
+    use chrono::{Duration as ChronoDuration, UTC};
+
+    let not_before = UTC::now() + ChronoDuration::milliseconds(300);
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    apple.not_before = Some(not_before);
+    ff.add_task_obj(&apple);
+
+    let result = execute_factfile(&ff, None, quick_success, None);
+    assert!(UTC::now() >= not_before);
+    assert_eq!(result.tasks[0][0].run_started, Some(not_before));
+
+    let options = ExecutionOptions { ignore_not_before: true, ..Default::default() };
+    let later = UTC::now() + ChronoDuration::hours(1);
+    apple.not_before = Some(later);
+    let mut ff = Factfile::new("N/A", "test");
+    ff.add_task_obj(&apple);
+    let result = execute_factfile_with_options(&ff, None, quick_success, None, options);
+    assert!(result.tasks[0][0].run_started.unwrap() < later);
+}
+
+#[test]
+fn tag_limits_match_tags_or_tag_values() {
+    use std::collections::{BTreeMap, HashMap};
+
+    let mut tags = BTreeMap::new();
+    tags.insert("resource".to_string(), "db".to_string());
+    tags.insert("team".to_string(), "data".to_string());
+
+    let mut limits = HashMap::new();
+    limits.insert("resource:db".to_string(), 2);
+    limits.insert("resource:api".to_string(), 1);
+    limits.insert("team".to_string(), 4);
+    limits.insert("owner".to_string(), 1);
+
+    let mut matching = get_matching_tag_limits(&tags, &limits);
+    matching.sort();
+    assert_eq!(matching, vec!["resource:db", "team"]);
+}
+
+static LIMITED_RUNNING: ::std::sync::atomic::AtomicUsize =
+    ::std::sync::atomic::AtomicUsize::new(0);
+static LIMITED_MAX_RUNNING: ::std::sync::atomic::AtomicUsize =
+    ::std::sync::atomic::AtomicUsize::new(0);
+
+fn track_concurrency(running_count: &::std::sync::atomic::AtomicUsize,
+                     max_count: &::std::sync::atomic::AtomicUsize)
+                     -> RunResult {
+    use std::sync::atomic::Ordering;
+
+    let running = running_count.fetch_add(1, Ordering::SeqCst) + 1;
+    let mut max_running = max_count.load(Ordering::SeqCst);
+    while running > max_running {
+        match max_count.compare_exchange(max_running,
+                                         running,
+                                         Ordering::SeqCst,
+                                         Ordering::SeqCst) {
+            Ok(_) => break,
+            Err(current) => max_running = current,
+        }
+    }
+    ::std::thread::sleep(::std::time::Duration::from_millis(50));
+    running_count.fetch_sub(1, Ordering::SeqCst);
+    RunResult { return_code: 0, ..Default::default() }
+}
+
+fn count_concurrent(_name: &str, _cmd: &mut ::std::process::Command) -> RunResult {
+    track_concurrency(&LIMITED_RUNNING, &LIMITED_MAX_RUNNING)
+}
+
+#[test]
+fn tag_limits_cap_concurrent_tasks() {
+    use std::sync::atomic::Ordering;
+
+    let mut ff = Factfile::new("N/A", "test");
+    for name in ["apple", "turnip", "egg", "potato"].iter() {
+        let mut task = make_task(name, &vec![]);
+        task.on_result.continue_job.push(0);
+        task.tags.insert("resource".to_string(), "db".to_string());
+        ff.add_task_obj(&task);
+    }
+
+    let mut options = ExecutionOptions::default();
+    options.tag_limits.insert("resource".to_string(), 2);
+    let result = execute_factfile_with_options(&ff, None, count_concurrent, None, options);
+
+    assert!(LIMITED_MAX_RUNNING.load(Ordering::SeqCst) <= 2);
+    assert!(result.tasks[0].iter().all(|t| t.run_result.is_some()));
+}
+
+#[test]
+fn tag_limits_and_mutexes_are_released_when_a_task_panics() {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::thread;
+
+    let mut limits = HashMap::new();
+    limits.insert("resource".to_string(), 1);
+    let limiter = Arc::new(TagLimiter::new(limits));
+
+    let panicking = limiter.clone();
+    let joined = thread::spawn(move || {
+            let _slots = TagLimiter::acquire(&panicking,
+                                             vec!["resource".to_string()],
+                                             Some("shared-dir".to_string()));
+            panic!("the task blew up");
+        })
+        .join();
+    assert!(joined.is_err());
+
+    let state = limiter.state.lock().unwrap();
+    assert_eq!(state.running.get("resource"), Some(&0));
+    assert!(state.held_mutexes.is_empty());
+}
+
+fn run_briefly(_name: &str, _cmd: &mut ::std::process::Command) -> RunResult {
+    let duration = ::std::time::Duration::from_millis(50);
+    ::std::thread::sleep(duration);
+    RunResult { return_code: 0, duration: duration, ..Default::default() }
+}
+
+#[test]
+fn tasks_held_back_by_a_limit_start_when_they_run() {
+    use chrono::Duration;
+
+    let mut ff = Factfile::new("N/A", "test");
+    for name in ["apple", "turnip"].iter() {
+        let mut task = make_task(name, &vec![]);
+        task.on_result.continue_job.push(0);
+        task.tags.insert("resource".to_string(), "db".to_string());
+        ff.add_task_obj(&task);
+    }
+
+    let mut options = ExecutionOptions::default();
+    options.tag_limits.insert("resource".to_string(), 1);
+    let result = execute_factfile_with_options(&ff, None, run_briefly, None, options);
+
+    let mut starts = result.tasks[0].iter().map(|t| t.run_started.unwrap()).collect::<Vec<_>>();
+    starts.sort();
+    assert!(starts[1] - starts[0] >= Duration::milliseconds(45));
+}
+
+static MUTEX_RUNNING: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
+static MUTEX_MAX_RUNNING: ::std::sync::atomic::AtomicUsize =
+    ::std::sync::atomic::AtomicUsize::new(0);
+
+fn count_mutex_concurrent(name: &str, _cmd: &mut ::std::process::Command) -> RunResult {
+    if name.starts_with("locked") {
+        track_concurrency(&MUTEX_RUNNING, &MUTEX_MAX_RUNNING)
+    } else {
+        RunResult { return_code: 0, ..Default::default() }
+    }
+}
+
+#[test]
+fn tasks_sharing_a_mutex_run_one_at_a_time() {
+    use std::sync::atomic::Ordering;
+
+    let mut ff = Factfile::new("N/A", "test");
+    for name in ["locked-a", "locked-b", "locked-c", "free"].iter() {
+        let mut task = make_task(name, &vec![]);
+        task.on_result.continue_job.push(0);
+        if name.starts_with("locked") {
+            task.mutex = Some("shared-dir".to_string());
+        }
+        ff.add_task_obj(&task);
+    }
+
+    let result = execute_factfile_with_options(&ff,
+                                               None,
+                                               count_mutex_concurrent,
+                                               None,
+                                               ExecutionOptions::default());
+
+    assert_eq!(MUTEX_MAX_RUNNING.load(Ordering::SeqCst), 1);
+    assert!(result.tasks[0].iter().all(|t| t.run_result.is_some()));
+}
+
+#[test]
+fn job_start_and_finish_are_job_events() {
+    use std::sync::mpsc;
+
+    let mut ff = Factfile::new("N/A", "test");
+    let mut apple = make_task("apple", &vec![]);
+    apple.on_result.continue_job.push(0);
+    ff.add_task_obj(&apple);
+
+    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+    execute_factfile(&ff, None, quick_success, Some(tx));
+
+    let events = rx.iter().map(|u| u.get_job_event()).collect::<Vec<_>>();
+    assert_eq!(events.first(), Some(&Some(JobEvent::Started)));
+    assert_eq!(events.last(), Some(&Some(JobEvent::Finished)));
+    assert_eq!(events.iter().filter(|e| e.is_some()).count(), 2);
+}
+
+#[test]
+fn duration_alerts_only_cover_tasks_past_the_threshold() {
+    use factotum::executor::task_list::Task;
+    use factotum::factfile::Task as FactfileTask;
+    use std::time::Duration;
+
+    assert_eq!(get_duration_variance(60, &Duration::from_secs(90)), 50.0);
+    assert_eq!(get_duration_variance(60, &Duration::from_millis(30000)), -50.0);
+
+    let ran_for = |name: &str, expected, secs| {
+        let mut task = Task::new(name,
+                                 FactfileTask {
+                                     expected_duration_seconds: expected,
+                                     ..Default::default()
+                                 });
+        task.run_result = Some(RunResult {
+            duration: Duration::from_secs(secs),
+            ..Default::default()
+        });
+        task
+    };
+    let snapshot = vec![ran_for("slow", Some(10), 30),
+                        ran_for("fast", Some(10), 2),
+                        ran_for("steady", Some(10), 12),
+                        ran_for("unknown", None, 30)];
+
+    let alerts = get_duration_alerts(&snapshot, 50.0);
+    assert_eq!(alerts.len(), 2);
+    assert_eq!(alerts["slow"], 200.0);
+    assert_eq!(alerts["fast"], -80.0);
+}
+
+#[test]
+fn skipped_tasks_skip_their_dependents() {
+    use factotum::executor::task_list::State;
+    use std::collections::HashMap;
+
+    let mut ff = Factfile::new("N/A", "test");
+    for (name, deps) in vec![("apple", vec![]), ("turnip", vec!["apple"]), ("egg", vec![])] {
+        let mut task = make_task(name, &deps);
+        task.on_result.continue_job.push(0);
+        ff.add_task_obj(&task);
+    }
+
+    let mut options = ExecutionOptions::default();
+    options.skip_tasks = vec!["apple".to_string()];
+    let result = execute_factfile_with_options(&ff, None, quick_success, None, options);
+
+    let states = result.tasks
+        .iter()
+        .flat_map(|tg| tg.iter())
+        .map(|t| (t.name.clone(), t.state.clone()))
+        .collect::<HashMap<String, State>>();
+    assert_eq!(states["apple"], State::Skipped(CLI_SKIP_MESSAGE.to_string()));
+    assert_eq!(states["turnip"],
+               State::Skipped("the task 'apple' was skipped from the command line".to_string()));
+    assert_eq!(states["egg"], State::Success);
+}
+
+#[test]
+fn wide_jobs_are_not_held_up_by_polling() {
+    use std::time::Instant;
+
+    // a task's dependents start as soon as its group finishes, rather than on the next poll
+    let mut ff = Factfile::new("N/A", "test");
+    let mut wide = vec![];
+    for i in 0..200 {
+        let name = format!("wide-{}", i);
+        let mut task = make_task(&name, &vec![]);
+        task.on_result.continue_job.push(0);
+        ff.add_task_obj(&task);
+        wide.push(name);
+    }
+    let mut last = make_task("last", &wide.iter().map(|n| n.as_str()).collect());
+    last.on_result.continue_job.push(0);
+    ff.add_task_obj(&last);
+
+    let mut options = ExecutionOptions::default();
+    options.poll_interval = ::std::time::Duration::from_secs(60);
+    options.abort_file = Some("./tests/resources/no-such-abort-file".to_string());
+
+    let started = Instant::now();
+    let result = execute_factfile_with_options(&ff, None, quick_success, None, options);
+    assert!(started.elapsed() < ::std::time::Duration::from_secs(10));
+    assert!(result.tasks[1][0].run_result.is_some());
+}
+
+#[test]
+fn tailed_task_output_goes_to_the_console() {
+    let mut ff = Factfile::new("N/A", "test");
+    for name in ["followed", "quiet"].iter() {
+        let mut task = make_task(name, &vec![]);
+        task.command = format!("echo {}", name);
+        task.on_result.continue_job.push(0);
+        ff.add_task_obj(&task);
+    }
+
+    let mut options = ExecutionOptions::default();
+    options.tail_task = Some("followed".to_string());
+    let result = execute_factfile_with_options(&ff,
+                                               None,
+                                               execution_strategy::execute_os,
+                                               None,
+                                               options);
+
+    for task in result.tasks[0].iter() {
+        let stdout = task.run_result.as_ref().unwrap().stdout.clone();
+        match task.name.as_ref() {
+            "followed" => assert_eq!(stdout, None),
+            _ => assert_eq!(stdout, Some("quiet".to_string())),
+        }
+    }
+}
+
+#[test]
+fn task_outputs_are_passed_to_dependents() {
+    let mut ff = Factfile::new("N/A", "test");
+    let mut count = make_task("count", &vec![]);
+    count.command = "echo 'ROWS=42' >> \"$FACTOTUM_OUTPUTS\"; \
+                     echo 'OTHER=1' >> \"$FACTOTUM_OUTPUTS\""
+        .to_string();
+    count.outputs = vec!["ROWS".to_string()];
+    count.on_result.continue_job.push(0);
+    ff.add_task_obj(&count);
+    let mut check = make_task("check", &vec!["count"]);
+    check.command = "test \"$ROWS\" = 42 && test -z \"$OTHER\"".to_string();
+    check.on_result.continue_job.push(0);
+    ff.add_task_obj(&check);
+
+    let result = execute_factfile(&ff, None, execution_strategy::execute_os, None);
+
+    let count_result = result.tasks[0][0].run_result.as_ref().unwrap();
+    assert_eq!(count_result.outputs.get("ROWS"), Some(&"42".to_string()));
+    assert_eq!(count_result.outputs.len(), 1);
+    assert_eq!(result.tasks[1][0].state, State::Success);
+}
+
+#[test]
+fn task_outputs_are_only_the_declared_ones() {
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+
+    let path = env::temp_dir().join("factotum-read-task-outputs-test.env");
+    {
+        let mut fh = File::create(&path).unwrap();
+        fh.write_all(b"A=1\r\nB = two = 2\nnot a variable\nC=3\nA=11\n").unwrap();
+    }
+    let outputs = read_task_outputs("t",
+                                    &path,
+                                    &vec!["A".to_string(), "B".to_string(), "D".to_string()]);
+    assert_eq!(outputs.into_iter().collect::<Vec<(String, String)>>(),
+               vec![("A".to_string(), "11".to_string()),
+                    ("B".to_string(), " two = 2".to_string())]);
+}
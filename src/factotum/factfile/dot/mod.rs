@@ -15,9 +15,42 @@
 #[cfg(test)]
 mod tests;
 
-use factotum::factfile::Factfile;
+use factotum::factfile::{Factfile, Task};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const CRITICAL_PATH_STYLE: &'static str = " [color=\"red\", style=\"bold\"]";
 
 pub fn generate_graphviz_dot(factfile: &Factfile, start: Option<String>) -> String {
+    let tasks = get_sorted_tasks(factfile, start);
+    render_dot(factfile, &tasks, &vec![])
+}
+
+pub fn generate_critical_path_dot(factfile: &Factfile,
+                                  start: Option<String>,
+                                  durations: &HashMap<String, Duration>)
+                                  -> String {
+    let tasks = get_sorted_tasks(factfile, start);
+    let critical_path = get_critical_path(&tasks, durations);
+    render_dot(factfile, &tasks, &critical_path)
+}
+
+// one "parent<TAB>child" line per dependency, sorted so the output is stable between runs.
+// in a reduced run, only the dependencies between tasks that would run are included
+pub fn generate_edge_list(factfile: &Factfile, start: Option<String>) -> String {
+    let tasks = get_sorted_tasks(factfile, start);
+    let names = tasks.iter().map(|t| t.name.as_str()).collect::<Vec<&str>>();
+    let mut edges = tasks.iter()
+        .flat_map(|t| t.depends_on.iter().map(move |dep| (dep.as_str(), t.name.as_str())))
+        .filter(|&(parent, _)| names.contains(&parent))
+        .collect::<Vec<(&str, &str)>>();
+    edges.sort();
+    edges.iter()
+        .map(|&(parent, child)| format!("{}\t{}\n", parent, child))
+        .collect()
+}
+
+fn get_sorted_tasks(factfile: &Factfile, start: Option<String>) -> Vec<&Task> {
     let tasks = if let Some(start_task) = start {
         factfile.get_tasks_in_order_from(&start_task)
     } else {
@@ -28,21 +61,78 @@ pub fn generate_graphviz_dot(factfile: &Factfile, start: Option<String>) -> Stri
 
     for t in tasks.iter() {
         for task in t.iter() {
-            topologically_sorted_tasks.push(task);
+            topologically_sorted_tasks.push(*task);
+        }
+    }
+
+    topologically_sorted_tasks
+}
+
+// the chain of dependent tasks with the longest total run time, first task first
+pub fn get_critical_path(tasks: &Vec<&Task>, durations: &HashMap<String, Duration>) -> Vec<String> {
+    let no_time = Duration::new(0, 0);
+    let mut finishes: HashMap<&str, (Duration, Option<&str>)> = HashMap::new();
+    let mut last: Option<(&str, Duration)> = None;
+
+    for task in tasks.iter() {
+        let mut slowest_dep: Option<(&str, Duration)> = None;
+        for dep in task.depends_on.iter() {
+            if let Some(&(dep_finish, _)) = finishes.get(dep.as_str()) {
+                if slowest_dep.map(|(_, d)| dep_finish > d).unwrap_or(true) {
+                    slowest_dep = Some((dep.as_str(), dep_finish));
+                }
+            }
+        }
+
+        let finish = durations.get(&task.name).cloned().unwrap_or(no_time) +
+                     slowest_dep.map(|(_, d)| d).unwrap_or(no_time);
+        finishes.insert(task.name.as_str(), (finish, slowest_dep.map(|(name, _)| name)));
+
+        if last.map(|(_, d)| finish > d).unwrap_or(true) {
+            last = Some((task.name.as_str(), finish));
         }
     }
 
+    let mut critical_path = vec![];
+    let mut current = match last {
+        Some((name, finish)) if finish > no_time => Some(name),
+        _ => None,
+    };
+    while let Some(name) = current {
+        critical_path.insert(0, name.to_string());
+        current = finishes.get(name).and_then(|&(_, previous)| previous);
+    }
+    critical_path
+}
+
+fn render_dot(factfile: &Factfile, tasks: &Vec<&Task>, critical_path: &Vec<String>) -> String {
     let title = format!("digraph \"{}\" {{", factfile.name);
 
-    let task_names = topologically_sorted_tasks.iter()
-        .map(|t| format!("    \"{}\"\n", t.name))
+    let task_names = tasks.iter()
+        .map(|t| {
+            let style = if critical_path.contains(&t.name) {
+                CRITICAL_PATH_STYLE
+            } else {
+                ""
+            };
+            format!("    \"{}\"{}\n", t.name, style)
+        })
         .collect::<String>();
 
-    let task_connections = topologically_sorted_tasks.iter()
+    let task_connections = tasks.iter()
         .map(|t| {
             t.depends_on
                 .iter()
-                .map(|dep| format!("    \"{}\" -> \"{}\"\n", dep, t.name))
+                .map(|dep| {
+                    let on_critical_path = critical_path.windows(2)
+                        .any(|pair| &pair[0] == dep && pair[1] == t.name);
+                    let style = if on_critical_path {
+                        CRITICAL_PATH_STYLE
+                    } else {
+                        ""
+                    };
+                    format!("    \"{}\" -> \"{}\"{}\n", dep, t.name, style)
+                })
                 .collect::<String>()
         })
         .collect::<String>();
@@ -17,6 +17,8 @@ use factotum::factfile::Factfile;
 use factotum::tests::make_task;
 use std::io::prelude::*;
 use std::fs::File;
+use std::collections::HashMap;
+use std::time::Duration;
 
 #[inline]
 fn resource(name: &str) -> String {
@@ -110,3 +112,71 @@ fn generate_graphviz_dot_good_short() {
 
     assert_eq!(actual, example);
 }
+
+fn apples_factfile() -> Factfile {
+    let mut ff = Factfile::new("N/A", "Sample job");
+    ff.add_task_obj(&make_task("apple", &vec![]));
+    ff.add_task_obj(&make_task("turnip", &vec![]));
+    ff.add_task_obj(&make_task("orange", &vec!["apple"]));
+    ff.add_task_obj(&make_task("egg", &vec!["apple"]));
+    ff.add_task_obj(&make_task("potato", &vec!["egg"]));
+    ff.add_task_obj(&make_task("chicken", &vec!["potato", "orange"]));
+    ff
+}
+
+fn apples_durations() -> HashMap<String, Duration> {
+    vec![("apple", 2), ("turnip", 5), ("orange", 1), ("egg", 3), ("potato", 4), ("chicken", 1)]
+        .into_iter()
+        .map(|(name, secs)| (name.to_string(), Duration::from_secs(secs)))
+        .collect()
+}
+
+#[test]
+fn generate_critical_path_dot_good() {
+    let example = file_contents(&resource("dot/example_apples_critical.dot"));
+
+    let actual = generate_critical_path_dot(&apples_factfile(), None, &apples_durations());
+
+    print!("ACTUAL:\n{}\n", actual);
+
+    assert_eq!(actual, example);
+}
+
+#[test]
+fn critical_path_follows_slowest_dependency() {
+    let ff = apples_factfile();
+    let tasks = get_sorted_tasks(&ff, None);
+
+    assert_eq!(get_critical_path(&tasks, &apples_durations()),
+               vec!["apple", "egg", "potato", "chicken"]);
+
+    let mut durations = apples_durations();
+    durations.insert("turnip".to_string(), Duration::from_secs(20));
+    assert_eq!(get_critical_path(&tasks, &durations), vec!["turnip"]);
+}
+
+#[test]
+fn critical_path_empty_when_nothing_ran() {
+    let ff = apples_factfile();
+    let tasks = get_sorted_tasks(&ff, None);
+
+    assert!(get_critical_path(&tasks, &HashMap::new()).is_empty());
+    assert_eq!(generate_critical_path_dot(&ff, None, &HashMap::new()),
+               generate_graphviz_dot(&ff, None));
+}
+
+#[test]
+fn generate_edge_list_is_sorted() {
+    let mut ff = Factfile::new("N/A", "Sample job");
+    ff.add_task_obj(&make_task("apple", &vec![]));
+    ff.add_task_obj(&make_task("turnip", &vec![]));
+    ff.add_task_obj(&make_task("orange", &vec!["apple"]));
+    ff.add_task_obj(&make_task("egg", &vec!["apple"]));
+    ff.add_task_obj(&make_task("potato", &vec!["egg"]));
+    ff.add_task_obj(&make_task("chicken", &vec!["potato", "orange"]));
+
+    assert_eq!(generate_edge_list(&ff, None),
+               "apple\tegg\napple\torange\negg\tpotato\norange\tchicken\npotato\tchicken\n");
+    assert_eq!(generate_edge_list(&ff, Some("egg".to_string())),
+               "egg\tpotato\npotato\tchicken\n");
+}
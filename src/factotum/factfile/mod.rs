@@ -18,6 +18,10 @@ mod dot;
 
 use daggy::*;
 use factotum::sequencer;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use rustc_serialize::json::{Json, ToJson};
+use chrono::{DateTime, UTC};
 
 
 pub struct Factfile {
@@ -35,9 +39,208 @@ pub struct Task {
     pub command: String,
     pub arguments: Vec<String>,
     pub on_result: OnResult,
+    pub hosts: Vec<String>,
+    pub host_selection: HostSelection,
+    pub address_selection: AddressSelection,
+    pub capture_stdout: bool,
+    pub capture_stderr: bool,
+    pub retry_count: u32,
+    pub retry_delay_seconds: u64,
+    pub retry_jitter_percent: u32,
+    pub retry_on: Option<Vec<i32>>,
+    pub max_total_duration_seconds: Option<u64>,
+    pub expected_duration_seconds: Option<u64>,
+    pub run_as_user: Option<u32>,
+    pub run_as_group: Option<u32>,
+    pub on_success: Option<String>,
+    pub on_failure: Option<String>,
+    pub precondition: Option<String>,
+    pub not_before: Option<DateTime<UTC>>,
+    pub cwd: Option<String>,
+    pub stdin: Option<String>,
+    pub inherit_stdin: bool,
+    pub outputs: Vec<String>,
+    pub notify: bool,
+    pub tags: BTreeMap<String, String>,
+    pub noop_scope: NoopScope,
+    pub output_encoding: OutputEncoding,
+    pub mutex: Option<String>,
+}
+
+impl ToJson for Task {
+    fn to_json(&self) -> Json {
+        let mut on_result = BTreeMap::new();
+        on_result.insert("terminateJobWithSuccess".to_string(),
+                         self.on_result.terminate_job.to_json());
+        on_result.insert("continueJob".to_string(), self.on_result.continue_job.to_json());
+
+        let mut d = BTreeMap::new();
+        d.insert("name".to_string(), self.name.to_json());
+        d.insert("executor".to_string(), self.executor.to_json());
+        d.insert("command".to_string(), self.command.to_json());
+        d.insert("arguments".to_string(), self.arguments.to_json());
+        d.insert("dependsOn".to_string(), self.depends_on.to_json());
+        d.insert("onResult".to_string(), Json::Object(on_result));
+        d.insert("hosts".to_string(), self.hosts.to_json());
+        d.insert("hostSelection".to_string(),
+                 match self.host_selection {
+                         HostSelection::RoundRobin => "round-robin",
+                         HostSelection::Random => "random",
+                     }
+                     .to_json());
+        d.insert("addressSelection".to_string(),
+                 match self.address_selection {
+                         AddressSelection::Hostname => "hostname",
+                         AddressSelection::First => "first",
+                         AddressSelection::Random => "random",
+                     }
+                     .to_json());
+        d.insert("captureStdout".to_string(), self.capture_stdout.to_json());
+        d.insert("captureStderr".to_string(), self.capture_stderr.to_json());
+        d.insert("retryCount".to_string(), self.retry_count.to_json());
+        d.insert("retryDelaySeconds".to_string(), self.retry_delay_seconds.to_json());
+        d.insert("retryJitterPercent".to_string(), self.retry_jitter_percent.to_json());
+        d.insert("inheritStdin".to_string(), self.inherit_stdin.to_json());
+        d.insert("notify".to_string(), self.notify.to_json());
+        d.insert("tags".to_string(), self.tags.to_json());
+        d.insert("noopScope".to_string(),
+                 match self.noop_scope {
+                         NoopScope::Branch => "branch",
+                         NoopScope::Job => "job",
+                     }
+                     .to_json());
+        d.insert("outputEncoding".to_string(),
+                 match self.output_encoding {
+                         OutputEncoding::Utf8 => "utf-8",
+                         OutputEncoding::Latin1 => "latin-1",
+                     }
+                     .to_json());
+
+        // users and groups have already been resolved to their ids
+        let optional = vec![("maxTotalDurationSeconds",
+                             self.max_total_duration_seconds.map(|s| s.to_json())),
+                            ("expectedDurationSeconds",
+                             self.expected_duration_seconds.map(|s| s.to_json())),
+                            ("retryOn", self.retry_on.as_ref().map(|codes| codes.to_json())),
+                            ("runAsUser", self.run_as_user.map(|u| u.to_string().to_json())),
+                            ("runAsGroup", self.run_as_group.map(|g| g.to_string().to_json())),
+                            ("onSuccess", self.on_success.as_ref().map(|h| h.to_json())),
+                            ("onFailure", self.on_failure.as_ref().map(|h| h.to_json())),
+                            ("precondition", self.precondition.as_ref().map(|p| p.to_json())),
+                            ("notBefore",
+                             self.not_before
+                                 .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string().to_json())),
+                            ("cwd", self.cwd.as_ref().map(|c| c.to_json())),
+                            ("stdin", self.stdin.as_ref().map(|i| i.to_json())),
+                            ("mutex", self.mutex.as_ref().map(|m| m.to_json())),
+                            ("outputs",
+                             if self.outputs.is_empty() {
+                                 None
+                             } else {
+                                 Some(self.outputs.to_json())
+                             })];
+        for (key, value) in optional {
+            if let Some(v) = value {
+                d.insert(key.to_string(), v);
+            }
+        }
+
+        Json::Object(d)
+    }
+}
+
+impl Default for Task {
+    fn default() -> Self {
+        Task {
+            name: String::new(),
+            depends_on: vec![],
+            executor: String::new(),
+            command: String::new(),
+            arguments: vec![],
+            on_result: OnResult::default(),
+            hosts: vec![],
+            host_selection: HostSelection::default(),
+            address_selection: AddressSelection::default(),
+            capture_stdout: true,
+            capture_stderr: true,
+            retry_count: 0,
+            retry_delay_seconds: 0,
+            retry_jitter_percent: 0,
+            retry_on: None,
+            max_total_duration_seconds: None,
+            expected_duration_seconds: None,
+            run_as_user: None,
+            run_as_group: None,
+            on_success: None,
+            on_failure: None,
+            precondition: None,
+            not_before: None,
+            cwd: None,
+            stdin: None,
+            inherit_stdin: false,
+            outputs: vec![],
+            notify: true,
+            tags: BTreeMap::new(),
+            noop_scope: NoopScope::default(),
+            output_encoding: OutputEncoding::default(),
+            mutex: None,
+        }
+    }
 }
 
 #[derive(Clone,Debug, PartialEq)]
+pub enum HostSelection {
+    RoundRobin,
+    Random,
+}
+
+impl Default for HostSelection {
+    fn default() -> Self {
+        HostSelection::RoundRobin
+    }
+}
+
+// what ssh is given once a host is picked: the name as written, or one of its resolved addresses
+#[derive(Clone,Debug, PartialEq)]
+pub enum AddressSelection {
+    Hostname,
+    First,
+    Random,
+}
+
+impl Default for AddressSelection {
+    fn default() -> Self {
+        AddressSelection::Hostname
+    }
+}
+
+// what's skipped when a task asks for an early finish (terminateJobWithSuccess)
+#[derive(Clone,Debug, PartialEq)]
+pub enum NoopScope {
+    Branch,
+    Job,
+}
+
+impl Default for NoopScope {
+    fn default() -> Self {
+        NoopScope::Branch
+    }
+}
+
+// how the task's stdout/err is decoded when it isn't valid UTF-8
+#[derive(Clone,Debug, PartialEq)]
+pub enum OutputEncoding {
+    Utf8,
+    Latin1,
+}
+
+impl Default for OutputEncoding {
+    fn default() -> Self {
+        OutputEncoding::Utf8
+    }
+}
+
+#[derive(Clone,Debug, PartialEq, Default)]
 pub struct OnResult {
     pub terminate_job: Vec<i32>,
     pub continue_job: Vec<i32>,
@@ -46,17 +249,7 @@ pub struct OnResult {
 impl Factfile {
     pub fn new<S: Into<String>>(raw: S, name: S) -> Factfile {
         let mut new_dag = Dag::<Task, ()>::new();
-        let root_task = Task {
-            name: "FactotumJob".to_string(),
-            depends_on: vec![],
-            executor: "".to_string(),
-            command: "".to_string(),
-            arguments: vec![],
-            on_result: OnResult {
-                terminate_job: vec![],
-                continue_job: vec![],
-            },
-        };
+        let root_task = Task { name: "FactotumJob".to_string(), ..Default::default() };
         let parent = new_dag.add_node(root_task);
         Factfile {
             name: name.into(),
@@ -70,6 +263,37 @@ impl Factfile {
         dot::generate_graphviz_dot(&self, start_task)
     }
 
+    pub fn as_edge_list(&self, start_task: Option<String>) -> String {
+        dot::generate_edge_list(&self, start_task)
+    }
+
+    // highlights the slowest chain of tasks, given how long each one took to run
+    pub fn as_critical_path_dotfile(&self,
+                                    start_task: Option<String>,
+                                    durations: &HashMap<String, Duration>)
+                                    -> String {
+        dot::generate_critical_path_dot(&self, start_task, durations)
+    }
+
+    // the factfile as it will run, once --env, defaults and argument files are applied
+    pub fn as_effective_json(&self) -> String {
+        let tasks = self.get_tasks_in_order()
+            .iter()
+            .flat_map(|group| group.iter().map(|task| task.to_json()))
+            .collect::<Vec<Json>>();
+
+        let mut data = BTreeMap::new();
+        data.insert("name".to_string(), self.name.to_json());
+        data.insert("tasks".to_string(), Json::Array(tasks));
+
+        let mut factfile = BTreeMap::new();
+        factfile.insert("schema".to_string(),
+                        "iglu:com.snowplowanalytics.factotum/factfile/jsonschema/1-0-0".to_json());
+        factfile.insert("data".to_string(), Json::Object(data));
+
+        format!("{}\n", Json::Object(factfile).pretty())
+    }
+
     fn get_tasks_in_order_from_node_index<'a>(&'a self,
                                               start_node_index: NodeIndex)
                                               -> Vec<Vec<&'a Task>> {
@@ -102,6 +326,15 @@ impl Factfile {
         sequencer::find_task_recursive(&self.dag, name, self.root)
     }
 
+    // for changes that leave the task's place in the DAG alone
+    pub fn get_task_mut(&mut self, name: &str) -> Option<&mut Task> {
+        let found = self.find_task_by_name(name).map(|(idx, _)| idx);
+        match found {
+            Some(idx) => Some(&mut self.dag[idx]),
+            None => None,
+        }
+    }
+
     pub fn can_job_run_from_task(&self, name: &str) -> Result<bool, &'static str> {
         let task_index = self.find_task_by_name(name);
         if let Some((node_index, _)) = task_index {
@@ -113,16 +346,6 @@ impl Factfile {
 
     // this is used in tests
     #[cfg(test)]
-    pub fn add_task_obj(&mut self, task: &Task) {
-        self.add_task(&task.name,
-                      &task.depends_on.iter().map(AsRef::as_ref).collect(),
-                      &task.executor,
-                      &task.command,
-                      &task.arguments.iter().map(AsRef::as_ref).collect(),
-                      &task.on_result.terminate_job,
-                      &task.on_result.continue_job) // TODO should this function really be the main one? or even the only one, its nicer to pass a struct as it has named params
-    }
-
     pub fn add_task(&mut self,
                     name: &str,
                     depends_on: &Vec<&str>,
@@ -131,24 +354,37 @@ impl Factfile {
                     args: &Vec<&str>,
                     terminate_job_on: &Vec<i32>,
                     continue_job_on: &Vec<i32>) {
+        self.add_task_obj(&Task {
+            name: String::from(name),
+            depends_on: depends_on.iter().map(|s| String::from(*s)).collect(),
+            executor: String::from(executor),
+            command: String::from(command),
+            arguments: args.iter().map(|s| String::from(*s)).collect(),
+            on_result: OnResult {
+                terminate_job: terminate_job_on.iter().map(|i| *i).collect(),
+                continue_job: continue_job_on.iter().map(|i| *i).collect(),
+            },
+            ..Default::default()
+        })
+    }
+
+    pub fn add_task_obj(&mut self, task: &Task) {
         // TODO ensure all fields are validated, Result is returned rather than panic (and get tests in shape for this validation)
 
-        if let Some((_, task)) = self.find_task_by_name(name) {
-            panic!("Key '{}' already exists!", task.name)
+        if let Some((_, existing)) = self.find_task_by_name(&task.name) {
+            panic!("Key '{}' already exists!", existing.name)
         }
 
-        if depends_on.len() > 0 {
-            if depends_on.iter().any(|s| s == &name) {
+        if task.depends_on.len() > 0 {
+            if task.depends_on.iter().any(|s| s == &task.name) {
                 panic!("A task cannot depend on itself");
             }
 
             let mut parents: Vec<NodeIndex> = vec![];
-            let mut deps: Vec<String> = vec![];
 
-            for dependency in depends_on {
+            for dependency in task.depends_on.iter() {
                 if let Some((idx, _)) = self.find_task_by_name(dependency) {
                     parents.push(idx);
-                    deps.push(dependency.to_string());
                 } else {
                     panic!("A task must have it's dependencies already defined - couldn't find \
                             definition of {}",
@@ -156,17 +392,7 @@ impl Factfile {
                 }
             }
 
-            let node = self.dag.add_node(Task {
-                name: String::from(name),
-                depends_on: deps,
-                executor: String::from(executor),
-                command: String::from(command),
-                arguments: args.iter().map(|s| String::from(*s)).collect(),
-                on_result: OnResult {
-                    terminate_job: terminate_job_on.iter().map(|i| *i).collect(),
-                    continue_job: continue_job_on.iter().map(|i| *i).collect(),
-                },
-            });
+            let node = self.dag.add_node(task.clone());
 
             for parent in parents {
                 if let Err(_) = self.dag.add_edge(parent, node, ()) {
@@ -176,18 +402,7 @@ impl Factfile {
                 }
             }
         } else {
-            let new_task = Task {
-                name: String::from(name),
-                depends_on: vec![],
-                executor: String::from(executor),
-                command: String::from(command),
-                arguments: args.iter().map(|s| String::from(*s)).collect(),
-                on_result: OnResult {
-                    terminate_job: terminate_job_on.iter().map(|i| *i).collect(),
-                    continue_job: continue_job_on.iter().map(|i| *i).collect(),
-                },
-            };
-            self.dag.add_child(self.root, (), new_task);
+            self.dag.add_child(self.root, (), task.clone());
         }
     }
 }
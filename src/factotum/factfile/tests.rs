@@ -176,3 +176,17 @@ fn running_jobs_from_existing_task() {
 
     compare_tasks(expected, actual);
 }
+
+#[test]
+fn get_task_mut_changes_the_task_in_place() {
+    let mut ff = Factfile::new("N/A", "test");
+    ff.add_task_obj(&make_task("apple", &vec![]));
+    ff.add_task_obj(&make_task("turnip", &vec!["apple"]));
+
+    ff.get_task_mut("turnip").unwrap().executor = "ssh".to_string();
+    assert!(ff.get_task_mut("potato").is_none());
+
+    let tasks = ff.get_tasks_in_order();
+    assert_eq!(tasks[1][0].name, "turnip");
+    assert_eq!(tasks[1][0].executor, "ssh");
+}
@@ -0,0 +1,204 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::task_list::Task;
+use factotum::factfile::Task as FactfileTask;
+use factotum::runrecord;
+use rusqlite::Connection;
+use chrono::{DateTime, NaiveDate, UTC};
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const CREATE_HISTORY_TABLE: &'static str = "CREATE TABLE IF NOT EXISTS task_runs (
+    run_id TEXT NOT NULL,
+    job TEXT NOT NULL,
+    task TEXT NOT NULL,
+    state TEXT NOT NULL,
+    duration_seconds REAL,
+    return_code INTEGER,
+    started TEXT,
+    recorded TEXT NOT NULL
+)";
+
+const INSERT_TASK_RUN: &'static str = "INSERT INTO task_runs (run_id, job, task, state, \
+                                       duration_seconds, return_code, started, recorded) \
+                                       VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+
+const HAS_HISTORY_TABLE: &'static str = "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' \
+                                         AND name = 'task_runs'";
+
+// recorded is always an RFC 3339 UTC timestamp, so comparing the strings compares the times
+const SELECT_TASK_RUNS: &'static str = "SELECT run_id, job, task, state, duration_seconds, \
+                                        return_code, started, recorded FROM task_runs WHERE \
+                                        recorded >= ?1 ORDER BY recorded, rowid";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskRun {
+    pub run_id: String,
+    pub job: String,
+    pub task: String,
+    pub state: String,
+    pub duration_seconds: Option<f64>,
+    pub return_code: Option<i32>,
+    pub started: Option<String>,
+    pub recorded: String,
+}
+
+impl ToJson for TaskRun {
+    fn to_json(&self) -> Json {
+        let mut d = BTreeMap::new();
+        d.insert("runId".to_string(), self.run_id.to_json());
+        d.insert("job".to_string(), self.job.to_json());
+        d.insert("task".to_string(), self.task.to_json());
+        d.insert("state".to_string(), self.state.to_json());
+        d.insert("durationSeconds".to_string(), self.duration_seconds.to_json());
+        d.insert("returnCode".to_string(), self.return_code.to_json());
+        d.insert("started".to_string(), self.started.to_json());
+        d.insert("recorded".to_string(), self.recorded.to_json());
+        Json::Object(d)
+    }
+}
+
+// a date on its own means midnight UTC at the start of that day
+pub fn get_since(date: &str) -> Result<DateTime<UTC>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(date) {
+        return Ok(timestamp.with_timezone(&UTC));
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| DateTime::<UTC>::from_utc(d.and_hms(0, 0, 0), UTC))
+        .map_err(|_| {
+            format!("'{}' is neither a date (YYYY-MM-DD) nor an RFC 3339 timestamp",
+                    date)
+        })
+}
+
+pub fn read_task_runs(db_path: &str,
+                      since: &Option<DateTime<UTC>>)
+                      -> Result<Vec<TaskRun>, String> {
+    // opening a database that isn't there would quietly create an empty one
+    if !Path::new(db_path).exists() {
+        return Err(format!("the history database '{}' doesn't exist", db_path));
+    }
+    let conn = try!(Connection::open(db_path)
+        .map_err(|e| format!("couldn't open history database '{}': {}", db_path, e)));
+
+    let tables: i64 = try!(conn.query_row(HAS_HISTORY_TABLE, &[], |r| r.get(0))
+        .map_err(|e| format!("couldn't read the history database '{}': {}", db_path, e)));
+    if tables == 0 {
+        return Ok(vec![]);
+    }
+
+    let since_text = since.as_ref().map_or(String::new(), |t| t.to_rfc3339());
+    let mut stmt = try!(conn.prepare(SELECT_TASK_RUNS)
+        .map_err(|e| format!("couldn't query the history database '{}': {}", db_path, e)));
+    let rows = try!(stmt.query_map(&[&since_text], |r| {
+            TaskRun {
+                run_id: r.get(0),
+                job: r.get(1),
+                task: r.get(2),
+                state: r.get(3),
+                duration_seconds: r.get(4),
+                return_code: r.get(5),
+                started: r.get(6),
+                recorded: r.get(7),
+            }
+        })
+        .map_err(|e| format!("couldn't query the history database '{}': {}", db_path, e)));
+
+    let mut task_runs = vec![];
+    for row in rows {
+        task_runs.push(try!(row.map_err(|e| {
+            format!("couldn't read the history database '{}': {}", db_path, e)
+        })));
+    }
+    Ok(task_runs)
+}
+
+pub fn to_history_table(task_runs: &Vec<TaskRun>) -> String {
+    let header = vec!["RECORDED", "RUN", "JOB", "TASK", "STATE", "RETURN CODE", "SECONDS"];
+    let rows: Vec<Vec<String>> = task_runs.iter()
+        .map(|r| {
+            vec![r.recorded.clone(),
+                 r.run_id.clone(),
+                 r.job.clone(),
+                 r.task.clone(),
+                 r.state.clone(),
+                 r.return_code.map_or("-".to_string(), |c| c.to_string()),
+                 r.duration_seconds.map_or("-".to_string(), |d| format!("{:.1}", d))]
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows.iter() {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        cells.iter()
+            .enumerate()
+            .map(|(i, c)| format!("{:1$}", c, widths[i]))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_right()
+            .to_string()
+    };
+
+    let mut lines = vec![format_row(header)];
+    for row in rows.iter() {
+        lines.push(format_row(row.iter().map(|c| c.as_str()).collect()));
+    }
+    lines.join("\n")
+}
+
+pub fn record_run(db_path: &str,
+                  run_id: &str,
+                  job_name: &str,
+                  tasks: &Vec<&Task<&FactfileTask>>)
+                  -> Result<(), String> {
+    let mut conn = try!(Connection::open(db_path)
+        .map_err(|e| format!("couldn't open history database '{}': {}", db_path, e)));
+    try!(conn.execute_batch(CREATE_HISTORY_TABLE)
+        .map_err(|e| format!("couldn't create the history table in '{}': {}", db_path, e)));
+
+    let tx = try!(conn.transaction()
+        .map_err(|e| format!("couldn't start a transaction in '{}': {}", db_path, e)));
+    let recorded = UTC::now().to_rfc3339();
+
+    for task in tasks.iter() {
+        let duration = task.run_result.as_ref().map(|r| {
+            r.duration.as_secs() as f64 + r.duration.subsec_nanos() as f64 / 1_000_000_000_f64
+        });
+        let return_code = task.run_result.as_ref().map(|r| r.return_code);
+        let started = task.run_started.as_ref().map(|t| t.to_rfc3339());
+
+        try!(tx.execute(INSERT_TASK_RUN,
+                     &[&run_id,
+                       &job_name,
+                       &task.name,
+                       &runrecord::state_name(&task.state),
+                       &duration,
+                       &return_code,
+                       &started,
+                       &recorded])
+            .map_err(|e| format!("couldn't record task '{}' in '{}': {}", task.name, db_path, e)));
+    }
+
+    tx.commit().map_err(|e| format!("couldn't save history to '{}': {}", db_path, e))
+}
@@ -0,0 +1,104 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use factotum::tests::make_task;
+use factotum::executor::task_list::{Task, State};
+use factotum::executor::execution_strategy::RunResult;
+use factotum::factfile::Task as FactfileTask;
+use rusqlite::Connection;
+use std::env;
+use std::fs;
+
+#[test]
+fn record_run_appends_a_row_per_task() {
+    let db = env::temp_dir().join("factotum-history-test.db");
+    let db_path = db.to_str().unwrap();
+    let _ = fs::remove_file(&db);
+
+    let apple_spec = make_task("apple", &vec![]);
+    let turnip_spec = make_task("turnip", &vec!["apple"]);
+    let mut apple = Task::<&FactfileTask>::new("apple", &apple_spec);
+    apple.state = State::Failed("bad things".to_string());
+    apple.run_result = Some(RunResult { return_code: 3, ..Default::default() });
+    let mut turnip = Task::<&FactfileTask>::new("turnip", &turnip_spec);
+    turnip.state = State::Skipped("the task 'apple' failed".to_string());
+
+    assert_eq!(record_run(db_path, "run-1", "job", &vec![&apple, &turnip]), Ok(()));
+    assert_eq!(record_run(db_path, "run-2", "job", &vec![&apple, &turnip]), Ok(()));
+
+    let conn = Connection::open(db_path).unwrap();
+    let rows: i64 = conn.query_row("SELECT COUNT(*) FROM task_runs", &[], |r| r.get(0)).unwrap();
+    assert_eq!(rows, 4);
+
+    let (state, return_code): (String, Option<i32>) =
+        conn.query_row("SELECT state, return_code FROM task_runs WHERE run_id = 'run-1' AND \
+                        task = 'apple'",
+                       &[],
+                       |r| (r.get(0), r.get(1)))
+            .unwrap();
+    assert_eq!(state, "FAILED");
+    assert_eq!(return_code, Some(3));
+
+    fs::remove_file(&db).unwrap();
+}
+
+#[test]
+fn since_takes_dates_and_timestamps() {
+    use chrono::{TimeZone, UTC};
+
+    assert_eq!(get_since("2017-03-01"), Ok(UTC.ymd(2017, 3, 1).and_hms(0, 0, 0)));
+    assert_eq!(get_since("2017-03-01T12:30:05+01:00"),
+               Ok(UTC.ymd(2017, 3, 1).and_hms(11, 30, 5)));
+    assert!(get_since("last tuesday").is_err());
+}
+
+#[test]
+fn read_task_runs_filters_by_recorded_time() {
+    let db = env::temp_dir().join("factotum-history-query-test.db");
+    let db_path = db.to_str().unwrap();
+    let _ = fs::remove_file(&db);
+
+    assert!(read_task_runs(db_path, &None).is_err());
+
+    let apple_spec = make_task("apple", &vec![]);
+    let mut apple = Task::<&FactfileTask>::new("apple", &apple_spec);
+    apple.state = State::Success;
+    apple.run_result = Some(RunResult { return_code: 0, ..Default::default() });
+    assert_eq!(record_run(db_path, "run-1", "job", &vec![&apple]), Ok(()));
+
+    let all = read_task_runs(db_path, &None).unwrap();
+    assert_eq!(all.len(), 1);
+    assert_eq!(all[0].run_id, "run-1");
+    assert_eq!(all[0].state, "SUCCEEDED");
+    assert_eq!(all[0].return_code, Some(0));
+    assert!(to_history_table(&all).lines().nth(1).unwrap().contains("run-1"));
+
+    let future = get_since("2999-01-01").unwrap();
+    assert_eq!(read_task_runs(db_path, &Some(future)), Ok(vec![]));
+
+    fs::remove_file(&db).unwrap();
+}
+
+#[test]
+fn read_task_runs_from_a_database_without_history_is_empty() {
+    let db = env::temp_dir().join("factotum-history-empty-test.db");
+    let db_path = db.to_str().unwrap();
+    let _ = fs::remove_file(&db);
+    Connection::open(db_path).unwrap().execute_batch("CREATE TABLE other (id INTEGER)").unwrap();
+
+    assert_eq!(read_task_runs(db_path, &None), Ok(vec![]));
+
+    fs::remove_file(&db).unwrap();
+}
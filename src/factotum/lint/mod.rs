@@ -0,0 +1,135 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::factfile::{Factfile, Task};
+use std::collections::HashMap;
+use std::fmt;
+
+// the executors factotum knows how to run - anything else is run like "shell"
+pub const RECOMMENDED_EXECUTORS: [&'static str; 2] = ["shell", "ssh"];
+
+// chains shorter than this are common enough not to be worth a mention
+pub const LONG_CHAIN_LENGTH: usize = 5;
+
+#[derive(Debug, PartialEq, Clone, Copy, PartialOrd, Ord, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{}",
+               match *self {
+                   Severity::Error => "error",
+                   Severity::Warning => "warning",
+                   Severity::Info => "info",
+               })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub task: String,
+    pub message: String,
+}
+
+impl Finding {
+    fn new(severity: Severity, task: &str, message: String) -> Self {
+        Finding {
+            severity: severity,
+            task: task.to_string(),
+            message: message,
+        }
+    }
+}
+
+pub fn lint(factfile: &Factfile) -> Vec<Finding> {
+    let tasks = factfile.get_tasks_in_order()
+        .into_iter()
+        .flat_map(|group| group.into_iter())
+        .collect::<Vec<&Task>>();
+
+    let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+    for task in tasks.iter() {
+        for dep in task.depends_on.iter() {
+            children.entry(&dep[..]).or_insert(vec![]).push(&task.name[..]);
+        }
+    }
+    let child_count = |name: &str| children.get(name).map_or(0, |c| c.len());
+
+    let mut findings = vec![];
+
+    for task in tasks.iter() {
+        if task.command.trim().is_empty() {
+            findings.push(Finding::new(Severity::Error,
+                                       &task.name,
+                                       format!("the task '{}' has an empty command", task.name)));
+        }
+
+        if !RECOMMENDED_EXECUTORS.contains(&&task.executor[..]) {
+            findings.push(Finding::new(Severity::Warning,
+                                       &task.name,
+                                       format!("the task '{}' uses the executor '{}', which \
+                                                factotum runs like 'shell' (use shell or ssh)",
+                                               task.name,
+                                               task.executor)));
+        }
+
+        if tasks.len() > 1 && task.depends_on.is_empty() && child_count(&task.name) == 0 {
+            findings.push(Finding::new(Severity::Warning,
+                                       &task.name,
+                                       format!("the task '{}' is an orphan - it has no \
+                                                dependencies and nothing depends on it",
+                                               task.name)));
+        }
+    }
+
+    // a chain is a run of tasks that each have one child, which has no other parent
+    let continues_chain = |task: &Task| {
+        task.depends_on.len() == 1 && child_count(&task.depends_on[0]) == 1
+    };
+    let by_name = tasks.iter().map(|t| (&t.name[..], *t)).collect::<HashMap<&str, &Task>>();
+    for task in tasks.iter().filter(|t| !continues_chain(t)) {
+        let mut chain = vec![&task.name[..]];
+        let mut current = *task;
+        while child_count(&current.name) == 1 {
+            let child = by_name[children[&current.name[..]][0]];
+            if !continues_chain(child) {
+                break;
+            }
+            chain.push(&child.name[..]);
+            current = child;
+        }
+        if chain.len() >= LONG_CHAIN_LENGTH {
+            findings.push(Finding::new(Severity::Info,
+                                       &task.name,
+                                       format!("the tasks '{}' to '{}' are a chain of {} that \
+                                                can only run one at a time - check each really \
+                                                needs the one before",
+                                               chain[0],
+                                               chain[chain.len() - 1],
+                                               chain.len())));
+        }
+    }
+
+    findings.sort_by(|a, b| a.severity.cmp(&b.severity));
+    findings
+}
@@ -0,0 +1,90 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use factotum::tests::make_task;
+use factotum::factfile::{Factfile, Task};
+
+fn shell_task(name: &str, depends_on: &Vec<&str>) -> Task {
+    Task {
+        executor: "shell".to_string(),
+        command: "echo".to_string(),
+        ..make_task(name, depends_on)
+    }
+}
+
+#[test]
+fn healthy_factfiles_have_no_findings() {
+    let mut ff = Factfile::new("N/A", "test");
+    ff.add_task_obj(&shell_task("apple", &vec![]));
+    ff.add_task_obj(&shell_task("turnip", &vec!["apple"]));
+    ff.add_task_obj(&shell_task("potato", &vec!["apple"]));
+    assert_eq!(lint(&ff), vec![]);
+
+    let mut single = Factfile::new("N/A", "test");
+    single.add_task_obj(&shell_task("apple", &vec![]));
+    assert_eq!(lint(&single), vec![]);
+}
+
+#[test]
+fn findings_point_at_the_task() {
+    let mut ff = Factfile::new("N/A", "test");
+    ff.add_task_obj(&shell_task("apple", &vec![]));
+    ff.add_task_obj(&Task { command: " ".to_string(), ..shell_task("turnip", &vec!["apple"]) });
+    ff.add_task_obj(&Task { executor: "python".to_string(), ..shell_task("orphan", &vec![]) });
+
+    assert_eq!(lint(&ff),
+               vec![Finding {
+                        severity: Severity::Error,
+                        task: "turnip".to_string(),
+                        message: "the task 'turnip' has an empty command".to_string(),
+                    },
+                    Finding {
+                        severity: Severity::Warning,
+                        task: "orphan".to_string(),
+                        message: "the task 'orphan' uses the executor 'python', which factotum \
+                                  runs like 'shell' (use shell or ssh)"
+                            .to_string(),
+                    },
+                    Finding {
+                        severity: Severity::Warning,
+                        task: "orphan".to_string(),
+                        message: "the task 'orphan' is an orphan - it has no dependencies and \
+                                  nothing depends on it"
+                            .to_string(),
+                    }]);
+}
+
+#[test]
+fn long_chains_are_reported_once() {
+    let mut ff = Factfile::new("N/A", "test");
+    ff.add_task_obj(&shell_task("a", &vec![]));
+    ff.add_task_obj(&shell_task("side", &vec!["a"]));
+    ff.add_task_obj(&shell_task("b", &vec!["a"]));
+    ff.add_task_obj(&shell_task("c", &vec!["b"]));
+    ff.add_task_obj(&shell_task("d", &vec!["c"]));
+    ff.add_task_obj(&shell_task("e", &vec!["d"]));
+    ff.add_task_obj(&shell_task("f", &vec!["e"]));
+
+    let findings = lint(&ff);
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].severity, Severity::Info);
+    assert_eq!(findings[0].task, "b");
+    assert_eq!(findings[0].message,
+               "the tasks 'b' to 'f' are a chain of 5 that can only run one at a time - check \
+                each really needs the one before");
+
+    ff.add_task_obj(&shell_task("g", &vec!["c"]));
+    assert_eq!(lint(&ff), vec![]);
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::task_list::Task;
+use factotum::factfile::Task as FactfileTask;
+use factotum::runrecord;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use rustc_serialize::json;
+use tar::{Builder, Header};
+
+#[derive(RustcEncodable, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct ManifestTask {
+    pub name: String,
+    pub state: String,
+    pub returnCode: Option<i32>,
+    pub stdoutFile: Option<String>,
+    pub stderrFile: Option<String>,
+}
+
+#[derive(RustcEncodable, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct Manifest {
+    pub jobName: String,
+    pub runId: String,
+    pub tasks: Vec<ManifestTask>,
+}
+
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+            c
+        } else {
+            '_'
+        })
+        .collect()
+}
+
+fn append_file<W: ::std::io::Write>(builder: &mut Builder<W>,
+                                    path: &str,
+                                    contents: &[u8])
+                                    -> Result<(), String> {
+    let mut header = Header::new_gnu();
+    try!(header.set_path(path).map_err(|e| format!("couldn't add '{}': {}", path, e)));
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append(&header, contents).map_err(|e| format!("couldn't add '{}': {}", path, e))
+}
+
+// the bundle holds each task's output under tasks/<index>-<name>/, plus manifest.json
+// describing the state of every task
+pub fn to_bundle(job_name: &str,
+                 run_id: &str,
+                 tasks: &Vec<&Task<&FactfileTask>>)
+                 -> Result<Vec<u8>, String> {
+    let mut builder = Builder::new(GzEncoder::new(vec![], Compression::Default));
+    let mut manifest = Manifest {
+        jobName: job_name.to_string(),
+        runId: run_id.to_string(),
+        tasks: vec![],
+    };
+
+    for (idx, task) in tasks.iter().enumerate() {
+        // the index keeps tasks apart if two names sanitise to the same thing
+        let task_dir = format!("tasks/{}-{}", idx + 1, safe_file_name(&task.name));
+        let mut stdout_file = None;
+        let mut stderr_file = None;
+
+        if let Some(ref result) = task.run_result {
+            if let Some(ref stdout) = result.stdout {
+                let path = format!("{}/stdout.log", task_dir);
+                try!(append_file(&mut builder, &path, stdout.as_bytes()));
+                stdout_file = Some(path);
+            }
+            if let Some(ref stderr) = result.stderr {
+                let path = format!("{}/stderr.log", task_dir);
+                try!(append_file(&mut builder, &path, stderr.as_bytes()));
+                stderr_file = Some(path);
+            }
+        }
+
+        manifest.tasks.push(ManifestTask {
+            name: task.name.clone(),
+            state: runrecord::state_name(&task.state).to_string(),
+            returnCode: task.run_result.as_ref().map(|r| r.return_code),
+            stdoutFile: stdout_file,
+            stderrFile: stderr_file,
+        });
+    }
+
+    let manifest_json = json::encode(&manifest).unwrap();
+    try!(append_file(&mut builder, "manifest.json", manifest_json.as_bytes()));
+
+    let encoder = try!(builder.into_inner()
+        .map_err(|e| format!("couldn't finish the bundle: {}", e)));
+    encoder.finish().map_err(|e| format!("couldn't compress the bundle: {}", e))
+}
+
+pub fn write_bundle(path: &str,
+                    job_name: &str,
+                    run_id: &str,
+                    tasks: &Vec<&Task<&FactfileTask>>)
+                    -> Result<(), String> {
+    let bundle = try!(to_bundle(job_name, run_id, tasks));
+    runrecord::write_file_atomically(path, &bundle)
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use factotum::tests::make_task;
+use factotum::executor::task_list::{Task, State};
+use factotum::executor::execution_strategy::RunResult;
+use factotum::factfile::Task as FactfileTask;
+use flate2::read::GzDecoder;
+use std::io::Read;
+use tar::Archive;
+
+#[test]
+fn bundle_has_task_output_and_manifest() {
+    let apple_spec = make_task("apple", &vec![]);
+    let turnip_spec = make_task("tur nip", &vec!["apple"]);
+    let mut apple = Task::<&FactfileTask>::new("apple", &apple_spec);
+    apple.state = State::Failed("bad things".to_string());
+    apple.run_result = Some(RunResult {
+        return_code: 3,
+        stdout: Some("hello".to_string()),
+        stderr: Some("oh no".to_string()),
+        ..Default::default()
+    });
+    let mut turnip = Task::<&FactfileTask>::new("tur nip", &turnip_spec);
+    turnip.state = State::Skipped("the task 'apple' failed".to_string());
+
+    let bundle = to_bundle("job", "run-1", &vec![&apple, &turnip]).unwrap();
+
+    let mut archive = Archive::new(GzDecoder::new(&bundle[..]).unwrap());
+    let mut files = vec![];
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let path = entry.path().unwrap().to_str().unwrap().to_string();
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).unwrap();
+        files.push((path, contents));
+    }
+
+    assert_eq!(files[0], ("tasks/1-apple/stdout.log".to_string(), "hello".to_string()));
+    assert_eq!(files[1], ("tasks/1-apple/stderr.log".to_string(), "oh no".to_string()));
+    assert_eq!(files[2].0, "manifest.json");
+    assert_eq!(files[2].1,
+               "{\"jobName\":\"job\",\"runId\":\"run-1\",\"tasks\":[{\"name\":\"apple\",\
+                \"state\":\"FAILED\",\"returnCode\":3,\"stdoutFile\":\"tasks/1-apple/stdout.log\",\
+                \"stderrFile\":\"tasks/1-apple/stderr.log\"},{\"name\":\"tur nip\",\"state\":\
+                \"SKIPPED\",\"returnCode\":null,\"stdoutFile\":null,\"stderrFile\":null}]}");
+    assert_eq!(files.len(), 3);
+}
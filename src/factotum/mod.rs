@@ -17,6 +17,16 @@ pub mod parser;
 pub mod executor;
 pub mod sequencer;
 pub mod webhook;
+pub mod runrecord;
+pub mod history;
+pub mod prometheus;
+pub mod logbundle;
+pub mod eventsocket;
+pub mod resultexport;
+pub mod runlock;
+pub mod chrometrace;
+pub mod lint;
+pub mod eventsfile;
 
 #[cfg(test)]
 mod tests;
@@ -19,10 +19,17 @@ pub mod schemavalidator;
 
 use std::io::prelude::*;
 use std::fs::File;
-use rustc_serialize::json::{self, Json};
+use std::path::Path;
+use std::collections::{BTreeMap, HashSet};
+use rustc_serialize::json::{self, Json, ToJson};
 use super::factfile;
+use chrono::{self, DateTime, NaiveTime, UTC};
 
 use std::error::Error;
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use libc;
 
 pub struct TaskReturnCodeMapping {
     pub continue_job: Vec<i32>,
@@ -38,23 +45,65 @@ pub fn parse(factfile: &str,
              env: Option<Json>,
              overrides: OverrideResultMappings)
              -> Result<factfile::Factfile, String> {
+    let f = try!(read_factfile(factfile));
+    parse_str(&f, factfile, env, overrides)
+}
+
+pub fn is_factfile_url(factfile: &str) -> bool {
+    factfile.starts_with("http://") || factfile.starts_with("https://")
+}
+
+pub fn read_factfile(factfile: &str) -> Result<String, String> {
     info!("reading {} into memory", factfile);
-    let mut fh = try!(File::open(&factfile)
-        .map_err(|e| format!("Couldn't open '{}' for reading: {}", factfile, e)));
-    let mut f = String::new();
-    try!(fh.read_to_string(&mut f).map_err(|e| format!("Couldn't read '{}': {}", factfile, e)));
+    let f = if is_factfile_url(factfile) {
+        try!(fetch_factfile(factfile))
+    } else {
+        let mut fh = try!(File::open(&factfile)
+            .map_err(|e| format!("Couldn't open '{}' for reading: {}", factfile, e)));
+        let mut f = String::new();
+        try!(fh.read_to_string(&mut f)
+            .map_err(|e| format!("Couldn't read '{}': {}", factfile, e)));
+        f
+    };
     info!("file {} was read successfully!", factfile);
+    Ok(f)
+}
 
-    parse_str(&f, factfile, env, overrides)
+fn fetch_factfile(url: &str) -> Result<String, String> {
+    use hyper::Client;
+    use hyper::net::HttpsConnector;
+    use hyper::status::StatusCode;
+    use hyper_native_tls::NativeTlsClient;
+
+    let ssl = try!(NativeTlsClient::new()
+        .map_err(|e| format!("Couldn't fetch '{}': {}", url, e)));
+    let client = Client::with_connector(HttpsConnector::new(ssl));
+    let mut res = try!(client.get(url)
+        .send()
+        .map_err(|e| format!("Couldn't fetch '{}': {}", url, e)));
+    if res.status != StatusCode::Ok {
+        return Err(format!("Couldn't fetch '{}': the server returned {}", url, res.status));
+    }
+
+    let mut body = String::new();
+    try!(res.read_to_string(&mut body)
+        .map_err(|e| format!("Couldn't read the response from '{}': {}", url, e)));
+    Ok(body)
 }
 
-fn parse_str(json: &str,
+pub fn parse_str(json: &str,
              from_filename: &str,
              env: Option<Json>,
              overrides: OverrideResultMappings)
              -> Result<factfile::Factfile, String> {
     info!("parsing json:\n{}", json);
 
+    // expanded ahead of validation, so variables can be used anywhere in the factfile
+    let unexpanded = json;
+    let expanded = try!(templater::expand_env_vars(json, |name| ::std::env::var(name).ok())
+        .map_err(|msg| format!("'{}' is not a valid factotum factfile: {}", from_filename, msg)));
+    let json = &expanded;
+
     let validation_result = schemavalidator::validate_against_factfile_schema(json);
 
     match validation_result {        
@@ -62,7 +111,8 @@ fn parse_str(json: &str,
             info!("'{}' matches the factotum schema definition!",
                   from_filename);
 
-            parse_valid_json(json, env, overrides).map_err(|msg| {
+            let factfile_dir = Path::new(from_filename).parent().unwrap_or(Path::new(""));
+            parse_valid_json(json, unexpanded, factfile_dir, env, overrides).map_err(|msg| {
                 format!("'{}' is not a valid factotum factfile: {}",
                         from_filename,
                         msg)
@@ -79,6 +129,124 @@ fn parse_str(json: &str,
 }
 
 
+// a problem with a factfile, and where it is when that's known
+#[derive(Debug, PartialEq)]
+pub struct ValidationError {
+    pub path: Option<String>,
+    pub task: Option<String>,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new<S: Into<String>>(message: S) -> Self {
+        let message = message.into();
+        // most checks after the schema name the task they're about
+        let task = if message.starts_with("the task '") {
+            message["the task '".len()..].split("' ").next().map(|name| name.to_string())
+        } else {
+            None
+        };
+        ValidationError {
+            path: None,
+            task: task,
+            field: None,
+            message: message,
+        }
+    }
+
+    // e.g. "/data/tasks/2/onResult" is the onResult field of the third task
+    fn at_path(path: &str, message: &str, factfile: &Json) -> Self {
+        let parts = path.split('/').filter(|p| !p.is_empty()).collect::<Vec<&str>>();
+        let in_data = parts.len() > 1 && parts[0] == "data";
+        let (task, field) = if in_data && parts.len() > 2 && parts[1] == "tasks" {
+            let task = parts[2]
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| {
+                    factfile.find_path(&["data", "tasks"])
+                        .and_then(|tasks| tasks.as_array())
+                        .and_then(|tasks| tasks.get(idx))
+                })
+                .and_then(|task| task.find("name"))
+                .and_then(|name| name.as_string())
+                .map(|name| name.to_string());
+            let field = if parts.len() > 3 {
+                Some(parts[3..].join("/"))
+            } else {
+                None
+            };
+            (task, field)
+        } else if in_data {
+            (None, Some(parts[1..].join("/")))
+        } else {
+            (None, None)
+        };
+        ValidationError {
+            path: Some(path.to_string()),
+            task: task,
+            field: field,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl ToJson for ValidationError {
+    fn to_json(&self) -> Json {
+        let mut d = BTreeMap::new();
+        d.insert("path".to_string(), self.path.to_json());
+        d.insert("task".to_string(), self.task.to_json());
+        d.insert("field".to_string(), self.field.to_json());
+        d.insert("message".to_string(), self.message.to_json());
+        Json::Object(d)
+    }
+}
+
+// like parse, but every schema error is reported on its own
+pub fn get_validation_errors(factfile: &str, env: Option<Json>) -> Vec<ValidationError> {
+    let contents = match read_factfile(factfile) {
+        Ok(contents) => contents,
+        Err(msg) => return vec![ValidationError::new(msg)],
+    };
+
+    let json = match templater::expand_env_vars(&contents, |name| ::std::env::var(name).ok()) {
+        Ok(expanded) => expanded,
+        Err(msg) => return vec![ValidationError::new(msg)],
+    };
+
+    match schemavalidator::get_factfile_schema_errors(&json) {
+        Ok(ref errors) if errors.is_empty() => {}
+        Ok(errors) => {
+            let tree = Json::from_str(&json).unwrap_or(Json::Null);
+            return errors.iter()
+                .map(|e| ValidationError::at_path(&e.path, &e.message, &tree))
+                .collect();
+        }
+        Err(msg) => return vec![ValidationError::new(msg)],
+    }
+
+    let factfile_dir = Path::new(factfile).parent().unwrap_or(Path::new(""));
+    match parse_valid_json(&json, &contents, factfile_dir, env, OverrideResultMappings::None) {
+        Ok(_) => vec![],
+        Err(msg) => vec![ValidationError::new(msg)],
+    }
+}
+
+pub fn validate_executors(ff: &factfile::Factfile, allowed: &Vec<String>) -> Result<(), String> {
+    for task_group in ff.get_tasks_in_order() {
+        for task in task_group {
+            if !allowed.iter().any(|executor| executor == &task.executor) {
+                return Err(format!("the task '{}' uses the executor '{}', which isn't allowed \
+                                    (allowed executors: {})",
+                                   task.name,
+                                   task.executor,
+                                   allowed.join(", ")));
+            }
+        }
+    }
+    Ok(())
+}
+
 #[derive(RustcEncodable, RustcDecodable)]
 #[allow(dead_code)]
 struct SelfDescribingJson {
@@ -89,18 +257,90 @@ struct SelfDescribingJson {
 #[derive(RustcEncodable, RustcDecodable)]
 struct FactfileFormat {
     name: String,
+    defaults: Option<FactfileTaskDefaultsFormat>,
+    includes: Option<Vec<FactfileIncludeFormat>>,
+    sequence: Option<Vec<String>>,
+    tasks: Vec<FactfileTaskFormat>,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct FactfileIncludeFormat {
+    file: String,
+    name: String,
+    with: Option<BTreeMap<String, String>>,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+struct FactfileFragmentFormat {
     tasks: Vec<FactfileTaskFormat>,
 }
 
 #[derive(RustcEncodable, RustcDecodable)]
 #[allow(non_snake_case)]
+struct FactfileTaskDefaultsFormat {
+    executor: Option<String>,
+    onResult: Option<FactfileTaskResultFormat>,
+    hosts: Option<Vec<String>>,
+    hostSelection: Option<String>,
+    addressSelection: Option<String>,
+    captureStdout: Option<bool>,
+    captureStderr: Option<bool>,
+    retryCount: Option<u32>,
+    retryDelaySeconds: Option<u64>,
+    retryJitterPercent: Option<u32>,
+    retryOn: Option<Vec<i32>>,
+    maxTotalDurationSeconds: Option<u64>,
+    expectedDurationSeconds: Option<u64>,
+    runAsUser: Option<String>,
+    runAsGroup: Option<String>,
+    onSuccess: Option<String>,
+    onFailure: Option<String>,
+    precondition: Option<String>,
+    notBefore: Option<String>,
+    cwd: Option<String>,
+    inheritStdin: Option<bool>,
+    notify: Option<bool>,
+    tags: Option<BTreeMap<String, String>>,
+    noopScope: Option<String>,
+    outputEncoding: Option<String>,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Clone)]
+#[allow(non_snake_case)]
 struct FactfileTaskFormat {
     name: String,
-    executor: String,
+    executor: Option<String>,
     command: String,
     arguments: Vec<String>,
+    argumentsFile: Option<String>,
     dependsOn: Vec<String>,
-    onResult: FactfileTaskResultFormat,
+    onResult: Option<FactfileTaskResultFormat>,
+    hosts: Option<Vec<String>>,
+    hostSelection: Option<String>,
+    addressSelection: Option<String>,
+    captureStdout: Option<bool>,
+    captureStderr: Option<bool>,
+    retryCount: Option<u32>,
+    retryDelaySeconds: Option<u64>,
+    retryJitterPercent: Option<u32>,
+    retryOn: Option<Vec<i32>>,
+    maxTotalDurationSeconds: Option<u64>,
+    expectedDurationSeconds: Option<u64>,
+    runAsUser: Option<String>,
+    runAsGroup: Option<String>,
+    onSuccess: Option<String>,
+    onFailure: Option<String>,
+    precondition: Option<String>,
+    notBefore: Option<String>,
+    cwd: Option<String>,
+    stdin: Option<String>,
+    inheritStdin: Option<bool>,
+    outputs: Option<Vec<String>>,
+    notify: Option<bool>,
+    tags: Option<BTreeMap<String, String>>,
+    noopScope: Option<String>,
+    outputEncoding: Option<String>,
+    mutex: Option<String>,
 }
 
 #[derive(RustcEncodable, RustcDecodable, Clone)]
@@ -110,13 +350,87 @@ struct FactfileTaskResultFormat {
     continueJob: Vec<i32>,
 }
 
+fn apply_task_defaults(task: &mut FactfileTaskFormat, defaults: &FactfileTaskDefaultsFormat) {
+    // values set on the task itself always win
+    macro_rules! default_if_unset {
+        ($($field:ident),*) => {
+            $(
+                if task.$field.is_none() {
+                    task.$field = defaults.$field.clone();
+                }
+            )*
+        }
+    }
+
+    default_if_unset!(executor,
+                      onResult,
+                      hosts,
+                      hostSelection,
+                      addressSelection,
+                      captureStdout,
+                      captureStderr,
+                      retryCount,
+                      retryDelaySeconds,
+                      retryJitterPercent,
+                      retryOn,
+                      maxTotalDurationSeconds,
+                      expectedDurationSeconds,
+                      runAsUser,
+                      runAsGroup,
+                      onSuccess,
+                      onFailure,
+                      precondition,
+                      notBefore,
+                      cwd,
+                      inheritStdin,
+                      notify,
+                      tags,
+                      noopScope,
+                      outputEncoding);
+}
+
 fn parse_valid_json(file: &str,
+                    unexpanded: &str,
+                    factfile_dir: &Path,
                     conf: Option<Json>,
                     overrides: OverrideResultMappings)
                     -> Result<factfile::Factfile, String> {
     let schema: SelfDescribingJson = try!(json::decode(file).map_err(|e| e.to_string()));
-    let compact_json:String = try!(json::encode(&schema).map_err(|e| e.to_string()));
-    let decoded_json = schema.data;
+    // the raw factfile keeps its ${VAR}s, as it's sent to webhooks and stored in run records
+    let mut unexpanded_schema: SelfDescribingJson = try!(json::decode(unexpanded)
+        .map_err(|e| e.to_string()));
+    let mut decoded_json = schema.data;
+
+    // included tasks come first, so the factfile's own tasks can depend on them
+    if let Some(ref includes) = decoded_json.includes {
+        let mut include_names = HashSet::new();
+        let mut included_tasks = vec![];
+        for include in includes.iter() {
+            if !include_names.insert(include.name.clone()) {
+                return Err(format!("the include name '{}' is used more than once.", include.name));
+            }
+            included_tasks.extend(try!(read_include(include, factfile_dir, &schema.schema, &conf)));
+        }
+        // and are written into the raw factfile, so its hash changes when an include does
+        let mut raw_tasks = included_tasks.clone();
+        raw_tasks.extend(unexpanded_schema.data.tasks.drain(..));
+        unexpanded_schema.data.tasks = raw_tasks;
+        unexpanded_schema.data.includes = None;
+
+        included_tasks.extend(decoded_json.tasks.drain(..));
+        decoded_json.tasks = included_tasks;
+    }
+    let compact_json:String = try!(json::encode(&unexpanded_schema).map_err(|e| e.to_string()));
+
+    if let Some(ref defaults) = decoded_json.defaults {
+        for task in decoded_json.tasks.iter_mut() {
+            apply_task_defaults(task, defaults);
+        }
+    }
+
+    if let Some(ref sequence) = decoded_json.sequence {
+        try!(apply_sequence(&mut decoded_json.tasks, sequence, &conf));
+    }
 
     let final_compact_json:String = if let Some(ref subs) = conf {
         try!(templater::decorate_str(&compact_json, &subs))
@@ -131,6 +445,16 @@ fn parse_valid_json(file: &str,
     }.to_string();
 
     let mut ff = factfile::Factfile::new(final_compact_json, final_dag_name);
+    let mut task_names = HashSet::new();
+
+    // only used to tell a misspelt dependency from one that's defined too late
+    let all_task_names = decoded_json.tasks
+        .iter()
+        .map(|t| match conf {
+            Some(ref subs) => templater::decorate_str(&t.name, subs).unwrap_or(t.name.clone()),
+            None => t.name.clone(),
+        })
+        .collect::<HashSet<String>>();
 
     for file_task in decoded_json.tasks.iter() {
         let final_name = if let Some(ref subs) = conf {
@@ -139,15 +463,37 @@ fn parse_valid_json(file: &str,
             file_task.name.clone()
         }.to_string();
 
+        if !task_names.insert(final_name.clone()) {
+            return Err(format!("the task name '{}' is used more than once.", final_name));
+        }
+
         // TODO errs in here - ? add task should Result not panic!
         info!("adding task '{}'", final_name);
 
-        if file_task.onResult.continueJob.len() == 0 {
+        let executor = match file_task.executor {
+            Some(ref e) => e.clone(),
+            None => {
+                return Err(format!("the task '{}' has no executor, and there's no default \
+                                    executor.",
+                                   final_name))
+            }
+        };
+
+        let on_result = match file_task.onResult {
+            Some(ref r) => r,
+            None => {
+                return Err(format!("the task '{}' has no onResult, and there's no default \
+                                    onResult.",
+                                   final_name))
+            }
+        };
+
+        if on_result.continueJob.len() == 0 {
             return Err(format!("the task '{}' has no way to continue successfully.",
                                final_name));
         } else {
-            for cont in file_task.onResult.continueJob.iter() {
-                if file_task.onResult
+            for cont in on_result.continueJob.iter() {
+                if on_result
                     .terminateJobWithSuccess
                     .iter()
                     .any(|conflict| conflict == cont) {
@@ -156,19 +502,115 @@ fn parse_valid_json(file: &str,
             }
         }
 
+        let hosts = file_task.hosts.clone().unwrap_or(vec![]);
+        if executor == "ssh" && hosts.is_empty() {
+            return Err(format!("the task '{}' uses the ssh executor but has no hosts.",
+                               final_name));
+        } else if executor != "ssh" && !hosts.is_empty() {
+            return Err(format!("the task '{}' has hosts, but these can only be used with the \
+                                ssh executor.",
+                               final_name));
+        }
+
+        let host_selection = match file_task.hostSelection {
+            Some(ref selection) if selection == "random" => factfile::HostSelection::Random,
+            _ => factfile::HostSelection::RoundRobin,
+        };
+
+        let address_selection = match file_task.addressSelection {
+            Some(ref selection) if selection == "first" => factfile::AddressSelection::First,
+            Some(ref selection) if selection == "random" => factfile::AddressSelection::Random,
+            _ => factfile::AddressSelection::Hostname,
+        };
+
+        let noop_scope = match file_task.noopScope {
+            Some(ref scope) if scope == "job" => factfile::NoopScope::Job,
+            _ => factfile::NoopScope::Branch,
+        };
+
+        let output_encoding = match file_task.outputEncoding {
+            Some(ref encoding) if encoding == "latin-1" => factfile::OutputEncoding::Latin1,
+            _ => factfile::OutputEncoding::Utf8,
+        };
+
+        let run_as_user = match file_task.runAsUser {
+            Some(ref user) => {
+                Some(try!(get_user_id(user).map_err(|e| {
+                    format!("the task '{}' can't be run as the user '{}': {}",
+                            final_name,
+                            user,
+                            e)
+                })))
+            }
+            None => None,
+        };
+
+        let run_as_group = match file_task.runAsGroup {
+            Some(ref group) => {
+                Some(try!(get_group_id(group).map_err(|e| {
+                    format!("the task '{}' can't be run as the group '{}': {}",
+                            final_name,
+                            group,
+                            e)
+                })))
+            }
+            None => None,
+        };
+
+        let mut arguments = file_task.arguments.clone();
+        if let Some(ref args_file) = file_task.argumentsFile {
+            let args_path = factfile_dir.join(args_file);
+            arguments.extend(try!(read_arguments_file(&args_path).map_err(|e| {
+                format!("the task '{}' couldn't read its arguments file '{}': {}",
+                        final_name,
+                        args_path.display(),
+                        e)
+            })));
+        }
+
+        let not_before = match file_task.notBefore {
+            Some(ref time) => {
+                Some(try!(get_not_before(time, &UTC::now()).map_err(|e| {
+                    format!("the task '{}' has an invalid notBefore: {}", final_name, e)
+                })))
+            }
+            None => None,
+        };
+
+        let inherit_stdin = file_task.inheritStdin.unwrap_or(false);
+        if inherit_stdin && file_task.stdin.is_some() {
+            return Err(format!("the task '{}' has stdin, so it can't also inherit factotum's \
+                                stdin.",
+                               final_name));
+        }
+
+        let outputs = file_task.outputs.clone().unwrap_or(vec![]);
+        if executor == "ssh" && !outputs.is_empty() {
+            // the outputs file is local, so a task on another host couldn't write to it
+            return Err(format!("the task '{}' has outputs, but these can only be used with the \
+                                shell executor.",
+                               final_name));
+        }
+
+        let stdin = match (file_task.stdin.as_ref(), conf.as_ref()) {
+            (Some(input), Some(subs)) => Some(try!(templater::decorate_str(input, subs))),
+            (input, _) => input.cloned(),
+        };
+
         let mut decorated_args = vec![];
         let mut decorated_deps = vec![];
+        let mut decorated_hosts = vec![];
         if let Some(ref subs) = conf {
             info!("applying variables command and args of '{}'",
                   &final_name);
 
             info!("before:\n\tcommand: '{}'\n\targs: '{}'",
                   file_task.command,
-                  file_task.arguments.join(" "));
+                  arguments.join(" "));
 
             let decorated_command = try!(templater::decorate_str(&file_task.command, &subs));
 
-            for arg in file_task.arguments.iter() {
+            for arg in arguments.iter() {
                 decorated_args.push(try!(templater::decorate_str(arg, &subs)))
             }
 
@@ -183,35 +625,294 @@ fn parse_valid_json(file: &str,
             info!("after:\n\tcommand: '{}'\n\tdeps: '{}'",
                   decorated_command,
                   decorated_deps.join(" "));
+
+            for host in hosts.iter() {
+                decorated_hosts.push(try!(templater::decorate_str(host, &subs)))
+            }
         } else {
             info!("No config specified, writing args & deps as undecorated strings");
-            for arg in file_task.arguments.iter() {
+            for arg in arguments.iter() {
                 decorated_args.push(arg.to_string());
             }
             for dep in file_task.dependsOn.iter() {
                 decorated_deps.push(dep.to_string());
             }
+            decorated_hosts = hosts;
         }
 
-        let deps: Vec<&str> = decorated_deps.iter().map(AsRef::as_ref).collect();
-        let args: Vec<&str> = decorated_args.iter().map(AsRef::as_ref).collect();
-
         let (terminate_mappings, continue_mappings) = match overrides {
             OverrideResultMappings::All(ref with_value) => {
                 (&with_value.terminate_early, &with_value.continue_job)
             }
             OverrideResultMappings::None => {
-                (&file_task.onResult.terminateJobWithSuccess, &file_task.onResult.continueJob)
+                (&on_result.terminateJobWithSuccess, &on_result.continueJob)
             }
         };
 
-        ff.add_task(&final_name,
-                    &deps,
-                    &file_task.executor,
-                    &file_task.command,
-                    &args,
-                    terminate_mappings,
-                    continue_mappings);
+        for dep in decorated_deps.iter() {
+            if dep == &final_name {
+                return Err(format!("the task '{}' depends on itself.", final_name));
+            } else if !all_task_names.contains(dep) {
+                return Err(format!("the task '{}' depends on '{}', which isn't a task in the \
+                                    factfile.",
+                                   final_name,
+                                   dep));
+            } else if !task_names.contains(dep) {
+                return Err(format!("the task '{}' depends on '{}', which has to come before it \
+                                    in the factfile.",
+                                   final_name,
+                                   dep));
+            }
+        }
+
+        ff.add_task_obj(&factfile::Task {
+            name: final_name,
+            depends_on: decorated_deps,
+            executor: executor,
+            command: file_task.command.clone(),
+            arguments: decorated_args,
+            on_result: factfile::OnResult {
+                terminate_job: terminate_mappings.clone(),
+                continue_job: continue_mappings.clone(),
+            },
+            hosts: decorated_hosts,
+            host_selection: host_selection,
+            address_selection: address_selection,
+            capture_stdout: file_task.captureStdout.unwrap_or(true),
+            capture_stderr: file_task.captureStderr.unwrap_or(true),
+            retry_count: file_task.retryCount.unwrap_or(0),
+            retry_delay_seconds: file_task.retryDelaySeconds.unwrap_or(0),
+            retry_jitter_percent: file_task.retryJitterPercent.unwrap_or(0),
+            retry_on: file_task.retryOn.clone(),
+            max_total_duration_seconds: file_task.maxTotalDurationSeconds,
+            expected_duration_seconds: file_task.expectedDurationSeconds,
+            run_as_user: run_as_user,
+            run_as_group: run_as_group,
+            on_success: file_task.onSuccess.clone(),
+            on_failure: file_task.onFailure.clone(),
+            precondition: file_task.precondition.clone(),
+            not_before: not_before,
+            cwd: file_task.cwd.clone(),
+            stdin: stdin,
+            inherit_stdin: inherit_stdin,
+            outputs: outputs,
+            notify: file_task.notify.unwrap_or(true),
+            tags: file_task.tags.clone().unwrap_or(BTreeMap::new()),
+            noop_scope: noop_scope,
+            output_encoding: output_encoding,
+            mutex: file_task.mutex.clone(),
+        });
     }
     Ok(ff)
 }
+
+// an include is a file of tasks, filled in with its own values and named "<include>.<task>"
+fn read_include(include: &FactfileIncludeFormat,
+                factfile_dir: &Path,
+                schema: &str,
+                conf: &Option<Json>)
+                -> Result<Vec<FactfileTaskFormat>, String> {
+    let include_path = factfile_dir.join(&include.file);
+    let mut contents = String::new();
+    try!(File::open(&include_path)
+        .and_then(|mut fh| fh.read_to_string(&mut contents))
+        .map_err(|e| {
+            format!("the include '{}' couldn't read '{}': {}",
+                    include.name,
+                    include_path.display(),
+                    e)
+        }));
+
+    // the tasks are checked against the factfile schema, as if they were a job of their own
+    let fragment_tasks = try!(Json::from_str(&contents)
+        .ok()
+        .and_then(|fragment| fragment.find("tasks").cloned())
+        .ok_or(format!("the include '{}' ('{}') isn't a JSON object with a list of tasks.",
+                       include.name,
+                       include_path.display())));
+    let mut data = BTreeMap::new();
+    data.insert("name".to_string(), Json::String(include.name.clone()));
+    data.insert("tasks".to_string(), fragment_tasks);
+    let mut wrapped = BTreeMap::new();
+    wrapped.insert("schema".to_string(), Json::String(schema.to_string()));
+    wrapped.insert("data".to_string(), Json::Object(data));
+    try!(schemavalidator::validate_against_factfile_schema(&Json::Object(wrapped).to_string())
+        .map_err(|e| format!("the include '{}' has invalid tasks: {}", include.name, e)));
+
+    let fragment: FactfileFragmentFormat = try!(json::decode(&contents).map_err(|e| {
+        format!("the include '{}' has invalid tasks: {}", include.name, e)
+    }));
+
+    // the include's own values win over --env
+    let mut values = match *conf {
+        Some(Json::Object(ref env)) => env.clone(),
+        _ => BTreeMap::new(),
+    };
+    if let Some(ref with) = include.with {
+        for (key, value) in with.iter() {
+            values.insert(key.clone(), Json::String(value.clone()));
+        }
+    }
+    let values = Json::Object(values);
+
+    let local_names = fragment.tasks.iter().map(|t| t.name.clone()).collect::<Vec<String>>();
+    let prefixed = |name: &str| -> Result<String, String> {
+        Ok(format!("{}.{}", include.name, try!(templater::decorate_str(name, &values))))
+    };
+
+    let mut tasks = vec![];
+    for mut task in fragment.tasks.into_iter() {
+        task.name = try!(prefixed(&task.name));
+        task.command = try!(templater::decorate_str(&task.command, &values));
+        let mut arguments = vec![];
+        for arg in task.arguments.iter() {
+            arguments.push(try!(templater::decorate_str(arg, &values)));
+        }
+        task.arguments = arguments;
+        // dependencies outside the include are tasks from earlier includes
+        let mut depends_on = vec![];
+        for dep in task.dependsOn.iter() {
+            if local_names.contains(dep) {
+                depends_on.push(try!(prefixed(dep)));
+            } else {
+                depends_on.push(try!(templater::decorate_str(dep, &values)));
+            }
+        }
+        task.dependsOn = depends_on;
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+// each task in the sequence also depends on the one before it, on top of its own dependsOn
+fn apply_sequence(tasks: &mut Vec<FactfileTaskFormat>,
+                  sequence: &Vec<String>,
+                  conf: &Option<Json>)
+                  -> Result<(), String> {
+    let decorate = |name: &str| -> Result<String, String> {
+        match *conf {
+            Some(ref subs) => templater::decorate_str(name, subs),
+            None => Ok(name.to_string()),
+        }
+    };
+
+    let task_names = try!(tasks.iter()
+        .map(|t| decorate(&t.name))
+        .collect::<Result<Vec<String>, String>>());
+
+    let mut sequence_names: Vec<String> = vec![];
+    let mut positions = vec![];
+    for name in sequence.iter() {
+        let name = try!(decorate(name));
+        if sequence_names.contains(&name) {
+            return Err(format!("the task '{}' is in the sequence more than once.", name));
+        }
+        match task_names.iter().position(|t| t == &name) {
+            Some(pos) => positions.push(pos),
+            None => {
+                return Err(format!("the sequence has '{}', which isn't a task in the factfile.",
+                                   name))
+            }
+        }
+        sequence_names.push(name);
+    }
+
+    let mut sequence_deps = vec![];
+    for (i, &pos) in positions.iter().enumerate() {
+        let deps = try!(tasks[pos]
+            .dependsOn
+            .iter()
+            .map(|d| decorate(d))
+            .collect::<Result<Vec<String>, String>>());
+        if let Some(dep) = deps.iter().find(|d| sequence_names[i + 1..].contains(d)) {
+            return Err(format!("the task '{}' depends on '{}', which comes after it in the \
+                                sequence.",
+                               sequence_names[i],
+                               dep));
+        }
+        if i > 0 && pos < positions[i - 1] {
+            return Err(format!("the sequence has '{}' after '{}', but it comes before it in the \
+                                factfile.",
+                               sequence_names[i],
+                               sequence_names[i - 1]));
+        }
+        sequence_deps.push(deps);
+    }
+
+    for i in 1..positions.len() {
+        if !sequence_deps[i].contains(&sequence_names[i - 1]) {
+            tasks[positions[i]].dependsOn.push(sequence_names[i - 1].clone());
+        }
+    }
+    Ok(())
+}
+
+// either a full RFC 3339 timestamp, or a UTC time of day ("02:00") meaning the next time it comes
+// round
+pub fn get_not_before(time: &str, now: &DateTime<UTC>) -> Result<DateTime<UTC>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(time) {
+        return Ok(timestamp.with_timezone(&UTC));
+    }
+
+    let time_of_day = try!(NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .map_err(|_| {
+            format!("'{}' is neither an RFC 3339 timestamp nor a time of day (HH:MM)",
+                    time)
+        }));
+    let today = now.date().and_time(time_of_day).unwrap();
+    if today > *now {
+        Ok(today)
+    } else {
+        Ok(today + chrono::Duration::days(1))
+    }
+}
+
+// each non-blank line of the file is one argument, appended after any inline arguments
+pub fn read_arguments_file(path: &Path) -> Result<Vec<String>, String> {
+    let mut fh = try!(File::open(path).map_err(|e| e.to_string()));
+    let mut contents = String::new();
+    try!(fh.read_to_string(&mut contents).map_err(|e| e.to_string()));
+    Ok(contents.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect())
+}
+
+#[cfg(unix)]
+pub fn get_user_id(user: &str) -> Result<u32, String> {
+    if let Ok(uid) = user.parse::<u32>() {
+        return Ok(uid);
+    }
+    let name = try!(CString::new(user).map_err(|e| e.to_string()));
+    let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+    if passwd.is_null() {
+        Err("no such user exists".to_string())
+    } else {
+        Ok(unsafe { (*passwd).pw_uid })
+    }
+}
+
+#[cfg(unix)]
+pub fn get_group_id(group: &str) -> Result<u32, String> {
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(gid);
+    }
+    let name = try!(CString::new(group).map_err(|e| e.to_string()));
+    let grp = unsafe { libc::getgrnam(name.as_ptr()) };
+    if grp.is_null() {
+        Err("no such group exists".to_string())
+    } else {
+        Ok(unsafe { (*grp).gr_gid })
+    }
+}
+
+#[cfg(not(unix))]
+pub fn get_user_id(_: &str) -> Result<u32, String> {
+    Err("running tasks as another user isn't supported on this platform".to_string())
+}
+
+#[cfg(not(unix))]
+pub fn get_group_id(_: &str) -> Result<u32, String> {
+    Err("running tasks as another group isn't supported on this platform".to_string())
+}
@@ -30,11 +30,44 @@ fn get_human_readable_parse_error(e: ParserError) -> String {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct SchemaError {
+    pub path: String,
+    pub message: String,
+}
+
 pub fn validate_schema(json: &str, schema: &str) -> Result<(), String> {
+    let (errors, unknown) = try!(get_schema_errors(json, schema));
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        let errors_str = errors.iter()
+            .map(|e| format!("'{}' - {}", e.path, e.message))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        if unknown.is_empty() {
+            Err(format!("{}", errors_str))
+        } else {
+            Err(format!("{}\n{}",
+                        errors_str,
+                        unknown.iter()
+                            .map(|e| e.message.clone())
+                            .collect::<Vec<String>>()
+                            .join("\n")))
+        }
+    }
+}
+
+// the validator's own errors, and the properties that weren't allowed
+fn get_schema_errors(json: &str,
+                     schema: &str)
+                     -> Result<(Vec<SchemaError>, Vec<SchemaError>), String> {
     let mut scope = json_schema::Scope::new();
     let json_schema = try!(Json::from_str(schema)
         .map_err(|e| format!("Schema is invalid json: {:?}", e)));
-    let compiled_schema = try!(scope.compile_and_return(json_schema, false)
+    let compiled_schema = try!(scope.compile_and_return(json_schema.clone(), false)
         .map_err(|e| format!("Failed to compile json schema: {:?}", e)));
 
     let json_tree = try!(Json::from_str(json)
@@ -43,27 +76,129 @@ pub fn validate_schema(json: &str, schema: &str) -> Result<(), String> {
     let json_schema_validation = compiled_schema.validate(&json_tree);
 
     if json_schema_validation.is_valid() == true {
-        Ok(())
-    } else {
-        let errors_str = json_schema_validation.errors
-            .iter()
-            .map(|e| {
-                format!("'{}' - {}{}",
-                        e.get_path(),
-                        e.get_title(),
-                        match e.get_detail() {
-                            Some(str) => format!(" ({})", str),
-                            _ => "".to_string(),
-                        })
-            })
-            .collect::<Vec<String>>()
-            .join("\n");
-        Err(format!("{}", errors_str))
+        return Ok((vec![], vec![]));
+    }
+
+    let errors = json_schema_validation.errors
+        .iter()
+        .map(|e| {
+            SchemaError {
+                path: e.get_path().to_string(),
+                message: format!("{}{}",
+                                 e.get_title(),
+                                 match e.get_detail() {
+                                     Some(str) => format!(" ({})", str),
+                                     _ => "".to_string(),
+                                 }),
+            }
+        })
+        .collect::<Vec<SchemaError>>();
+
+    // the validator doesn't say which properties weren't allowed, so they're found here
+    let mut unknown = vec![];
+    find_unknown_properties(&json_tree, &json_schema, "", &mut unknown);
+    Ok((errors, unknown))
+}
+
+// every schema error on its own, for reports that point at the offending field
+pub fn get_factfile_schema_errors(json: &str) -> Result<Vec<SchemaError>, String> {
+    let schema = try!(get_supported_factfile_schema(json));
+    let (mut errors, unknown) = try!(get_schema_errors(json, schema));
+    errors.extend(unknown);
+    Ok(errors)
+}
+
+fn find_unknown_properties(value: &Json,
+                           schema: &Json,
+                           path: &str,
+                           unknown: &mut Vec<SchemaError>) {
+    match (value, schema.find("properties").and_then(|p| p.as_object())) {
+        (&Json::Object(ref fields), Some(properties)) => {
+            let closed = schema.find("additionalProperties") == Some(&Json::Boolean(false));
+            for (name, field) in fields.iter() {
+                match properties.get(name) {
+                    Some(field_schema) => {
+                        find_unknown_properties(field,
+                                                field_schema,
+                                                &format!("{}/{}", path, name),
+                                                unknown)
+                    }
+                    None if closed => {
+                        let owner = match value.find("name").and_then(|n| n.as_string()) {
+                            Some(owner_name) if path.contains("/tasks/") => {
+                                format!("task '{}'", owner_name)
+                            }
+                            _ if path.is_empty() => "the factfile".to_string(),
+                            _ if path == "/data" => "the job".to_string(),
+                            _ => format!("'{}'", path),
+                        };
+                        unknown.push(SchemaError {
+                            path: format!("{}/{}", path, name),
+                            message: format!("{} has an unknown field '{}'", owner, name),
+                        });
+                    }
+                    None => {}
+                }
+            }
+        }
+        (&Json::Array(ref items), _) => {
+            if let Some(item_schema) = schema.find("items") {
+                for (idx, item) in items.iter().enumerate() {
+                    find_unknown_properties(item, item_schema, &format!("{}/{}", path, idx), unknown);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+const FACTFILE_SCHEMA_PREFIX: &'static str = "iglu:com.snowplowanalytics.factotum/factfile/\
+                                               jsonschema/";
+
+fn get_factfile_schema(version: &str) -> Option<&'static str> {
+    match version {
+        "1-0-0" => Some(include_str!("jsonschemas/factotum.json")),
+        _ => None,
+    }
+}
+
+const SUPPORTED_FACTFILE_VERSIONS: [&'static str; 1] = ["1-0-0"];
+
+pub fn get_declared_schema_version(json: &str) -> Result<Option<String>, String> {
+    let json_tree = try!(Json::from_str(json)
+        .map_err(|e| format!("invalid JSON - {}", get_human_readable_parse_error(e))));
+
+    match json_tree.find("schema").and_then(|s| s.as_string()) {
+        Some(schema) => {
+            if schema.starts_with(FACTFILE_SCHEMA_PREFIX) {
+                Ok(Some(schema[FACTFILE_SCHEMA_PREFIX.len()..].to_string()))
+            } else {
+                Err(format!("'{}' is not a factfile schema (expected '{}<version>')",
+                            schema,
+                            FACTFILE_SCHEMA_PREFIX))
+            }
+        }
+        // no schema is reported by the validator itself
+        None => Ok(None),
     }
 }
 
 pub fn validate_against_factfile_schema(json: &str) -> Result<(), String> {
-    let factotum_schema_str: &'static str = include_str!("jsonschemas/factotum.json");
+    let schema = try!(get_supported_factfile_schema(json));
+    validate_schema(json, schema)
+}
+
+fn get_supported_factfile_schema(json: &str) -> Result<&'static str, String> {
+    let version = try!(get_declared_schema_version(json))
+        .unwrap_or(SUPPORTED_FACTFILE_VERSIONS[0].to_string());
 
-    validate_schema(json, factotum_schema_str)
+    match get_factfile_schema(&version) {
+        Some(factotum_schema_str) => Ok(factotum_schema_str),
+        None => {
+            Err(format!("the factfile schema version '{}' is not supported (supported \
+                         versions: {})",
+                        version,
+                        SUPPORTED_FACTFILE_VERSIONS.join(", ")))
+        }
+    }
 }
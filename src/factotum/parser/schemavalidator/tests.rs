@@ -13,4 +13,38 @@
 //
 
 
-// TODO write some tests for the schema validator
+use factotum::parser::schemavalidator::*;
+
+#[test]
+fn declared_schema_version_is_found() {
+    let ff = r#"{"schema": "iglu:com.snowplowanalytics.factotum/factfile/jsonschema/1-0-0"}"#;
+    assert_eq!(get_declared_schema_version(ff), Ok(Some("1-0-0".to_string())));
+    assert_eq!(get_declared_schema_version("{}"), Ok(None));
+    assert_eq!(get_declared_schema_version(r#"{"schema": "iglu:com.acme/thing/jsonschema/1-0-0"}"#),
+               Err("'iglu:com.acme/thing/jsonschema/1-0-0' is not a factfile schema (expected \
+                    'iglu:com.snowplowanalytics.factotum/factfile/jsonschema/<version>')"
+                   .to_string()));
+}
+
+#[test]
+fn unsupported_schema_version_is_rejected() {
+    let ff = r#"{"schema": "iglu:com.snowplowanalytics.factotum/factfile/jsonschema/9-0-0",
+                 "data": {"name": "x", "tasks": []}}"#;
+    assert_eq!(validate_against_factfile_schema(ff),
+               Err("the factfile schema version '9-0-0' is not supported (supported versions: \
+                    1-0-0)"
+                   .to_string()));
+}
+
+#[test]
+fn unknown_fields_are_named() {
+    let ff = r#"{"schema": "iglu:com.snowplowanalytics.factotum/factfile/jsonschema/1-0-0",
+                 "data": {"name": "x", "owner": "ed", "tasks": [
+                   {"name": "apple", "executor": "shell", "command": "echo", "arguments": [],
+                    "dependsOn": [], "dependOn": ["turnip"],
+                    "onResult": {"terminateJobWithSuccess": [], "continueJob": [0]}}]}}"#;
+
+    let err = validate_against_factfile_schema(ff).unwrap_err();
+    assert!(err.contains("\nthe job has an unknown field 'owner'"));
+    assert!(err.contains("\ntask 'apple' has an unknown field 'dependOn'"));
+}
@@ -18,7 +18,7 @@ extern crate mustache;
 mod tests;
 
 use std::error::Error;
-use rustc_serialize::json::Json;
+use rustc_serialize::json::{self, Json};
 
 pub fn decorate_str(template: &str, env: &Json) -> Result<String, String> {
     let compiled_template = mustache::compile_str(&template);
@@ -30,3 +30,54 @@ pub fn decorate_str(template: &str, env: &Json) -> Result<String, String> {
                 Error::description(&e))
     })
 }
+
+// expands ${VAR} and ${VAR:-default} in raw json text, with $${ for a literal ${. Values are
+// escaped so they're safe inside a json string
+pub fn expand_env_vars<F>(text: &str, lookup: F) -> Result<String, String>
+    where F: Fn(&str) -> Option<String>
+{
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start]);
+            expanded.push_str("{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        expanded.push_str(&rest[..start]);
+        let end = match rest[start..].find('}') {
+            Some(len) => start + len,
+            None => return Err(format!("unterminated variable reference '{}'", &rest[start..])),
+        };
+        let reference = &rest[start + 2..end];
+
+        let (name, default) = match reference.find(":-") {
+            Some(idx) => (&reference[..idx], Some(&reference[idx + 2..])),
+            None => (reference, None),
+        };
+        if name.is_empty() {
+            return Err("empty variable reference '${}'".to_string());
+        }
+
+        let value = match (lookup(name), default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => default.to_string(),
+            (None, None) => {
+                return Err(format!("the environment variable '{}' isn't set (use ${{{}:-default}} \
+                                    to give it a default)",
+                                   name,
+                                   name))
+            }
+        };
+        let encoded = json::encode(&value).unwrap();
+        expanded.push_str(&encoded[1..encoded.len() - 1]);
+
+        rest = &rest[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
@@ -32,3 +32,28 @@ fn decorated_nested_string_works() {
                             &from_json("{\"person\": { \"name\":\"Ted\" } }"))
                    .unwrap())
 }
+
+fn lookup(name: &str) -> Option<String> {
+    match name {
+        "USER" => Some("ed".to_string()),
+        "QUOTED" => Some("say \"hi\"".to_string()),
+        _ => None,
+    }
+}
+
+#[test]
+fn env_vars_are_expanded() {
+    assert_eq!(expand_env_vars("hello ${USER}, ${MISSING:-nobody} and ${QUOTED}", lookup),
+               Ok("hello ed, nobody and say \\\"hi\\\"".to_string()));
+    assert_eq!(expand_env_vars("literal $${USER} and $HOME", lookup),
+               Ok("literal ${USER} and $HOME".to_string()));
+}
+
+#[test]
+fn undefined_env_vars_err() {
+    assert_eq!(expand_env_vars("hello ${MISSING}", lookup),
+               Err("the environment variable 'MISSING' isn't set (use ${MISSING:-default} to \
+                    give it a default)"
+                   .to_string()));
+    assert!(expand_env_vars("hello ${USER", lookup).is_err());
+}
@@ -176,3 +176,343 @@ fn overrides_set_noop_values() {
     }
 
 }
+
+#[test]
+fn validate_executors_rejects_disallowed() {
+    let ff = parse(&resource("example_ok.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+
+    assert!(validate_executors(&ff, &vec!["shell".to_string()]).is_ok());
+    assert_eq!(validate_executors(&ff, &vec!["noop".to_string()]),
+               Err("the task 'EmrEtlRunner' uses the executor 'shell', which isn't allowed \
+                    (allowed executors: noop)"
+                   .to_string()));
+}
+
+#[test]
+fn valid_ssh_factfile_has_hosts() {
+    use factotum::factfile::HostSelection;
+
+    let ff = parse(&resource("example_ok_ssh.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+
+    let load = tasks[0][0];
+    assert_eq!(load.hosts, vec!["worker-1", "worker-2"]);
+    assert_eq!(load.host_selection, HostSelection::Random);
+
+    let report = tasks[1][0];
+    assert_eq!(report.hosts, Vec::<String>::new());
+    assert_eq!(report.host_selection, HostSelection::RoundRobin);
+}
+
+#[test]
+#[cfg(unix)]
+fn get_user_and_group_ids() {
+    assert_eq!(get_user_id("root"), Ok(0));
+    assert_eq!(get_user_id("1234"), Ok(1234));
+    assert_eq!(get_user_id("factotum-no-such-user"),
+               Err("no such user exists".to_string()));
+
+    assert_eq!(get_group_id("0"), Ok(0));
+    assert_eq!(get_group_id("factotum-no-such-group"),
+               Err("no such group exists".to_string()));
+}
+
+#[test]
+fn defaults_apply_to_tasks_without_values() {
+    let ff = parse(&resource("example_ok_defaults.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+
+    let extract = tasks[0][0];
+    assert_eq!(extract.executor, "shell");
+    assert_eq!(extract.retry_count, 2);
+    assert_eq!(extract.on_result.terminate_job, vec![3]);
+    assert_eq!(extract.on_result.continue_job, vec![0]);
+
+    let load = tasks[1][0];
+    assert_eq!(load.executor, "noop");
+    assert_eq!(load.retry_count, 0);
+    assert_eq!(load.on_result.terminate_job, Vec::<i32>::new());
+    assert_eq!(load.on_result.continue_job, vec![0, 1]);
+}
+
+#[test]
+fn task_tags_replace_default_tags() {
+    let ff = parse(&resource("example_ok_defaults.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+
+    assert_eq!(tasks[0][0].tags.get("category"), Some(&"ingest".to_string()));
+    assert_eq!(tasks[1][0].tags.get("category"), Some(&"load".to_string()));
+    assert_eq!(tasks[1][0].tags.get("team"), Some(&"data".to_string()));
+}
+
+#[test]
+fn arguments_file_is_appended_to_inline_arguments() {
+    let ff = parse(&resource("example_ok_arguments_file.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+
+    assert_eq!(tasks[0][0].arguments,
+               vec!["--verbose", "--from", "2016-01-01", "--to", "2016-01-31"]);
+}
+
+#[test]
+fn missing_arguments_file_names_the_task() {
+    let invalid = resource("example_invalid_missing_arguments_file.factfile");
+    let res = parse(&invalid, None, OverrideResultMappings::None);
+    if let Err(msg) = res {
+        assert_eq!(msg,
+                   format!("'{}' is not a valid factotum factfile: the task 'Extract' couldn't \
+                            read its arguments file './tests/resources/missing.args': No such \
+                            file or directory (os error 2)",
+                           invalid))
+    } else {
+        panic!("the arguments file doesn't exist - the test should have failed");
+    }
+}
+
+#[test]
+fn os_env_vars_are_expanded() {
+    use std::env;
+
+    env::set_var("FACTOTUM_TEST_COMMAND", "./extract.sh");
+    let ff = parse(&resource("example_ok_os_env.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+
+    assert_eq!(tasks[0][0].command, "./extract.sh");
+    assert_eq!(tasks[0][0].arguments, vec!["--region", "eu-west-1"]);
+}
+
+#[test]
+fn os_env_vars_are_left_out_of_the_raw_factfile() {
+    use std::env;
+
+    env::set_var("FACTOTUM_TEST_RAW_COMMAND", "./secret-extract.sh");
+    let json = "{\"schema\": \"iglu:com.snowplowanalytics.factotum/factfile/jsonschema/1-0-0\", \
+                \"data\": {\"name\": \"raw\", \"tasks\": [{\"name\": \"extract\", \
+                \"executor\": \"shell\", \"command\": \"${FACTOTUM_TEST_RAW_COMMAND}\", \
+                \"arguments\": [], \"dependsOn\": [], \
+                \"onResult\": {\"terminateJobWithSuccess\": [], \"continueJob\": [0]}}]}}";
+    let ff = parse_str(json, "raw.factfile", None, OverrideResultMappings::None).unwrap();
+
+    assert_eq!(ff.get_tasks_in_order()[0][0].command, "./secret-extract.sh");
+    assert!(ff.raw.contains("${FACTOTUM_TEST_RAW_COMMAND}"));
+    assert!(!ff.raw.contains("./secret-extract.sh"));
+}
+
+#[test]
+fn stdin_is_decorated_with_env() {
+    let ff = parse(&resource("example_ok_stdin.factfile"),
+                   Some(Json::from_str("{\"date\":\"2016-01-01\"}").unwrap()),
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+
+    assert_eq!(tasks[0][0].stdin, Some("date=2016-01-01\n".to_string()));
+}
+
+#[test]
+fn mutex_is_only_set_where_declared() {
+    let ff = parse(&resource("example_ok_mutex.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+    let mutex_of = |name: &str| {
+        tasks[0].iter().find(|t| t.name == name).and_then(|t| t.mutex.clone())
+    };
+
+    assert_eq!(mutex_of("Compact"), Some("warehouse-dir".to_string()));
+    assert_eq!(mutex_of("Report"), None);
+}
+
+#[test]
+fn overrides_beat_default_on_result() {
+    let ff = parse(&resource("example_ok_defaults.factfile"),
+                   None,
+                   OverrideResultMappings::All(TaskReturnCodeMapping {
+                       continue_job: vec![0],
+                       terminate_early: vec![],
+                   }))
+        .unwrap();
+
+    for task in ff.get_tasks_in_order().iter().flat_map(|tg| tg.iter()) {
+        assert_eq!(task.on_result.continue_job, vec![0]);
+        assert_eq!(task.on_result.terminate_job, Vec::<i32>::new());
+    }
+}
+
+#[test]
+fn effective_factfile_parses_to_the_same_tasks() {
+    let ff = parse(&resource("example_ok_defaults.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+
+    let effective = ff.as_effective_json();
+    let reparsed = parse_str(&effective, "effective.factfile", None, OverrideResultMappings::None)
+        .unwrap();
+
+    assert_eq!(reparsed.name, ff.name);
+    assert_eq!(reparsed.get_tasks_in_order(), ff.get_tasks_in_order());
+}
+
+#[test]
+fn includes_are_instantiated_with_their_own_values() {
+    let ff = parse(&resource("example_ok_includes.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+
+    let mut extracts = tasks[0].iter().map(|t| t.name.clone()).collect::<Vec<String>>();
+    extracts.sort();
+    assert_eq!(extracts, vec!["db1.extract", "db2.extract"]);
+
+    let db2_load = tasks[1].iter().find(|t| t.name == "db2.load").unwrap();
+    assert_eq!(db2_load.depends_on, vec!["db2.extract"]);
+    assert_eq!(db2_load.arguments, vec!["--table", "db2_events"]);
+
+    assert_eq!(tasks[2][0].name, "report");
+}
+
+#[test]
+fn included_tasks_are_part_of_the_raw_factfile() {
+    let ff = parse(&resource("example_ok_includes.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+
+    // so the hash of the factfile changes when an included file is edited
+    assert!(ff.raw.contains("\"db2.load\""));
+    assert!(ff.raw.contains("\"db2_events\""));
+    assert!(!ff.raw.contains("includes/ingest.json"));
+}
+
+#[test]
+fn included_task_names_must_be_unique() {
+    let invalid = resource("example_invalid_include_collision.factfile");
+    let res = parse(&invalid, None, OverrideResultMappings::None);
+    if let Err(msg) = res {
+        assert_eq!(msg,
+                   format!("'{}' is not a valid factotum factfile: the task name 'db1.load' is \
+                            used more than once.",
+                           invalid))
+    } else {
+        panic!("two tasks are called 'db1.load' - the test should have failed");
+    }
+}
+
+#[test]
+fn dependencies_must_be_tasks_in_the_factfile() {
+    let invalid = resource("example_invalid_missing_dependency.factfile");
+    let res = parse(&invalid, None, OverrideResultMappings::None);
+    if let Err(msg) = res {
+        assert_eq!(msg,
+                   format!("'{}' is not a valid factotum factfile: the task 'load' depends on \
+                            'transfrom', which isn't a task in the factfile.",
+                           invalid))
+    } else {
+        panic!("'load' depends on a task that doesn't exist - the test should have failed");
+    }
+}
+
+#[test]
+fn validation_errors_point_at_the_field() {
+    let errors = get_validation_errors(&resource("example_wrong_type.factfile"), None);
+    assert_eq!(errors,
+               vec![ValidationError {
+                        path: Some("/data/tasks/0/onResult/terminateJobWithSuccess/0"
+                            .to_string()),
+                        task: Some("EmrEtlRunner".to_string()),
+                        field: Some("onResult/terminateJobWithSuccess/0".to_string()),
+                        message: "Type of the value is wrong (The value must be integer)"
+                            .to_string(),
+                    }]);
+
+    let errors = get_validation_errors(&resource("example_invalid_no_continue.factfile"), None);
+    assert_eq!(errors,
+               vec![ValidationError {
+                        path: None,
+                        task: Some("continue".to_string()),
+                        field: None,
+                        message: "the task 'continue' has no way to continue successfully."
+                            .to_string(),
+                    }]);
+
+    assert!(get_validation_errors(&resource("example_ok.factfile"), None).is_empty());
+}
+
+#[test]
+fn not_before_takes_timestamps_and_times_of_day() {
+    use chrono::{TimeZone, UTC};
+
+    let now = UTC.ymd(2017, 3, 1).and_hms(12, 30, 0);
+    assert_eq!(get_not_before("2017-03-02T01:00:00+01:00", &now),
+               Ok(UTC.ymd(2017, 3, 2).and_hms(0, 0, 0)));
+    assert_eq!(get_not_before("14:00", &now), Ok(UTC.ymd(2017, 3, 1).and_hms(14, 0, 0)));
+    assert_eq!(get_not_before("02:00:30", &now),
+               Ok(UTC.ymd(2017, 3, 2).and_hms(2, 0, 30)));
+    assert_eq!(get_not_before("12:30", &now), Ok(UTC.ymd(2017, 3, 2).and_hms(12, 30, 0)));
+    assert_eq!(get_not_before("soon", &now),
+               Err("'soon' is neither an RFC 3339 timestamp nor a time of day (HH:MM)"
+                   .to_string()));
+}
+
+#[test]
+fn factfiles_can_be_read_from_urls() {
+    assert!(is_factfile_url("http://artifacts.example.com/jobs/ingest.factfile"));
+    assert!(is_factfile_url("https://artifacts.example.com/jobs/ingest.factfile"));
+    assert!(!is_factfile_url("./tests/resources/example_ok.factfile"));
+    assert!(!is_factfile_url("httpish/example_ok.factfile"));
+
+    match read_factfile("http://127.0.0.1:1/ingest.factfile") {
+        Ok(_) => panic!("nothing should be listening on port 1"),
+        Err(msg) => assert!(msg.starts_with("Couldn't fetch 'http://127.0.0.1:1/ingest.factfile'")),
+    }
+}
+
+#[test]
+fn sequence_is_merged_with_depends_on() {
+    let ff = parse(&resource("example_ok_sequence.factfile"),
+                   None,
+                   OverrideResultMappings::None)
+        .unwrap();
+    let tasks = ff.get_tasks_in_order();
+
+    assert_eq!(tasks[0][0].name, "extract");
+    let transform = tasks[1].iter().find(|t| t.name == "transform").unwrap();
+    assert_eq!(transform.depends_on, vec!["extract"]);
+    let load = tasks[2].iter().find(|t| t.name == "load").unwrap();
+    assert_eq!(load.depends_on, vec!["check", "transform"]);
+}
+
+#[test]
+fn sequence_must_agree_with_depends_on() {
+    let invalid = resource("example_invalid_sequence_conflict.factfile");
+    let res = parse(&invalid, None, OverrideResultMappings::None);
+    if let Err(msg) = res {
+        assert_eq!(msg,
+                   format!("'{}' is not a valid factotum factfile: the task 'transform' depends \
+                            on 'load', which comes after it in the sequence.",
+                           invalid))
+    } else {
+        panic!("'transform' depends on a later task in the sequence - the test should have failed");
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::task_list::{Task, State};
+use factotum::factfile::Task as FactfileTask;
+use factotum::runrecord;
+use chrono::{DateTime, UTC};
+
+fn escape_label(value: &str) -> String {
+    value.replace("\\", "\\\\").replace("\"", "\\\"").replace("\n", "\\n")
+}
+
+fn add_metric(out: &mut String, name: &str, help: &str, samples: &Vec<(String, String)>) {
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+    for &(ref labels, ref value) in samples.iter() {
+        out.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+    }
+}
+
+pub fn to_textfile(job_name: &str,
+                   succeeded: bool,
+                   finished: &DateTime<UTC>,
+                   tasks: &Vec<&Task<&FactfileTask>>)
+                   -> String {
+    let job_label = format!("job=\"{}\"", escape_label(job_name));
+    let mut out = String::new();
+
+    add_metric(&mut out,
+               "factotum_job_success",
+               "Whether the last run of the job succeeded (1) or not (0).",
+               &vec![(job_label.clone(), if succeeded { "1" } else { "0" }.to_string())]);
+    add_metric(&mut out,
+               "factotum_job_last_run_timestamp_seconds",
+               "When the last run of the job finished, as a unix timestamp.",
+               &vec![(job_label.clone(), finished.timestamp().to_string())]);
+
+    // only tasks that actually ran have a duration or return code
+    let ran = tasks.iter()
+        .filter_map(|t| t.run_result.as_ref().map(|r| (t, r)))
+        .map(|(t, r)| (format!("{},task=\"{}\"", job_label, escape_label(&t.name)), r))
+        .collect::<Vec<_>>();
+
+    add_metric(&mut out,
+               "factotum_task_duration_seconds",
+               "How long each task took on the last run.",
+               &ran.iter()
+                   .map(|&(ref labels, r)| {
+                       let secs = r.duration.as_secs() as f64 +
+                                  r.duration.subsec_nanos() as f64 / 1_000_000_000_f64;
+                       (labels.clone(), secs.to_string())
+                   })
+                   .collect());
+    add_metric(&mut out,
+               "factotum_task_return_code",
+               "The return code of each task on the last run.",
+               &ran.iter()
+                   .map(|&(ref labels, r)| (labels.clone(), r.return_code.to_string()))
+                   .collect());
+    add_metric(&mut out,
+               "factotum_task_success",
+               "Whether each task succeeded (1) or not (0) on the last run.",
+               &tasks.iter()
+                   .map(|t| {
+                       let success = match t.state {
+                           State::Success | State::SuccessNoop => "1",
+                           _ => "0",
+                       };
+                       (format!("{},task=\"{}\"", job_label, escape_label(&t.name)),
+                        success.to_string())
+                   })
+                   .collect());
+
+    out
+}
+
+pub fn write_textfile(path: &str,
+                      job_name: &str,
+                      succeeded: bool,
+                      tasks: &Vec<&Task<&FactfileTask>>)
+                      -> Result<(), String> {
+    // the textfile collector may read at any time, so the file is always replaced whole
+    let textfile = to_textfile(job_name, succeeded, &UTC::now(), tasks);
+    runrecord::write_file_atomically(path, textfile.as_bytes())
+}
@@ -0,0 +1,59 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use factotum::tests::make_task;
+use factotum::executor::task_list::{Task, State};
+use factotum::executor::execution_strategy::RunResult;
+use factotum::factfile::Task as FactfileTask;
+use chrono::{TimeZone, UTC};
+use std::time::Duration;
+
+#[test]
+fn textfile_has_job_and_task_metrics() {
+    let apple_spec = make_task("apple", &vec![]);
+    let turnip_spec = make_task("tur\"nip", &vec!["apple"]);
+    let mut apple = Task::<&FactfileTask>::new("apple", &apple_spec);
+    apple.state = State::Failed("bad things".to_string());
+    apple.run_result = Some(RunResult {
+        return_code: 3,
+        duration: Duration::from_millis(1500),
+        ..Default::default()
+    });
+    let mut turnip = Task::<&FactfileTask>::new("tur\"nip", &turnip_spec);
+    turnip.state = State::Skipped("the task 'apple' failed".to_string());
+
+    let finished = UTC.ymd(2016, 1, 1).and_hms(0, 0, 0);
+    let textfile = to_textfile("my job", false, &finished, &vec![&apple, &turnip]);
+
+    let expected = "# HELP factotum_job_success Whether the last run of the job succeeded (1) \
+                    or not (0).\n# TYPE factotum_job_success gauge\n\
+                    factotum_job_success{job=\"my job\"} 0\n\
+                    # HELP factotum_job_last_run_timestamp_seconds When the last run of the job \
+                    finished, as a unix timestamp.\n\
+                    # TYPE factotum_job_last_run_timestamp_seconds gauge\n\
+                    factotum_job_last_run_timestamp_seconds{job=\"my job\"} 1451606400\n\
+                    # HELP factotum_task_duration_seconds How long each task took on the last \
+                    run.\n# TYPE factotum_task_duration_seconds gauge\n\
+                    factotum_task_duration_seconds{job=\"my job\",task=\"apple\"} 1.5\n\
+                    # HELP factotum_task_return_code The return code of each task on the last \
+                    run.\n# TYPE factotum_task_return_code gauge\n\
+                    factotum_task_return_code{job=\"my job\",task=\"apple\"} 3\n\
+                    # HELP factotum_task_success Whether each task succeeded (1) or not (0) on \
+                    the last run.\n# TYPE factotum_task_success gauge\n\
+                    factotum_task_success{job=\"my job\",task=\"apple\"} 0\n\
+                    factotum_task_success{job=\"my job\",task=\"tur\\\"nip\"} 0\n";
+
+    assert_eq!(textfile, expected);
+}
@@ -0,0 +1,103 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::task_list::Task;
+use factotum::factfile::Task as FactfileTask;
+use factotum::runrecord;
+use factotum::webhook::jobupdate::to_string_datetime;
+use chrono::{DateTime, UTC};
+use rustc_serialize::json::{Json, ToJson};
+use std::collections::BTreeMap;
+
+pub fn to_result_json(job_name: &str,
+                      run_id: &str,
+                      succeeded: bool,
+                      started: &DateTime<UTC>,
+                      finished: &DateTime<UTC>,
+                      tasks: &Vec<&Task<&FactfileTask>>)
+                      -> Json {
+    let task_results = tasks.iter()
+        .map(|t| {
+            let mut d = BTreeMap::new();
+            d.insert("name".to_string(), t.name.to_json());
+            d.insert("state".to_string(), runrecord::state_name(&t.state).to_json());
+            // only tasks that actually ran have a start, duration, return code or output
+            if let Some(ref started) = t.run_started {
+                d.insert("started".to_string(), to_string_datetime(started).to_json());
+            }
+            if let Some(ref r) = t.run_result {
+                let secs = r.duration.as_secs() as f64 +
+                           r.duration.subsec_nanos() as f64 / 1_000_000_000_f64;
+                d.insert("durationSeconds".to_string(), secs.to_json());
+                d.insert("returnCode".to_string(), r.return_code.to_json());
+                d.insert("stdoutBytes".to_string(), r.stdout_bytes().to_json());
+                d.insert("stderrBytes".to_string(), r.stderr_bytes().to_json());
+                if !r.outputs.is_empty() {
+                    d.insert("outputs".to_string(), r.outputs.to_json());
+                }
+            }
+            Json::Object(d)
+        })
+        .collect::<Vec<Json>>();
+
+    let mut d = BTreeMap::new();
+    d.insert("jobName".to_string(), job_name.to_json());
+    d.insert("runId".to_string(), run_id.to_json());
+    d.insert("succeeded".to_string(), succeeded.to_json());
+    d.insert("startTime".to_string(), to_string_datetime(started).to_json());
+    d.insert("endTime".to_string(), to_string_datetime(finished).to_json());
+    d.insert("tasks".to_string(), Json::Array(task_results));
+    Json::Object(d)
+}
+
+// the URL is expected to be pre-signed, so it's never included in the error - it holds credentials
+pub fn http_put(url: &str, data: &str) -> Result<(), String> {
+    use hyper::Client;
+    use hyper::net::HttpsConnector;
+    use hyper_native_tls::NativeTlsClient;
+    use hyper::header::{Headers, ContentType};
+    use hyper::mime::{Mime, TopLevel, SubLevel, Attr, Value};
+
+    let ssl = try!(NativeTlsClient::new().map_err(|e| e.to_string()));
+    let client = Client::with_connector(HttpsConnector::new(ssl));
+    let mut headers = Headers::new();
+    headers.set(ContentType(Mime(TopLevel::Application,
+                                 SubLevel::Json,
+                                 vec![(Attr::Charset, Value::Utf8)])));
+
+    let res = try!(client.put(url)
+        .headers(headers)
+        .body(data)
+        .send()
+        .map_err(|e| e.to_string()));
+    if res.status.is_success() {
+        Ok(())
+    } else {
+        Err(format!("the server returned {}", res.status))
+    }
+}
+
+pub fn upload_result(url: &str,
+                     job_name: &str,
+                     run_id: &str,
+                     succeeded: bool,
+                     started: &DateTime<UTC>,
+                     tasks: &Vec<&Task<&FactfileTask>>)
+                     -> Result<(), String> {
+    let result = to_result_json(job_name, run_id, succeeded, started, &UTC::now(), tasks);
+    http_put(url, &result.to_string())
+}
@@ -0,0 +1,56 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use factotum::tests::make_task;
+use factotum::executor::task_list::{Task, State};
+use factotum::executor::execution_strategy::RunResult;
+use factotum::factfile::Task as FactfileTask;
+use chrono::{TimeZone, UTC};
+use std::time::Duration;
+
+#[test]
+fn result_json_has_the_job_and_every_task() {
+    let apple_spec = make_task("apple", &vec![]);
+    let turnip_spec = make_task("turnip", &vec!["apple"]);
+    let started = UTC.ymd(2016, 1, 1).and_hms(0, 0, 0);
+    let finished = UTC.ymd(2016, 1, 1).and_hms(0, 0, 2);
+
+    let mut apple = Task::<&FactfileTask>::new("apple", &apple_spec);
+    apple.state = State::Failed("bad things".to_string());
+    apple.run_started = Some(started);
+    apple.run_result = Some(RunResult {
+        return_code: 3,
+        duration: Duration::from_millis(1500),
+        stdout: Some("oops".to_string()),
+        ..Default::default()
+    });
+    let mut turnip = Task::<&FactfileTask>::new("turnip", &turnip_spec);
+    turnip.state = State::Skipped("the task 'apple' failed".to_string());
+
+    let result = to_result_json("my job",
+                                "abc-123",
+                                false,
+                                &started,
+                                &finished,
+                                &vec![&apple, &turnip]);
+
+    assert_eq!(result.to_string(),
+               "{\"endTime\":\"2016-01-01T00:00:02.000Z\",\"jobName\":\"my job\",\
+                \"runId\":\"abc-123\",\"startTime\":\"2016-01-01T00:00:00.000Z\",\
+                \"succeeded\":false,\"tasks\":[{\"durationSeconds\":1.5,\"name\":\"apple\",\
+                \"returnCode\":3,\"started\":\"2016-01-01T00:00:00.000Z\",\"state\":\"FAILED\",\
+                \"stderrBytes\":0,\"stdoutBytes\":4},\
+                {\"name\":\"turnip\",\"state\":\"SKIPPED\"}]}");
+}
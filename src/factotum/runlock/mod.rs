@@ -0,0 +1,118 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use libc;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::io::prelude::*;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+
+pub const RUN_LOCK_DIR: &'static str = ".factotum/locks";
+
+pub fn get_default_lock_path(factfile: &str) -> String {
+    let full_path = match fs::canonicalize(factfile) {
+        Ok(p) => format!("{}", p.display()),
+        Err(_) => factfile.to_string(),
+    };
+    let mut digest = Sha256::new();
+    digest.input_str(&full_path);
+    format!("{}/{}.lock", RUN_LOCK_DIR, digest.result_str())
+}
+
+#[derive(Debug)]
+pub struct RunLock {
+    path: String,
+    // the lock is held for as long as this stays open, and dies with the process
+    file: File,
+}
+
+impl RunLock {
+    pub fn acquire(path: &str) -> Result<RunLock, String> {
+        // the file can be removed by its holder between us opening and locking it, in which
+        // case the lock we got is on a file nobody else can see, so try again
+        for _ in 0..10 {
+            let mut file = try!(OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open(path)
+                .map_err(|e| format!("couldn't create the lock file '{}': {}", path, e)));
+
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() != ErrorKind::WouldBlock {
+                    return Err(format!("couldn't lock the lock file '{}': {}", path, err));
+                }
+                return Err(match read_lock_pid(path) {
+                    Some(pid) => {
+                        format!("another factotum (pid {}) is already running this job", pid)
+                    }
+                    None => "another factotum is already running this job".to_string(),
+                });
+            }
+
+            if !is_same_file(&file, path) {
+                continue;
+            }
+
+            // a pid left behind by a factotum that died is simply replaced
+            let pid = unsafe { libc::getpid() };
+            try!(file.set_len(0)
+                .and_then(|_| file.write_all(pid.to_string().as_bytes()))
+                .and_then(|_| file.sync_all())
+                .map_err(|e| format!("couldn't write the lock file '{}': {}", path, e)));
+            return Ok(RunLock {
+                path: path.to_string(),
+                file: file,
+            });
+        }
+        Err(format!("couldn't create the lock file '{}'", path))
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        // removed while it's still locked, so anyone waiting on it sees it's gone
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("couldn't remove the lock file '{}': {}", self.path, e);
+        }
+        unsafe { libc::flock(self.file.as_raw_fd(), libc::LOCK_UN) };
+    }
+}
+
+fn is_same_file(file: &File, path: &str) -> bool {
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(named)) => open.dev() == named.dev() && open.ino() == named.ino(),
+        _ => false,
+    }
+}
+
+pub fn read_lock_pid(path: &str) -> Option<i32> {
+    let mut contents = String::new();
+    match File::open(path).and_then(|mut fh| fh.read_to_string(&mut contents)) {
+        Ok(_) => contents.trim().parse::<i32>().ok(),
+        Err(_) => None,
+    }
+}
+
+pub fn ensure_lock_dir() -> Result<(), String> {
+    fs::create_dir_all(RUN_LOCK_DIR)
+        .map_err(|e| format!("couldn't create directory '{}': {}", RUN_LOCK_DIR, e))
+}
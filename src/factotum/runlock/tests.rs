@@ -0,0 +1,84 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use libc;
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+fn temp_lock_path(name: &str) -> String {
+    let path = env::temp_dir().join(format!("factotum-{}.lock", name));
+    let path_str = path.to_str().unwrap().to_string();
+    fs::remove_file(&path_str).ok();
+    path_str
+}
+
+#[test]
+fn default_lock_path_is_stable() {
+    let path = get_default_lock_path("tests/resources/example_ok.factfile");
+    assert!(path.starts_with(".factotum/locks/"));
+    assert!(path.ends_with(".lock"));
+    assert_eq!(path, get_default_lock_path("tests/resources/example_ok.factfile"));
+    assert!(path != get_default_lock_path("tests/resources/example_ok_defaults.factfile"));
+}
+
+#[test]
+fn lock_is_exclusive_and_released_on_drop() {
+    let path = temp_lock_path("exclusive");
+    {
+        let _lock = RunLock::acquire(&path).unwrap();
+        assert!(Path::new(&path).exists());
+        let pid = unsafe { libc::getpid() };
+        assert_eq!(read_lock_pid(&path), Some(pid));
+        let err = RunLock::acquire(&path).unwrap_err();
+        assert_eq!(err,
+                   format!("another factotum (pid {}) is already running this job", pid));
+    }
+    assert!(!Path::new(&path).exists());
+    assert!(RunLock::acquire(&path).is_ok());
+}
+
+#[test]
+fn stale_locks_are_replaced() {
+    let path = temp_lock_path("stale");
+    {
+        // pids don't go this high on linux, so this process can't exist
+        let mut fh = File::create(&path).unwrap();
+        fh.write_all(b"2147483647").unwrap();
+    }
+    {
+        let _lock = RunLock::acquire(&path).unwrap();
+        assert_eq!(read_lock_pid(&path), Some(unsafe { libc::getpid() }));
+    }
+
+    File::create(&path).unwrap();
+    assert!(RunLock::acquire(&path).is_ok());
+    assert!(!Path::new(&path).exists());
+}
+
+#[test]
+fn a_lock_file_that_was_replaced_is_not_the_same_file() {
+    let path = temp_lock_path("replaced");
+    let old = File::create(&path).unwrap();
+    assert!(is_same_file(&old, &path));
+
+    fs::remove_file(&path).unwrap();
+    assert!(!is_same_file(&old, &path));
+    File::create(&path).unwrap();
+    assert!(!is_same_file(&old, &path));
+
+    fs::remove_file(&path).unwrap();
+}
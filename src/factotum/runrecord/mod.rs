@@ -0,0 +1,163 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::task_list::{Task, State};
+use factotum::factfile::Task as FactfileTask;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use rustc_serialize::json;
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+const RUN_RECORD_DIR: &'static str = ".factotum/runs";
+const STATE_SUCCEEDED: &'static str = "SUCCEEDED";
+const STATE_FAILED: &'static str = "FAILED";
+
+#[derive(RustcEncodable, RustcDecodable, Debug, PartialEq, Clone)]
+pub struct TaskRecord {
+    pub name: String,
+    pub state: String,
+}
+
+#[derive(RustcEncodable, RustcDecodable, Debug, PartialEq, Clone)]
+#[allow(non_snake_case)]
+pub struct RunRecord {
+    pub factfile: String,
+    pub factfileHash: String,
+    pub tasks: Vec<TaskRecord>,
+}
+
+impl RunRecord {
+    pub fn new(factfile: &str, factfile_raw: &str, tasks: &Vec<&Task<&FactfileTask>>) -> Self {
+        RunRecord {
+            factfile: factfile.to_string(),
+            factfileHash: hash_factfile(factfile_raw),
+            tasks: tasks.iter()
+                .map(|t| {
+                    TaskRecord {
+                        name: t.name.clone(),
+                        state: state_name(&t.state).to_string(),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    pub fn succeeded_tasks(&self) -> Vec<String> {
+        self.tasks_in_state(STATE_SUCCEEDED)
+    }
+
+    pub fn failed_tasks(&self) -> Vec<String> {
+        self.tasks_in_state(STATE_FAILED)
+    }
+
+    fn tasks_in_state(&self, state: &str) -> Vec<String> {
+        self.tasks
+            .iter()
+            .filter(|t| t.state == state)
+            .map(|t| t.name.clone())
+            .collect()
+    }
+
+    pub fn is_same_factfile(&self, factfile_raw: &str) -> bool {
+        self.factfileHash == hash_factfile(factfile_raw)
+    }
+
+    pub fn to_json_string(&self) -> String {
+        json::encode(self).unwrap()
+    }
+
+    pub fn from_json_string(s: &str) -> Result<Self, String> {
+        json::decode(s).map_err(|e| format!("the run record is not valid: {}", e))
+    }
+}
+
+pub fn state_name(state: &State) -> &'static str {
+    match *state {
+        State::Waiting => "WAITING",
+        State::Running => "RUNNING",
+        State::Success => STATE_SUCCEEDED,
+        State::SuccessNoop => "SUCCEEDED_NO_OP",
+        State::Failed(_) => STATE_FAILED,
+        State::Skipped(_) => "SKIPPED",
+    }
+}
+
+pub fn hash_factfile(factfile_raw: &str) -> String {
+    let mut digest = Sha256::new();
+    digest.input_str(factfile_raw);
+    digest.result_str()
+}
+
+pub fn get_default_record_path(factfile: &str) -> String {
+    let full_path = match fs::canonicalize(factfile) {
+        Ok(p) => format!("{}", p.display()),
+        Err(_) => factfile.to_string(),
+    };
+    let mut digest = Sha256::new();
+    digest.input_str(&full_path);
+    format!("{}/{}.json", RUN_RECORD_DIR, digest.result_str())
+}
+
+pub fn read_record(path: &str) -> Result<RunRecord, String> {
+    let mut fh = try!(File::open(path)
+        .map_err(|e| format!("couldn't open run record '{}' for reading: {}", path, e)));
+    let mut contents = String::new();
+    try!(fh.read_to_string(&mut contents)
+        .map_err(|e| format!("couldn't read run record '{}': {}", path, e)));
+    RunRecord::from_json_string(&contents)
+}
+
+pub fn write_record(path: &str, record: &RunRecord) -> Result<(), String> {
+    write_file_atomically(path, record.to_json_string().as_bytes())
+}
+
+pub fn write_file_atomically(path: &str, contents: &[u8]) -> Result<(), String> {
+    // write to a temporary file first and rename it into place, so a crash mid-write can't
+    // leave a truncated file behind
+    let temp_path = format!("{}.tmp", path);
+    {
+        let mut fh = try!(File::create(&temp_path)
+            .map_err(|e| format!("couldn't create file '{}' ({})", temp_path, e)));
+        try!(fh.write_all(contents)
+            .map_err(|e| format!("couldn't write to file '{}' ({})", temp_path, e)));
+        try!(fh.sync_all().map_err(|e| format!("couldn't write to file '{}' ({})", temp_path, e)));
+    }
+    fs::rename(&temp_path, path)
+        .map_err(|e| format!("couldn't move '{}' to '{}' ({})", temp_path, path, e))
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct StateFile {
+    pub path: String,
+    pub factfile: String,
+}
+
+impl StateFile {
+    pub fn update(&self, factfile_raw: &str, tasks: &Vec<&Task<&FactfileTask>>) {
+        let record = RunRecord::new(&self.factfile, factfile_raw, tasks);
+        if let Err(msg) = write_record(&self.path, &record) {
+            warn!("couldn't update the state file: {}", msg);
+        }
+    }
+}
+
+pub fn ensure_record_dir() -> Result<(), String> {
+    fs::create_dir_all(RUN_RECORD_DIR)
+        .map_err(|e| format!("couldn't create directory '{}': {}", RUN_RECORD_DIR, e))
+}
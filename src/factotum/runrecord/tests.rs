@@ -0,0 +1,98 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+
+use super::*;
+use factotum::tests::make_task;
+use factotum::executor::task_list::{Task, State};
+use factotum::factfile::Task as FactfileTask;
+
+#[test]
+fn record_captures_task_states() {
+    let apple_spec = make_task("apple", &vec![]);
+    let turnip_spec = make_task("turnip", &vec!["apple"]);
+    let mut apple = Task::<&FactfileTask>::new("apple", &apple_spec);
+    apple.state = State::Success;
+    let mut turnip = Task::<&FactfileTask>::new("turnip", &turnip_spec);
+    turnip.state = State::Failed("bad things".to_string());
+
+    let record = RunRecord::new("example.factfile", "{}", &vec![&apple, &turnip]);
+
+    assert_eq!(record.factfile, "example.factfile");
+    assert_eq!(record.succeeded_tasks(), vec!["apple"]);
+    assert_eq!(record.failed_tasks(), vec!["turnip"]);
+    assert!(record.is_same_factfile("{}"));
+    assert!(!record.is_same_factfile("{\"changed\":true}"));
+}
+
+#[test]
+fn record_round_trips_json() {
+    let record = RunRecord {
+        factfile: "example.factfile".to_string(),
+        factfileHash: hash_factfile("{}"),
+        tasks: vec![TaskRecord {
+                        name: "apple".to_string(),
+                        state: "SKIPPED".to_string(),
+                    }],
+    };
+
+    let json = record.to_json_string();
+    assert_eq!(RunRecord::from_json_string(&json), Ok(record));
+}
+
+#[test]
+fn record_invalid_json_err() {
+    assert!(RunRecord::from_json_string("{\"factfile\":").is_err());
+}
+
+#[test]
+fn default_record_path_is_stable() {
+    let path = get_default_record_path("./tests/resources/example_ok.factfile");
+    assert!(path.starts_with(".factotum/runs/"));
+    assert!(path.ends_with(".json"));
+    assert_eq!(path,
+               get_default_record_path("./tests/resources/example_ok.factfile"));
+    assert!(path != get_default_record_path("./tests/resources/invalid_json.factfile"));
+}
+
+#[test]
+fn write_record_replaces_file() {
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    let path = env::temp_dir().join("factotum-write-record-test.json");
+    let path_str = path.to_str().unwrap();
+
+    let first = RunRecord {
+        factfile: "example.factfile".to_string(),
+        factfileHash: hash_factfile("{}"),
+        tasks: vec![],
+    };
+    assert_eq!(write_record(path_str, &first), Ok(()));
+
+    let second = RunRecord {
+        tasks: vec![TaskRecord {
+                        name: "apple".to_string(),
+                        state: "RUNNING".to_string(),
+                    }],
+        ..first.clone()
+    };
+    assert_eq!(write_record(path_str, &second), Ok(()));
+
+    assert_eq!(read_record(path_str), Ok(second));
+    assert!(!Path::new(&format!("{}.tmp", path_str)).exists());
+
+    fs::remove_file(&path).unwrap();
+}
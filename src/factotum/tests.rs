@@ -38,5 +38,6 @@ pub fn make_task(name: &str, depends_on: &Vec<&str>) -> Task {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     }
 }
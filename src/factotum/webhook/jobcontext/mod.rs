@@ -29,6 +29,7 @@ pub struct JobContext {
     pub job_reference: String,
     pub run_reference: String,
     pub factfile: String,
+    pub factfile_sha256: String,
     pub factotum_version: String,
     pub start_time: DateTime<UTC>,
     pub tags: HashMap<String,String>,
@@ -52,6 +53,10 @@ impl JobContext {
 
         let job_ref = job_digest.result_str();
 
+        let mut factfile_digest = Sha256::new();
+        factfile_digest.input_str(&ff);
+        let ff_sha256 = factfile_digest.result_str();
+
         let mut run_digest = Sha256::new();
         run_digest.input_str(&format!("{}", Uuid::new_v4()));
         let run_ref = run_digest.result_str();
@@ -71,6 +76,7 @@ impl JobContext {
             job_reference: job_ref,
             run_reference: run_ref,
             factfile: b64_ff,
+            factfile_sha256: ff_sha256,
             factotum_version: env!("CARGO_PKG_VERSION").to_string(),
             start_time: UTC::now(),
             tags: job_tags,
@@ -47,6 +47,21 @@ fn new_sets_job_ref_to_hash_of_factfile() {
     assert_eq!(context.job_reference, expected);
 }
 
+#[test]
+fn factfile_hash_ignores_tags() {
+    let factfile_sim = "{Blabla}";
+    let mut digest = Sha256::new();
+    digest.input_str(factfile_sim);
+    let expected = digest.result_str();
+
+    let mut tags = HashMap::new();
+    tags.insert("env".to_string(), "prod".to_string());
+    let context = JobContext::new("hello", factfile_sim, Some(tags));
+
+    assert_eq!(context.factfile_sha256, expected);
+    assert!(context.job_reference != expected);
+}
+
 #[test]
 fn new_sets_run_ref_to_random_hash() {
     let job_name = "hello";
@@ -0,0 +1,114 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+static JOB_EVENT_SCHEMA_NAME: &'static str = "iglu:com.snowplowanalytics.\
+                                              factotum/job_event/jsonschema/1-0-0";
+
+use factotum::executor::{ExecutionUpdate, JobEvent};
+use factotum::executor::task_list::State;
+use super::FactfileInclusion;
+use super::jobcontext::JobContext;
+use super::jobupdate::to_string_datetime;
+use chrono::UTC;
+use rustc_serialize::json::{ToJson, Json};
+use std::collections::BTreeMap;
+
+// brackets a run for consumers that don't want to infer it from the job transitions
+pub fn to_job_event(context: &JobContext,
+                    host: &Option<String>,
+                    update: &ExecutionUpdate,
+                    include_factfile: FactfileInclusion)
+                    -> Option<Json> {
+    let event = match update.get_job_event() {
+        Some(event) => event,
+        None => return None,
+    };
+
+    let tasks = &update.task_snapshot;
+    let mut d = BTreeMap::new();
+
+    d.insert("jobName".to_string(), context.job_name.to_json());
+    d.insert("jobReference".to_string(), context.job_reference.to_json());
+    d.insert("runReference".to_string(), context.run_reference.to_json());
+    d.insert("startTime".to_string(), to_string_datetime(&context.start_time).to_json());
+    d.insert("taskCount".to_string(), tasks.len().to_json());
+
+    if let Some(ref host) = *host {
+        d.insert("host".to_string(), host.to_json());
+    }
+    if let Some(ref label) = update.run_label {
+        d.insert("runLabel".to_string(), label.to_json());
+    }
+
+    match event {
+        JobEvent::Started => {
+            d.insert("event".to_string(), "job_started".to_json());
+            match include_factfile {
+                FactfileInclusion::Full => {
+                    d.insert("factfile".to_string(), context.factfile.to_json());
+                }
+                FactfileInclusion::Hash => {
+                    d.insert("factfileSha256".to_string(), context.factfile_sha256.to_json());
+                }
+                FactfileInclusion::Excluded => (),
+            }
+        }
+        JobEvent::Finished => {
+            let succeeded = tasks.iter()
+                .filter(|t| t.state == State::Success || t.state == State::SuccessNoop)
+                .count();
+            let failed = tasks.iter()
+                .filter(|t| match t.state {
+                    State::Failed(_) => true,
+                    _ => false,
+                })
+                .count();
+            let skipped = tasks.iter()
+                .filter(|t| match t.state {
+                    State::Skipped(_) => true,
+                    _ => false,
+                })
+                .count();
+
+            let now = UTC::now();
+            d.insert("event".to_string(), "job_finished".to_json());
+            d.insert("endTime".to_string(), to_string_datetime(&now).to_json());
+            d.insert("runDuration".to_string(),
+                     (now - context.start_time).to_string().to_json());
+            d.insert("runState".to_string(),
+                     if failed > 0 { "FAILED" } else { "SUCCEEDED" }.to_json());
+            d.insert("succeeded".to_string(), succeeded.to_json());
+            d.insert("failed".to_string(), failed.to_json());
+            d.insert("skipped".to_string(), skipped.to_json());
+        }
+    }
+
+    Some(Json::Object(d))
+}
+
+pub fn to_job_event_json(context: &JobContext,
+                         host: &Option<String>,
+                         update: &ExecutionUpdate,
+                         include_factfile: FactfileInclusion)
+                         -> Option<String> {
+    to_job_event(context, host, update, include_factfile).map(|data| {
+        let mut wrapped = BTreeMap::new();
+        wrapped.insert("schema".to_string(), JOB_EVENT_SCHEMA_NAME.to_json());
+        wrapped.insert("data".to_string(), data);
+        Json::Object(wrapped).to_string()
+    })
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use factotum::executor::{ExecutionState, ExecutionUpdate, JobTransition, Transition};
+use factotum::executor::task_list::{Task, State};
+use factotum::factfile::Task as FactfileTask;
+
+fn make_update(from: Option<ExecutionState>,
+               to: ExecutionState,
+               task_states: Vec<State>)
+               -> ExecutionUpdate {
+    let snapshot = task_states.into_iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let mut task = Task::new(format!("task-{}", i), FactfileTask::default());
+            task.state = s;
+            task
+        })
+        .collect();
+    ExecutionUpdate::new(to.clone(),
+                         snapshot,
+                         Transition::Job(JobTransition::new(from, to)))
+}
+
+fn get_field(event: &Json, field: &str) -> Json {
+    event.as_object().unwrap()[field].clone()
+}
+
+#[test]
+fn only_job_start_and_finish_are_events() {
+    let context = JobContext::new("job", "{}", None);
+    let running = make_update(Some(ExecutionState::Started),
+                              ExecutionState::Running,
+                              vec![State::Running]);
+    assert_eq!(to_job_event(&context, &None, &running, FactfileInclusion::Hash), None);
+}
+
+#[test]
+fn job_started_event_has_the_run_details() {
+    let context = JobContext::new("job", "{}", None);
+    let started = make_update(None,
+                              ExecutionState::Started,
+                              vec![State::Waiting, State::Waiting]);
+    let event = to_job_event(&context,
+                             &Some("box-1".to_string()),
+                             &started,
+                             FactfileInclusion::Hash)
+        .unwrap();
+
+    assert_eq!(get_field(&event, "event"), "job_started".to_json());
+    assert_eq!(get_field(&event, "jobName"), "job".to_json());
+    assert_eq!(get_field(&event, "runReference"), context.run_reference.to_json());
+    assert_eq!(get_field(&event, "host"), "box-1".to_json());
+    assert_eq!(get_field(&event, "taskCount"), 2usize.to_json());
+    assert!(event.as_object().unwrap().get("runState").is_none());
+}
+
+#[test]
+fn job_finished_event_has_the_outcome() {
+    let context = JobContext::new("job", "{}", None);
+    let finished = make_update(Some(ExecutionState::Running),
+                               ExecutionState::Finished,
+                               vec![State::Success,
+                                    State::Failed("bad".to_string()),
+                                    State::Skipped("bad".to_string())]);
+    let event = to_job_event(&context, &None, &finished, FactfileInclusion::Full).unwrap();
+
+    assert_eq!(get_field(&event, "event"), "job_finished".to_json());
+    assert_eq!(get_field(&event, "runState"), "FAILED".to_json());
+    assert_eq!(get_field(&event, "succeeded"), 1usize.to_json());
+    assert_eq!(get_field(&event, "failed"), 1usize.to_json());
+    assert_eq!(get_field(&event, "skipped"), 1usize.to_json());
+    assert!(event.as_object().unwrap().get("host").is_none());
+
+    let json = to_job_event_json(&context, &None, &finished, FactfileInclusion::Hash).unwrap();
+    assert!(json.starts_with("{\"data\":{"));
+    assert!(json.ends_with("\"schema\":\"iglu:com.snowplowanalytics.factotum/job_event/\
+                            jsonschema/1-0-0\"}"));
+}
+
+#[test]
+fn job_started_event_includes_the_factfile_as_asked() {
+    let context = JobContext::new("job", "{}", None);
+    let started = make_update(None, ExecutionState::Started, vec![State::Waiting]);
+
+    let hashed = to_job_event(&context, &None, &started, FactfileInclusion::Hash).unwrap();
+    assert_eq!(get_field(&hashed, "factfileSha256"),
+               context.factfile_sha256.to_json());
+    assert!(hashed.as_object().unwrap().get("factfile").is_none());
+
+    let full = to_job_event(&context, &None, &started, FactfileInclusion::Full).unwrap();
+    assert_eq!(get_field(&full, "factfile"), context.factfile.to_json());
+    assert!(full.as_object().unwrap().get("factfileSha256").is_none());
+
+    let excluded = to_job_event(&context, &None, &started, FactfileInclusion::Excluded)
+        .unwrap();
+    assert!(excluded.as_object().unwrap().get("factfile").is_none());
+    assert!(excluded.as_object().unwrap().get("factfileSha256").is_none());
+}
@@ -224,7 +224,23 @@ impl JobUpdate {
             runReference: context.run_reference.clone(),
             factfile: context.factfile.clone(),
             applicationContext: ApplicationContext::new(&context),
-            tags: context.tags.clone(),
+            tags: {
+                // the run label and execution types go in with the tags, so the update still
+                // matches its schema
+                let mut tags = context.tags.clone();
+                if let Some(ref label) = execution_update.run_label {
+                    tags.insert("runLabel".to_string(), label.clone());
+                }
+                for (task_name, execution_type) in execution_update.execution_types.iter() {
+                    tags.insert(format!("executionType:{}", task_name),
+                                execution_type.as_str().to_string());
+                }
+                for (task_name, variance) in execution_update.duration_alerts.iter() {
+                    tags.insert(format!("durationAlert:{}", task_name),
+                                format!("{:+.0}%", variance));
+                }
+                tags
+            },
             runState: to_job_run_state(&execution_update.execution_state,
                                        &execution_update.task_snapshot),
             startTime: to_string_datetime(&context.start_time),
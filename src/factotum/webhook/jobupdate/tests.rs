@@ -170,6 +170,21 @@ fn headers_correct() {
     assert_eq!(job_update.tags, tags);
 }
 
+#[test]
+fn run_label_is_sent_as_a_tag() {
+    let context = JobContext::new("hello", "world", None);
+    let exec_update =
+        ExecutionUpdate::new(ExecutionState::Finished,
+                             vec![],
+                             Transition::Job(ExecutorJobTransition::new(Some(ExecutionState::Running),
+                                                                ExecutionState::Finished)))
+            .with_run_label(&Some("build-42".to_string()));
+    let max_stdouterr_size: usize = 10_000;
+    let job_update = JobUpdate::new(&context, &exec_update, &max_stdouterr_size);
+
+    assert_eq!(job_update.tags.get("runLabel"), Some(&"build-42".to_string()));
+}
+
 #[test]
 fn failed_headers_correct() {
     let mut ff = Factfile::new("N/A", "test");
@@ -244,6 +259,7 @@ fn task_states_converted_with_run_data() {
         stderr: Some("banana".to_string()),
         stdout: Some("get".to_string()),
         duration: Duration::seconds(0).to_std().unwrap(),
+        ..Default::default()
     });
 
     example_tasks[1].state = State::Success;
@@ -254,6 +270,7 @@ fn task_states_converted_with_run_data() {
         stderr: None,
         stdout: None,
         duration: Duration::seconds(1).to_std().unwrap(),
+        ..Default::default()
     });
 
     let start_sample =
@@ -333,6 +350,7 @@ fn big_task_stdout_trimmed() {
         stderr: None,
         stdout: Some(format!("{}tail", make_n_char_string(20000))), // too long
         duration: Duration::seconds(0).to_std().unwrap(),
+        ..Default::default()
     });
 
     example_tasks[1].state = State::Success;
@@ -343,6 +361,7 @@ fn big_task_stdout_trimmed() {
         stderr: None,
         stdout: Some(format!("{}tail", make_n_char_string(max_len-"tail".len()))), // just fits
         duration: Duration::seconds(1).to_std().unwrap(),
+        ..Default::default()
     });
 
 
@@ -392,6 +411,7 @@ fn big_task_stderr_trimmed() {
         stderr: Some(format!("{}tail", make_n_char_string(20000))), // too long,
         stdout: None,
         duration: Duration::seconds(0).to_std().unwrap(),
+        ..Default::default()
     });
 
     example_tasks[1].state = State::Success;
@@ -402,6 +422,7 @@ fn big_task_stderr_trimmed() {
         stderr: Some(format!("{}tail", make_n_char_string(max_len-"tail".len()))),
         stdout: None, // just fits
         duration: Duration::seconds(1).to_std().unwrap(),
+        ..Default::default()
     });
 
 
@@ -449,3 +470,52 @@ fn tail_n_chars_zero() {
     let twenty_character_str = make_n_char_string(20);
     assert_eq!(tail_n_chars(&twenty_character_str, 0), "");
 }
+
+#[test]
+fn execution_types_are_sent_as_tags() {
+    let mut executed = Task::new("executed", make_task("executed", &vec![]));
+    executed.state = State::Success;
+    executed.run_result = Some(RunResult { ..Default::default() });
+    let mut resumed = Task::new("resumed", make_task("resumed", &vec![]));
+    resumed.state = State::Success;
+    let mut skipped = Task::new("skipped", make_task("skipped", &vec![]));
+    skipped.state = State::Skipped("the task 'executed' failed".to_string());
+    let waiting = Task::new("waiting", make_task("waiting", &vec![]));
+
+    let context = JobContext::new("hello", "world", None);
+    let exec_update =
+        ExecutionUpdate::new(ExecutionState::Running,
+                             vec![executed, resumed, skipped, waiting],
+                             Transition::Job(ExecutorJobTransition::new(Some(ExecutionState::Started),
+                                                                ExecutionState::Running)));
+    let max_stdouterr_size: usize = 10_000;
+    let job_update = JobUpdate::new(&context, &exec_update, &max_stdouterr_size);
+
+    assert_eq!(job_update.tags.get("executionType:executed"), Some(&"executed".to_string()));
+    assert_eq!(job_update.tags.get("executionType:resumed"), Some(&"resumed".to_string()));
+    assert_eq!(job_update.tags.get("executionType:skipped"), Some(&"skipped".to_string()));
+    assert_eq!(job_update.tags.get("executionType:waiting"), None);
+}
+
+#[test]
+fn duration_alerts_are_sent_as_tags() {
+    let mut slow_spec = make_task("slow", &vec![]);
+    slow_spec.expected_duration_seconds = Some(10);
+    let mut slow = Task::new("slow", slow_spec);
+    slow.state = State::Success;
+    slow.run_result = Some(RunResult {
+        duration: ::std::time::Duration::from_secs(25),
+        ..Default::default()
+    });
+
+    let context = JobContext::new("hello", "world", None);
+    let exec_update =
+        ExecutionUpdate::new(ExecutionState::Running,
+                             vec![slow],
+                             Transition::Job(ExecutorJobTransition::new(Some(ExecutionState::Started),
+                                                                        ExecutionState::Running)))
+            .with_duration_alerts(50.0);
+    let job_update = JobUpdate::new(&context, &exec_update, &10_000);
+
+    assert_eq!(job_update.tags.get("durationAlert:slow"), Some(&"+150%".to_string()));
+}
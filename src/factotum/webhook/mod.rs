@@ -13,7 +13,9 @@
 //
 
 pub mod jobcontext;
-mod jobupdate;
+pub mod jobevent;
+pub mod jobupdate;
+mod slack;
 #[cfg(test)]
 mod tests;
 
@@ -21,14 +23,28 @@ mod tests;
 use std::thread;
 use std::thread::JoinHandle;
 use std::sync::mpsc::Receiver;
-use factotum::executor::{ExecutionState, ExecutionUpdate};
+use factotum::executor::{ExecutionState, ExecutionUpdate, JobEvent};
 use std::time::Duration;
 use rand;
 use factotum::webhook::jobcontext::JobContext;
 use std::collections::HashMap;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use rustc_serialize::hex::ToHex;
 
 const MAX_RETRIES: usize = 3;
 
+pub const SIGNATURE_HEADER: &'static str = "X-Factotum-Signature";
+pub const DEFAULT_MAX_STDOUTERR_SIZE: usize = 10_000;
+
+// in the same "sha256=<hex>" form GitHub uses, so existing verifiers can be reused
+pub fn sign_payload(secret: &str, payload: &str) -> String {
+    let mut hmac = Hmac::new(Sha256::new(), secret.as_bytes());
+    hmac.input(payload.as_bytes());
+    format!("sha256={}", hmac.result().code().to_hex())
+}
+
 pub fn backoff_rand_1_minute() -> Duration {
     let max_duration_millis = 60 * 1000;
     let random_ms = rand::random::<u64>();
@@ -55,6 +71,65 @@ impl Attempt {
     }
 }
 
+pub fn strip_task_output(update: &ExecutionUpdate) -> ExecutionUpdate {
+    let mut stripped = update.clone();
+    for task in stripped.task_snapshot.iter_mut() {
+        if let Some(ref mut result) = task.run_result {
+            result.stdout = None;
+            result.stderr = None;
+        }
+    }
+    stripped
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WebhookFormat {
+    JobUpdate,
+    Slack,
+}
+
+impl Default for WebhookFormat {
+    fn default() -> Self {
+        WebhookFormat::JobUpdate
+    }
+}
+
+pub fn get_webhook_format(name: &str) -> Result<WebhookFormat, String> {
+    match name {
+        "job-update" => Ok(WebhookFormat::JobUpdate),
+        "slack" => Ok(WebhookFormat::Slack),
+        _ => {
+            Err(format!("unknown webhook format '{}' (supported formats: job-update, slack)",
+                        name))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FactfileInclusion {
+    Full,
+    Hash,
+    Excluded,
+}
+
+impl Default for FactfileInclusion {
+    fn default() -> Self {
+        FactfileInclusion::Hash
+    }
+}
+
+pub fn get_factfile_inclusion(name: &str) -> Result<FactfileInclusion, String> {
+    match name {
+        "full" => Ok(FactfileInclusion::Full),
+        "hash" => Ok(FactfileInclusion::Hash),
+        "none" => Ok(FactfileInclusion::Excluded),
+        _ => {
+            Err(format!("unknown factfile inclusion '{}' (supported values: full, hash, none)",
+                        name))
+        }
+    }
+}
+
 pub type WebhookAttemptResult = Result<Attempt, Attempt>;
 
 #[derive(Debug,Clone,PartialEq)]
@@ -86,10 +161,20 @@ pub struct Webhook {
     pub endpoint: String,
     job_context: JobContext,
     pub max_stdouterr_size: usize,
+    pub include_output: bool,
+    pub format: WebhookFormat,
+    pub secret: Option<String>,
+    pub redact_endpoint: bool,
+    pub job_events: bool,
+    pub include_factfile: FactfileInclusion,
+    pub host: Option<String>,
 }
 
 impl Webhook {
-    pub fn http_post(url: &str, data: &str) -> Result<u32, (u32, String)> {
+    pub fn http_post(url: &str,
+                     data: &str,
+                     signature: &Option<String>)
+                     -> Result<u32, (u32, String)> {
         use hyper::Client;
         use hyper::net::HttpsConnector;
         use hyper_native_tls::NativeTlsClient;
@@ -104,6 +189,9 @@ impl Webhook {
         headers.set(ContentType(Mime(TopLevel::Application,
                                      SubLevel::Json,
                                      vec![(Attr::Charset, Value::Utf8)])));
+        if let Some(ref sig) = *signature {
+            headers.set_raw(SIGNATURE_HEADER, vec![sig.clone().into_bytes()]);
+        }
 
         let res = client.post(url)
             .headers(headers)
@@ -129,7 +217,7 @@ impl Webhook {
         let max_stdouterr_size_bytes: usize = if let Some(max_bytes) = max_stdouterr_size {
             max_bytes
         } else {
-            10_000
+            DEFAULT_MAX_STDOUTERR_SIZE
         };
 
         Webhook {
@@ -138,6 +226,13 @@ impl Webhook {
             factfile_json: ff_json,
             endpoint: endpoint.into(),
             max_stdouterr_size: max_stdouterr_size_bytes,
+            include_output: true,
+            format: WebhookFormat::default(),
+            secret: None,
+            redact_endpoint: false,
+            job_events: false,
+            include_factfile: FactfileInclusion::default(),
+            host: None,
         }
     }
 
@@ -146,13 +241,27 @@ impl Webhook {
                                  emitter_func: F,
                                  backoff_retry_period: G)
                                  -> JoinHandle<WebhookResult>
-        where F: Fn(&str, &str) -> Result<u32, (u32, String)> + Send + Sync + 'static + Copy,
+        where F: Fn(&str, &str, &Option<String>) -> Result<u32, (u32, String)> + Send + Sync +
+                 'static + Copy,
               G: Fn() -> Duration + Send + Sync + 'static
     {
 
         let endpoint = self.endpoint.clone();
         let job_context = self.job_context.clone();
         let max_stdouterr_size = self.max_stdouterr_size.clone();
+        let include_output = self.include_output;
+        let format = self.format;
+        let job_name = self.factfile_job_name.clone();
+        let secret = self.secret.clone();
+        let job_events = self.job_events;
+        let include_factfile = self.include_factfile;
+        let host = self.host.clone();
+        // the URL itself can hold credentials, so it may need to stay out of the log
+        let endpoint_name = if self.redact_endpoint {
+            "(redacted URL)".to_string()
+        } else {
+            self.endpoint.clone()
+        };
 
         thread::spawn(move || {
 
@@ -171,35 +280,77 @@ impl Webhook {
                     done = true;
                 }
 
-                let job_update = jobupdate::JobUpdate::new(&job_context, &message, &max_stdouterr_size);
-                let json_post_data = job_update.as_self_desc_json();
-
-                for _ in 0..MAX_RETRIES {
-                    let mut good = false;
-
-                    let attempt = match emitter_func(&endpoint, &json_post_data) {
-                        Ok(code) => {
-                            success_count = success_count + 1;
-                            good = true;
-                            Ok(Attempt::new(Some(code), "OK", message.clone()))
+                let update_post_data = match format {
+                    WebhookFormat::JobUpdate => {
+                        let job_update = if include_output {
+                            jobupdate::JobUpdate::new(&job_context, &message, &max_stdouterr_size)
+                        } else {
+                            jobupdate::JobUpdate::new(&job_context,
+                                                      &strip_task_output(&message),
+                                                      &max_stdouterr_size)
+                        };
+                        job_update.as_self_desc_json()
+                    }
+                    WebhookFormat::Slack => {
+                        match slack::to_slack_message(&job_name, &message) {
+                            Some(slack_message) => slack_message.to_json_string(),
+                            None => {
+                                // nothing to post for this update, so there's nothing to fail
+                                success_count = success_count + 1;
+                                continue;
+                            }
                         }
-                        Err((code, r)) => {
-                            fail_count = fail_count + 1;
-                            warn!("Failed to send webhook update to '{}': {}",
-                                  &endpoint,
-                                  &json_post_data);
-                            warn!("Reason: {}, {}", code, r);
-                            Err(Attempt::new(Some(code), r, message.clone()))
+                    }
+                };
+
+                // the job event goes before the first update of a run, and after the last
+                let mut posts = vec![update_post_data];
+                if job_events {
+                    if let Some(event) = jobevent::to_job_event_json(&job_context,
+                                                                     &host,
+                                                                     &message,
+                                                                     include_factfile) {
+                        match message.get_job_event() {
+                            Some(JobEvent::Started) => posts.insert(0, event),
+                            _ => posts.push(event),
                         }
-                    };
+                    }
+                }
 
-                    attempts.push(attempt);
+                // an update only counts as sent once its job event has been sent too
+                let mut all_sent = true;
+                for json_post_data in posts.iter() {
+                    let signature = secret.as_ref().map(|s| sign_payload(s, json_post_data));
 
-                    if good {
-                        break;
-                    } else {
-                        thread::sleep(backoff_retry_period());
+                    let mut good = false;
+                    for _ in 0..MAX_RETRIES {
+                        let attempt = match emitter_func(&endpoint, json_post_data, &signature) {
+                            Ok(code) => {
+                                good = true;
+                                Ok(Attempt::new(Some(code), "OK", message.clone()))
+                            }
+                            Err((code, r)) => {
+                                fail_count = fail_count + 1;
+                                warn!("Failed to send webhook update to '{}': {}",
+                                      &endpoint_name,
+                                      json_post_data);
+                                warn!("Reason: {}, {}", code, r);
+                                Err(Attempt::new(Some(code), r, message.clone()))
+                            }
+                        };
+
+                        attempts.push(attempt);
+
+                        if good {
+                            break;
+                        } else {
+                            thread::sleep(backoff_retry_period());
+                        }
                     }
+                    all_sent = all_sent && good;
+                }
+                if all_sent {
+                    success_count = success_count + 1;
                 }
             }
 
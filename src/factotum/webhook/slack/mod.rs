@@ -0,0 +1,108 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+#[cfg(test)]
+mod tests;
+
+use factotum::executor::{ExecutionState, ExecutionUpdate};
+use factotum::executor::task_list::State;
+use rustc_serialize::json;
+
+pub const COLOR_SUCCESS: &'static str = "good";
+pub const COLOR_EARLY_FINISH: &'static str = "warning";
+pub const COLOR_FAILURE: &'static str = "danger";
+pub const COLOR_NOT_RUN: &'static str = "#cccccc";
+
+#[derive(RustcEncodable, Debug, PartialEq)]
+pub struct SlackAttachment {
+    pub color: String,
+    pub title: String,
+    pub text: String,
+}
+
+#[derive(RustcEncodable, Debug, PartialEq)]
+pub struct SlackMessage {
+    pub text: String,
+    pub attachments: Vec<SlackAttachment>,
+}
+
+impl SlackMessage {
+    pub fn to_json_string(&self) -> String {
+        json::encode(self).unwrap()
+    }
+}
+
+fn get_state_color(state: &State) -> &'static str {
+    match *state {
+        State::Success => COLOR_SUCCESS,
+        State::SuccessNoop => COLOR_EARLY_FINISH,
+        State::Failed(_) => COLOR_FAILURE,
+        _ => COLOR_NOT_RUN,
+    }
+}
+
+fn get_state_text(state: &State) -> String {
+    match *state {
+        State::Waiting => "waiting".to_string(),
+        State::Running => "running".to_string(),
+        State::Success => "succeeded".to_string(),
+        State::SuccessNoop => "succeeded, and requested an early finish".to_string(),
+        State::Failed(ref reason) => format!("failed: {}", reason),
+        State::Skipped(ref reason) => format!("skipped: {}", reason),
+    }
+}
+
+// only the end of a job is posted to Slack - an update per task transition is too noisy for a channel
+pub fn to_slack_message(job_name: &str, update: &ExecutionUpdate) -> Option<SlackMessage> {
+    if update.execution_state != ExecutionState::Finished {
+        return None;
+    }
+
+    let tasks = &update.task_snapshot;
+
+    let (job_color, outcome) = if tasks.iter().any(|t| match t.state {
+        State::Failed(_) => true,
+        _ => false,
+    }) {
+        (COLOR_FAILURE, "failed")
+    } else if tasks.iter().any(|t| t.state == State::SuccessNoop) {
+        (COLOR_EARLY_FINISH, "finished early")
+    } else {
+        (COLOR_SUCCESS, "succeeded")
+    };
+
+    let mut attachments = vec![SlackAttachment {
+                                   color: job_color.to_string(),
+                                   title: job_name.to_string(),
+                                   text: format!("{} tasks", tasks.len()),
+                               }];
+
+    for task in tasks.iter() {
+        attachments.push(SlackAttachment {
+            color: get_state_color(&task.state).to_string(),
+            title: task.name.clone(),
+            text: get_state_text(&task.state),
+        });
+    }
+
+    let run = match update.run_label {
+        Some(ref label) => format!(" (run '{}')", label),
+        None => "".to_string(),
+    };
+
+    Some(SlackMessage {
+        text: format!("Factotum job '{}'{} {}", job_name, run, outcome),
+        attachments: attachments,
+    })
+}
@@ -0,0 +1,75 @@
+// Copyright (c) 2016-2021 Snowplow Analytics Ltd. All rights reserved.
+//
+// This program is licensed to you under the Apache License Version 2.0, and
+// you may not use this file except in compliance with the Apache License
+// Version 2.0.  You may obtain a copy of the Apache License Version 2.0 at
+// http://www.apache.org/licenses/LICENSE-2.0.
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the Apache License Version 2.0 is distributed on an "AS
+// IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or
+// implied.  See the Apache License Version 2.0 for the specific language
+// governing permissions and limitations there under.
+//
+
+use super::*;
+use factotum::executor::{ExecutionState, ExecutionUpdate, JobTransition, Transition};
+use factotum::executor::task_list::{Task, State};
+use factotum::factfile::Task as FactfileTask;
+
+fn make_update(state: ExecutionState, task_states: Vec<State>) -> ExecutionUpdate {
+    let snapshot = task_states.into_iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let mut task = Task::new(format!("task-{}", i), FactfileTask::default());
+            task.state = s;
+            task
+        })
+        .collect();
+    ExecutionUpdate::new(state,
+                         snapshot,
+                         Transition::Job(JobTransition::new(None, ExecutionState::Finished)))
+}
+
+#[test]
+fn only_finished_updates_are_posted() {
+    let update = make_update(ExecutionState::Running, vec![State::Running]);
+    assert_eq!(to_slack_message("job", &update), None);
+}
+
+#[test]
+fn job_color_matches_outcome() {
+    let success = make_update(ExecutionState::Finished,
+                              vec![State::Success, State::Success]);
+    let msg = to_slack_message("job", &success).unwrap();
+    assert_eq!(msg.text, "Factotum job 'job' succeeded");
+    assert_eq!(msg.attachments[0].color, COLOR_SUCCESS);
+    assert_eq!(msg.attachments.len(), 3);
+
+    let early = make_update(ExecutionState::Finished,
+                            vec![State::SuccessNoop, State::Skipped("noop".to_string())]);
+    let msg = to_slack_message("job", &early).unwrap();
+    assert_eq!(msg.text, "Factotum job 'job' finished early");
+    assert_eq!(msg.attachments[0].color, COLOR_EARLY_FINISH);
+    assert_eq!(msg.attachments[2].color, COLOR_NOT_RUN);
+
+    let failed = make_update(ExecutionState::Finished,
+                             vec![State::Success, State::Failed("bad".to_string())]);
+    let msg = to_slack_message("job", &failed).unwrap();
+    assert_eq!(msg.text, "Factotum job 'job' failed");
+    assert_eq!(msg.attachments[0].color, COLOR_FAILURE);
+    assert_eq!(msg.attachments[1].color, COLOR_SUCCESS);
+    assert_eq!(msg.attachments[2].color, COLOR_FAILURE);
+    assert_eq!(msg.attachments[2].text, "failed: bad");
+
+    assert!(msg.to_json_string().starts_with("{\"text\":\"Factotum job 'job' failed\",\
+                                              \"attachments\":[{\"color\":\"danger\""));
+}
+
+#[test]
+fn run_label_is_in_the_text() {
+    let update = make_update(ExecutionState::Finished, vec![State::Success])
+        .with_run_label(&Some("build-42".to_string()));
+    let msg = to_slack_message("job", &update).unwrap();
+    assert_eq!(msg.text, "Factotum job 'job' (run 'build-42') succeeded");
+}
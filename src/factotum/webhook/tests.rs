@@ -17,11 +17,11 @@ use std::sync::mpsc;
 use factotum::executor::{ExecutionState, TaskSnapshot, JobTransition, Transition, ExecutionUpdate};
 use std::time::Duration;
 
-fn mock_200_ok(_: &str, _: &str) -> Result<u32, (u32, String)> {
+fn mock_200_ok(_: &str, _: &str, _: &Option<String>) -> Result<u32, (u32, String)> {
     Ok(200)
 }
 
-fn mock_500_err(_: &str, _: &str) -> Result<u32, (u32, String)> {
+fn mock_500_err(_: &str, _: &str, _: &Option<String>) -> Result<u32, (u32, String)> {
     Err((500, "Internal Server Error".to_string()))
 }
 
@@ -170,3 +170,87 @@ fn bad_urls_post_rejects() {
         }
     }
 }
+
+#[test]
+fn strip_task_output_removes_stdout_stderr() {
+    use factotum::tests::make_task;
+    use factotum::executor::task_list::{Task, State};
+    use factotum::executor::execution_strategy::RunResult;
+
+    let mut task = Task::new("apple", make_task("apple", &vec![]));
+    task.state = State::Success;
+    task.run_result = Some(RunResult {
+        duration: Duration::from_secs(1),
+        task_execution_error: None,
+        stdout: Some("hello".to_string()),
+        stderr: Some("world".to_string()),
+        return_code: 0,
+        ..Default::default()
+    });
+
+    let update = ExecutionUpdate::new(ExecutionState::Running,
+                                      vec![task],
+                                      Transition::Task(vec![]));
+    let stripped = strip_task_output(&update);
+
+    let result = stripped.task_snapshot[0].run_result.clone().unwrap();
+    assert_eq!(result.stdout, None);
+    assert_eq!(result.stderr, None);
+    assert_eq!(result.return_code, 0);
+    assert_eq!(update.task_snapshot[0].run_result.clone().unwrap().stdout,
+               Some("hello".to_string()));
+}
+
+#[test]
+fn sign_payload_is_hmac_sha256() {
+    // RFC 4231, test case 2
+    assert_eq!(sign_payload("Jefe", "what do ya want for nothing?"),
+               "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+}
+
+fn mock_requires_signature(_: &str,
+                           data: &str,
+                           sig: &Option<String>)
+                           -> Result<u32, (u32, String)> {
+    if *sig == Some(sign_payload("s3cret", data)) {
+        Ok(200)
+    } else {
+        Err((401, "Unauthorized".to_string()))
+    }
+}
+
+#[test]
+fn secret_signs_every_update() {
+    let mut wh = Webhook::new("job_name", "hello", "https://goodplace.com", None, None);
+    wh.secret = Some("s3cret".to_string());
+    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+    let jh = wh.connect_webhook(rx, mock_requires_signature, zero_backoff);
+    tx.send(ExecutionUpdate::new(ExecutionState::Finished,
+                                 vec![],
+                                 Transition::Job(JobTransition::new(Some(ExecutionState::Running),
+                                                                    ExecutionState::Finished))))
+        .unwrap();
+    let result = jh.join().unwrap();
+    assert_eq!(result.success_count, 1);
+    assert_eq!(result.failed_count, 0);
+}
+
+#[test]
+fn job_events_bracket_the_updates() {
+    let mut wh = Webhook::new("job_name", "hello", "https://goodplace.com", None, None);
+    wh.job_events = true;
+    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+    let jh = wh.connect_webhook(rx, mock_200_ok, zero_backoff);
+
+    let sent_states = make_mock_run();
+    for state in sent_states.iter() {
+        tx.send(state.clone()).unwrap();
+    }
+
+    let result = jh.join().ok().unwrap();
+    assert_eq!(result.events_received, 4);
+    assert_eq!(result.success_count, 4);
+    assert_eq!(result.results.len(), 6);
+    assert_eq!(result.results[0], Ok(Attempt::new(Some(200), "OK", sent_states[0].clone())));
+    assert_eq!(result.results[5], Ok(Attempt::new(Some(200), "OK", sent_states[3].clone())));
+}
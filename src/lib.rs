@@ -29,6 +29,9 @@ extern crate hyper_native_tls;
 extern crate libc;
 extern crate ifaces;
 extern crate dns_lookup;
+extern crate rusqlite;
+extern crate tar;
+extern crate flate2;
 
 use std::fs;
 use factotum::executor::task_list::{Task, State};
@@ -842,6 +845,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         run_result: Some(RunResult {
             duration: Duration::from_secs(20),
@@ -849,6 +853,7 @@ fn test_get_task_result_line_str() {
             stdout: Some(String::from("hello world")),
             stderr: None,
             return_code: 0,
+            ..Default::default()
         }),
     };
 
@@ -881,6 +886,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         run_result: Some(RunResult {
             duration: Duration::from_secs(20),
@@ -888,6 +894,7 @@ fn test_get_task_result_line_str() {
             stdout: Some(String::from("hello world")),
             stderr: Some(String::from("There's errors")),
             return_code: 0,
+            ..Default::default()
         }),
     };
 
@@ -921,6 +928,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         state: State::Skipped("for some reason".to_string()),
         run_result: None,
@@ -945,6 +953,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         run_result: None,
     };
@@ -970,6 +979,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         run_result: Some(RunResult {
             duration: Duration::from_secs(20),
@@ -977,6 +987,7 @@ fn test_get_task_result_line_str() {
             stdout: Some(String::from("hello world")),
             stderr: Some(String::from("There's errors")),
             return_code: 0,
+            ..Default::default()
         }),
     };
 
@@ -1016,6 +1027,7 @@ fn test_get_task_results_str_summary() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_one = Task::<&FactfileTask> {
@@ -1030,6 +1042,7 @@ fn test_get_task_results_str_summary() {
             stdout: Some(String::from("hello world")),
             stderr: Some(String::from("Mistake")),
             return_code: 0,
+            ..Default::default()
         }),
     };
 
@@ -1044,6 +1057,7 @@ fn test_get_task_results_str_summary() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_two = Task::<&FactfileTask> {
@@ -1058,6 +1072,7 @@ fn test_get_task_results_str_summary() {
             stdout: Some(String::from("hello world")),
             stderr: Some(String::from("Mistake")),
             return_code: 0,
+            ..Default::default()
         }),
     };
 
@@ -1126,6 +1141,7 @@ fn test_start_task_cycles() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_b = Task {
@@ -1138,6 +1154,7 @@ fn test_start_task_cycles() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_c = Task {
@@ -1150,6 +1167,7 @@ fn test_start_task_cycles() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_d = Task {
@@ -1162,6 +1180,7 @@ fn test_start_task_cycles() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     factfile.add_task_obj(&task_a);
@@ -29,33 +29,57 @@ extern crate hyper_native_tls;
 extern crate libc;
 extern crate ifaces;
 extern crate dns_lookup;
+extern crate rusqlite;
+extern crate tar;
+extern crate flate2;
 
 use docopt::Docopt;
 use std::fs;
-use factotum::executor::task_list::{Task, State};
+use factotum::executor::task_list::{Task, State, StopReason};
 use factotum::factfile::Factfile;
 use factotum::factfile::Task as FactfileTask;
 use factotum::parser::OverrideResultMappings;
 use factotum::parser::TaskReturnCodeMapping;
+use factotum::parser::ValidationError;
 use factotum::executor::execution_strategy::*;
-use factotum::webhook::Webhook;
+use factotum::webhook::{Webhook, DEFAULT_MAX_STDOUTERR_SIZE};
 use factotum::executor::ExecutionUpdate;
+use factotum::executor::{ExecutionOptions, TaskApproval, TaskApprover};
+use factotum::executor::{get_duration_variance, mask_secrets, DEFAULT_DURATION_ALERT_PERCENT,
+                         DEFAULT_POLL_INTERVAL_MS};
+use factotum::runrecord;
+use factotum::history;
+use factotum::prometheus;
+use factotum::logbundle;
+use factotum::resultexport;
+use factotum::runlock;
+use factotum::chrometrace;
+use factotum::lint;
+use factotum::eventsocket;
+use factotum::eventsfile;
+use factotum::webhook::jobcontext::JobContext;
 use factotum::webhook;
 use colored::*;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use std::thread;
 use std::process::Command;
-use std::io::Write;
+use std::io::{self, Read, Write};
 use std::fs::OpenOptions;
 use std::env;
+use std::path::Path;
 use hyper::Url;
 use std::sync::mpsc;
 use std::net;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixDatagram;
 use rustc_serialize::json::{self, Json, ToJson};
 use std::collections::BTreeMap;
 #[cfg(test)]
 use std::fs::File;
 use std::collections::HashMap;
 use std::error::Error;
+use chrono::{Local, UTC};
+use uuid::Uuid;
 
 mod factotum;
 
@@ -63,8 +87,15 @@ const PROC_SUCCESS: i32 = 0;
 const PROC_PARSE_ERROR: i32 = 1;
 const PROC_EXEC_ERROR: i32 = 2;
 const PROC_OTHER_ERROR: i32 = 3;
+const PROC_START_TASK_NOT_FOUND: i32 = 4;
+const PROC_START_TASK_TRIGGERS_PRIOR_TASKS: i32 = 5;
 
 const CONSTRAINT_HOST: &'static str = "host";
+const CONSTRAINT_INTERFACE: &'static str = "interface";
+const CONSTRAINT_ADDRESSES: &'static str = "addresses";
+
+const WEBHOOK_SECRET_ENV_VAR: &'static str = "FACTOTUM_WEBHOOK_SECRET";
+const WEBHOOK_URL_ENV_VAR: &'static str = "FACTOTUM_WEBHOOK_URL";
 
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 const USAGE: &'static str =
@@ -72,9 +103,12 @@ const USAGE: &'static str =
 Factotum.
 
 Usage:
-  factotum run <factfile> [--start=<start_task>] [--env=<env>] [--dry-run] [--no-colour] [--webhook=<url>] [--tag=<tag>]... [--constraint=<constraint>]... [--max-stdouterr-size=<bytes>]
-  factotum validate <factfile> [--no-colour]
-  factotum dot <factfile> [--start=<start_task>] [--output=<output_file>] [--overwrite] [--no-colour]
+  factotum run <factfile> [--start=<start_task>] [--env=<env>] [--dry-run] [--no-colour] [--webhook=<url>] [--tag=<tag>]... [--constraint=<constraint>]... [--max-stdouterr-size=<bytes>] [--webhook-no-output] [--retry-failed] [--log-json] [--allow-executors=<executors>] [--output-on-failure] [--count] [--fail-on-skipped] [--fail-on-webhook-failure] [--state-file=<path>] [--webhook-format=<format>] [--shuffle] [--seed=<seed>] [--history-db=<path>] [--strategy=<strategy>] [--abort-file=<path>] [--prom-textfile=<path>] [--workdir=<path>] [--run-label=<label>] [--log-bundle=<path>] [--repeat-every=<seconds>] [--repeat-count=<count>] [--no-skip-downstream] [--webhook-secret=<secret>] [--preflight] [--summary-format=<format>] [--max-local-output-size=<bytes>] [--matrix=<matrix>] [--fail-fast] [--interactive] [--group-by=<tag>] [--event-socket=<path>] [--dump-effective=<path>] [--max-width] [--graph=<graph>] [--output=<output_file>] [--overwrite] [--fail-threshold=<percent>] [--dotenv=<file>] [--exit-from=<task>] [--max-concurrent-tag=<limit>]... [--webhook-job-events] [--duration-alert=<percent>] [--skip=<task>]... [--poll-interval-ms=<ms>] [--result-s3=<url>] [--secret=<name>]... [--tail=<task>] [--force] [--syslog] [--syslog-facility=<facility>] [--trace-out=<file>] [--verbose] [--quiet] [--run-meta=<file>] [--max-executions=<count>] [--override-executor=<task_executor>]... [--events-out=<file>] [--summary-order=<order>] [--webhook-include-factfile=<mode>]
+  factotum validate <factfile> [--no-colour] [--log-json] [--allow-executors=<executors>] [--format=<format>] [--dotenv=<file>]
+  factotum lint <factfile> [--no-colour] [--log-json] [--dotenv=<file>]
+  factotum dot <factfile> [--start=<start_task>] [--output=<output_file>] [--overwrite] [--no-colour] [--log-json] [--graph=<graph>]
+  factotum --list-failed=<statefile> [--no-colour] [--log-json]
+  factotum --history-query=<path> [--since=<date>] [--format=<format>] [--no-colour] [--log-json]
   factotum (-h | --help) [--no-colour]
   factotum (-v | --version) [--no-colour]
 
@@ -84,13 +118,82 @@ Options:
   --start=<start_task>                  Begin at specified task.
   --env=<env>                           Supply JSON to define mustache variables in Factfile.
   --dry-run                             Pretend to execute a Factfile, showing the commands that would be executed. Can be used with other options.
-  --output=<output_file>                File to print output to. Used with `dot`.
+  --output=<output_file>                File to print output to. Used with `dot` and `run --graph`. May contain {job}, {uuid} and {timestamp}.
   --overwrite                           Overwrite the output file if it exists.
   --no-colour                           Turn off ANSI terminal colours/formatting in output.
-  --webhook=<url>                       Post updates on job execution to the specified URL.
-  --tag=<tag>                           Add job metadata (tags).
-  --constraint=<constraint>             Checks for an external constraint that will prevent execution; allowed constraints (host).
+  --webhook=<url>                       Post updates on job execution to the specified URL. Can also be set with FACTOTUM_WEBHOOK_URL, which is never shown in the output or log.
+  --tag=<tag>                           Add job metadata (tags), as key,value or key=value. Commas in a key=value value are kept.
+  --constraint=<constraint>             Checks for an external constraint that will prevent execution; allowed constraints (host, interface, addresses). addresses decides which of the host's addresses must be local: any (default), first or all.
   --max-stdouterr-size=<bytes>          The maximum size of the individual stdout/err sent via the webhook functions for job updates.
+  --webhook-no-output                   Don't include task stdout/err in the updates sent via the webhook.
+  --retry-failed                        Re-run only the tasks that didn't succeed the last time this Factfile was run.
+  --log-json                            Write the log file (.factotum/factotum.log) as JSON lines rather than plain text.
+  --allow-executors=<executors>         Comma separated list of the only executors tasks may use (e.g. "shell,noop").
+  --output-on-failure                   Only print the stdout/err of tasks that failed.
+  --count                               Print the number of tasks that would be run, without running them.
+  --fail-on-skipped                     Exit with an error if any task was skipped, even if no task failed.
+  --fail-on-webhook-failure             Exit with an error if any webhook events couldn't be sent, rather than warning.
+  --state-file=<path>                   Keep the state of every task in this file, updated as the job runs. May contain {job}, {uuid} and {timestamp}.
+  --webhook-format=<format>             Format of the webhook updates: job-update (default) or slack (a colour-coded summary when the job ends).
+  --shuffle                             Start independent tasks in a random order, to catch undeclared dependencies.
+  --seed=<seed>                         Seed for --shuffle and retryJitterPercent, to reproduce a previous order or set of retry delays.
+  --history-db=<path>                   Append the outcome of every task to this SQLite database once the job ends.
+  --strategy=<strategy>                 How tasks are executed: os (default) runs them, simulate shows what would run (like --dry-run).
+  --abort-file=<path>                   Stop starting new tasks once this file exists; tasks already running are allowed to finish.
+  --prom-textfile=<path>                Write Prometheus metrics for the run to this file once the job ends (for the node_exporter textfile collector).
+  --workdir=<path>                      Run every task from this directory (a task's own cwd is relative to it), rather than the current directory.
+  --run-label=<label>                   A label for this run (e.g. a git SHA or CI build number), shown in the log, webhook updates and summary.
+  --log-bundle=<path>                   Once the job ends, write the output of every task and a manifest of task states to this .tar.gz. May contain {job}, {uuid} and {timestamp}.
+  --repeat-every=<seconds>              Run the job again every <seconds> until stopped; a run that's still going when the next one is due causes it to be skipped.
+  --repeat-count=<count>                With --repeat-every, stop after this many runs.
+  --no-skip-downstream                  Risky: still run the tasks that depend on a failed task, rather than skipping them. Only for jobs where every task is safe to run regardless.
+  --webhook-secret=<secret>             Sign webhook updates with an HMAC-SHA256 of the body in the X-Factotum-Signature header. Can also be set with FACTOTUM_WEBHOOK_SECRET.
+  --preflight                           Check that task working directories exist and output files can be created, without running anything.
+  --summary-format=<format>             Format of the summary printed once the job ends: text (default), tap (Test Anything Protocol) or csv (one row per task, without colours). With tap or csv, only the summary goes to stdout and every other message goes to stderr.
+  --max-local-output-size=<bytes>       The maximum size of the individual stdout/err kept for the summary, state file and run records; independent of --max-stdouterr-size.
+  --matrix=<matrix>                     JSON object of lists (e.g. '{"region":["us","eu"]}'); the job runs once per combination of values, each added to the --env values.
+  --fail-fast                           With --matrix, don't run the remaining combinations once one fails.
+  --interactive                         Ask before starting each task whether to run it, skip it (and the tasks that depend on it) or abort the job. Needs a terminal.
+  --group-by=<tag>                      Add a breakdown of the summary by the value of this task tag (e.g. category).
+  --event-socket=<path>                 Stream job updates to this Unix domain socket as JSON lines (the same updates the webhook sends).
+  --dump-effective=<path>               Write the factfile as it will run (with --env, defaults and argument files applied) to this file. With --dry-run, nothing is run.
+  --max-width                           Print the largest number of tasks that would run at the same time, without running them.
+  --graph=<graph>                       Once the job ends, print a graph of the run: dot-critical (Graphviz, with the slowest chain of tasks highlighted). With `dot`, the format of the graph: dot (Graphviz, the default) or edges (sorted "parent<TAB>child" lines).
+  --fail-threshold=<percent>            Only treat the job as failed if more than this percentage of its tasks failed (e.g. 5 for a best-effort fan-out).
+  --format=<format>                     Format of the validation report: text (default) or json (every problem, with the task and field it's about). For --history-query: table (default) or json.
+  --dotenv=<file>                       Load KEY=value lines from this file as variables; --env values win over them, and they win over the OS environment (for ${VAR} references and tasks).
+  --exit-from=<task>                    Exit with the return code of this task, if it ran, rather than Factotum's own exit codes.
+  --max-concurrent-tag=<limit>          Run at most this many tasks with a tag at once, e.g. resource=2 (any value) or resource:db=2 (one value). Can be given more than once.
+  --webhook-job-events                  Also post a job_started event before the first webhook update and a job_finished event (with the task counts and outcome) after the last.
+  --duration-alert=<percent>            Warn when a task with an expectedDurationSeconds runs this much longer or shorter than expected (default 50).
+  --skip=<task>                         Skip this task (and the tasks that depend on it) without editing the factfile. Can be given more than once.
+  --poll-interval-ms=<ms>               How often to check for the --abort-file (default 500). Nothing else is polled: tasks run a dependency level at a time, and the next level starts as soon as the last task in the current one finishes.
+  --result-s3=<url>                     Once the job ends, PUT a JSON summary of the run to this pre-signed S3 (or S3-compatible) URL. A failed upload is only a warning.
+  --secret=<name>                       Mark an --env value or environment variable as secret, so its value is shown as *** in the --dump-effective file and --verbose output. Can be given more than once.
+  --tail=<task>                         Show this task's stdout/err on the console as it runs. Its output isn't captured, so it's left out of the summary and webhook updates.
+  --force                               Run even if another factotum is already running this factfile. Without it, a second run exits straight away.
+  --syslog                              Also send the log to the local syslog (/dev/log), as well as the log file.
+  --syslog-facility=<facility>          The syslog facility to log with: user (the default), daemon or local0 to local7.
+  --trace-out=<file>                    Once the job ends, write each task's run as a Chrome trace (for chrome://tracing or Perfetto), with overlapping tasks on separate tracks.
+  --verbose                             Print each task's command and environment as it starts, with --secret values shown as ***.
+  --quiet                               Print nothing on stdout, not even the summary - only the stderr of tasks that fail. The exit code, webhooks and log file are unaffected.
+  --run-meta=<file>                     Write a small JSON file about this invocation (run id, host, start and end time, arguments with secrets masked, factfile hash and exit status), even if the run fails. May contain {job}, {uuid} and {timestamp}, so each --matrix combination or --repeat-every run keeps its own.
+  --max-executions=<count>              Stop the job once this many task runs (retries included) have been started; tasks that would need more are skipped and factotum exits with 3.
+  --override-executor=<task_executor>   Run a task with another executor, given as task=executor (shell or ssh). shell runs an ssh task locally; ssh needs the task to have hosts. Applied in the order given.
+  --events-out=<file>                   Also write every update the webhook would get to this file, as newline-delimited JSON (written as the job runs, so it can be replayed to a test receiver).
+  --summary-order=<order>               Order of the tasks in the text summary: dag (default), failures-first or failures-last.
+  --webhook-include-factfile=<mode>     What the job_started event carries of the factfile: full (base64), hash (sha256, the default) or none. Needs --webhook-job-events.
+  --list-failed=<statefile>             Print the names of the tasks that failed according to a state file, one per line.
+  --history-query=<path>                Print the task runs recorded in a --history-db database.
+  --since=<date>                        Only show the task runs recorded on or after this date (YYYY-MM-DD, midnight UTC) or RFC 3339 timestamp.
+
+Exit codes:
+  0                                     Success.
+  1                                     The factfile isn't valid.
+  2                                     A task failed.
+  3                                     Any other error.
+  4                                     The --start task isn't in the factfile.
+  5                                     The job can't be started from the --start task without triggering prior tasks.
 ";
 
 #[derive(Debug, RustcDecodable)]
@@ -105,10 +208,72 @@ struct Args {
     flag_tag: Option<Vec<String>>,
     flag_constraint: Option<Vec<String>>,
     flag_max_stdouterr_size: Option<usize>,
+    flag_webhook_no_output: bool,
+    flag_retry_failed: bool,
+    flag_log_json: bool,
+    flag_allow_executors: Option<String>,
+    flag_output_on_failure: bool,
+    flag_count: bool,
+    flag_fail_on_skipped: bool,
+    flag_fail_on_webhook_failure: bool,
+    flag_state_file: Option<String>,
+    flag_webhook_format: Option<String>,
+    flag_shuffle: bool,
+    flag_seed: Option<usize>,
+    flag_history_db: Option<String>,
+    flag_strategy: Option<String>,
+    flag_abort_file: Option<String>,
+    flag_prom_textfile: Option<String>,
+    flag_list_failed: Option<String>,
+    flag_workdir: Option<String>,
+    flag_run_label: Option<String>,
+    flag_log_bundle: Option<String>,
+    flag_repeat_every: Option<u64>,
+    flag_repeat_count: Option<u32>,
+    flag_no_skip_downstream: bool,
+    flag_webhook_secret: Option<String>,
+    flag_preflight: bool,
+    flag_summary_format: Option<String>,
+    flag_max_local_output_size: Option<usize>,
+    flag_matrix: Option<String>,
+    flag_fail_fast: bool,
+    flag_interactive: bool,
+    flag_group_by: Option<String>,
+    flag_event_socket: Option<String>,
+    flag_dump_effective: Option<String>,
+    flag_max_width: bool,
+    flag_graph: Option<String>,
+    flag_fail_threshold: Option<f64>,
+    flag_format: Option<String>,
+    flag_dotenv: Option<String>,
+    flag_exit_from: Option<String>,
+    flag_max_concurrent_tag: Option<Vec<String>>,
+    flag_webhook_job_events: bool,
+    flag_duration_alert: Option<f64>,
+    flag_skip: Option<Vec<String>>,
+    flag_poll_interval_ms: Option<u64>,
+    flag_result_s3: Option<String>,
+    flag_secret: Option<Vec<String>>,
+    flag_tail: Option<String>,
+    flag_force: bool,
+    flag_syslog: bool,
+    flag_syslog_facility: Option<String>,
+    flag_trace_out: Option<String>,
+    flag_verbose: bool,
+    flag_quiet: bool,
+    flag_run_meta: Option<String>,
+    flag_max_executions: Option<usize>,
+    flag_override_executor: Option<Vec<String>>,
+    flag_events_out: Option<String>,
+    flag_summary_order: Option<String>,
+    flag_webhook_include_factfile: Option<String>,
+    flag_history_query: Option<String>,
+    flag_since: Option<String>,
     arg_factfile: String,
     flag_version: bool,
     cmd_run: bool,
     cmd_validate: bool,
+    cmd_lint: bool,
     cmd_dot: bool,
 }
 
@@ -154,7 +319,9 @@ fn get_duration_as_string(d: &Duration) -> String {
     }
 }
 
-fn get_task_result_line_str(task_result: &Task<&FactfileTask>) -> (String, Option<String>) {
+fn get_task_result_line_str(task_result: &Task<&FactfileTask>,
+                            output_on_failure: bool)
+                            -> (String, Option<String>) {
 
     let state = task_result.state.clone();
     let start_time = match task_result.run_started {
@@ -165,26 +332,43 @@ fn get_task_result_line_str(task_result: &Task<&FactfileTask>) -> (String, Optio
         task_result.run_result {
         // we know tasks with run details were attempted
 
-        let opener = format!("Task '{}' was started at {}\n",
-                             task_result.name.cyan(),
-                             start_time.unwrap());
+        let opener = match res.host {
+            Some(ref host) => {
+                format!("Task '{}' was started at {} on '{}'\n",
+                        task_result.name.cyan(),
+                        start_time.unwrap(),
+                        host)
+            }
+            None => {
+                format!("Task '{}' was started at {}\n",
+                        task_result.name.cyan(),
+                        start_time.unwrap())
+            }
+        };
+
+        let show_output = match state {
+            State::Failed(_) => true,
+            _ => !output_on_failure,
+        };
 
         let output = match res.stdout {
-            Some(ref o) => {
-                Some(format!("Task '{}' stdout:\n{}\n",
+            Some(ref o) if show_output => {
+                Some(format!("Task '{}' stdout ({} bytes):\n{}\n",
                              task_result.name.cyan(),
+                             res.stdout_bytes(),
                              o.trim_right().bold()))
-            } 
-            None => None,
+            }
+            _ => None,
         };
 
         let errors = match res.stderr {
-            Some(ref e) => {
-                Some(format!("Task '{}' stderr:\n{}\n",
+            Some(ref e) if show_output => {
+                Some(format!("Task '{}' stderr ({} bytes):\n{}\n",
                              task_result.name.cyan(),
+                             res.stderr_bytes(),
                              e.trim_right().red()))
             }
-            None => None,
+            _ => None,
         };
 
         let summary = match (&res.task_execution_error, state) {
@@ -221,10 +405,10 @@ fn get_task_result_line_str(task_result: &Task<&FactfileTask>) -> (String, Optio
         // tasks without run details may have been unable to start (some internal error)
         // or skipped because a prior task errored or NOOPed
 
-        let reason_for_not_running = if let State::Failed(_) = task_result.state {
-            "Factotum could not start the task".red().to_string()
-        } else {
-            "skipped".to_string()
+        let reason_for_not_running = match task_result.state {
+            State::Failed(_) => "Factotum could not start the task".red().to_string(),
+            State::Success => "already completed in a previous run".to_string(),
+            _ => "skipped".to_string(),
         };
 
         let opener = format!("Task '{}': {}!\n",
@@ -245,15 +429,223 @@ fn get_task_result_line_str(task_result: &Task<&FactfileTask>) -> (String, Optio
     return (result, stderr);
 }
 
-fn get_task_results_str(task_results: &Vec<&Task<&FactfileTask>>) -> (String, String) {
+fn get_tap_summary(task_results: &Vec<&Task<&FactfileTask>>) -> String {
+    let mut tap = format!("TAP version 13\n1..{}\n", task_results.len());
+
+    for (idx, task) in task_results.iter().enumerate() {
+        // '#' starts a directive in TAP so it can't appear unescaped in a description
+        let description = format!("{} - {}", idx + 1, task.name.replace("#", "\\#"));
+
+        match (&task.state, &task.run_result) {
+            (&State::Failed(ref reason), run_result) => {
+                tap.push_str(&format!("not ok {}\n", description));
+                tap.push_str("  ---\n");
+                tap.push_str(&format!("  message: {}\n", reason.to_json()));
+                if let Some(ref res) = *run_result {
+                    if let Some(ref err) = res.task_execution_error {
+                        tap.push_str(&format!("  error: {}\n", err.to_json()));
+                    }
+                    tap.push_str(&format!("  returnCode: {}\n", res.return_code));
+                    tap.push_str(&format!("  stdoutBytes: {}\n", res.stdout_bytes()));
+                    tap.push_str(&format!("  stderrBytes: {}\n", res.stderr_bytes()));
+                    tap.push_str(&format!("  duration: {}\n",
+                                          get_duration_as_string(&res.duration).to_json()));
+                }
+                tap.push_str("  ...\n");
+            }
+            (_, &Some(ref res)) => {
+                tap.push_str(&format!("ok {} # {}\n",
+                                      description,
+                                      get_duration_as_string(&res.duration)));
+            }
+            (&State::Success, &None) => {
+                tap.push_str(&format!("ok {} # SKIP already completed in a previous run\n",
+                                      description));
+            }
+            (&State::Skipped(ref reason), &None) => {
+                tap.push_str(&format!("ok {} # SKIP {}\n", description, reason));
+            }
+            (_, &None) => {
+                tap.push_str(&format!("ok {} # SKIP not run\n", description));
+            }
+        }
+    }
+
+    tap
+}
+
+fn get_csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn get_csv_summary(task_results: &Vec<&Task<&FactfileTask>>) -> String {
+    let mut csv = "task,state,return_code,start_time,end_time,duration_seconds\n".to_string();
+
+    for task in task_results.iter() {
+        let start = task.run_started.as_ref().map(|t| t.to_rfc3339()).unwrap_or(String::new());
+        let (return_code, end, duration) = match (&task.run_result, &task.run_started) {
+            (&Some(ref res), &Some(ref started)) => {
+                let end = chrono::Duration::from_std(res.duration)
+                    .map(|d| (started.clone() + d).to_rfc3339())
+                    .unwrap_or(String::new());
+                let secs = res.duration.as_secs() as f64 +
+                           res.duration.subsec_nanos() as f64 / 1e9;
+                (res.return_code.to_string(), end, format!("{:.3}", secs))
+            }
+            _ => (String::new(), String::new(), String::new()),
+        };
+        csv.push_str(&format!("{},{},{},{},{},{}\n",
+                              get_csv_field(&task.name),
+                              runrecord::state_name(&task.state),
+                              return_code,
+                              start,
+                              end,
+                              duration));
+    }
+
+    csv
+}
+
+// this only reports on drift - it never changes whether the job passed
+fn get_duration_warnings(task_results: &Vec<&Task<&FactfileTask>>,
+                         alert_percent: f64)
+                         -> Vec<String> {
+    task_results.iter()
+        .filter_map(|task| match (task.task_spec.expected_duration_seconds, &task.run_result) {
+            (Some(expected), &Some(ref result)) => {
+                let variance = get_duration_variance(expected, &result.duration);
+                if variance.abs() > alert_percent {
+                    Some(format!("'{}' took {}, {:.0}% {} than its expected {}",
+                                 task.name,
+                                 get_duration_as_string(&result.duration),
+                                 variance.abs(),
+                                 if variance > 0.0 { "longer" } else { "shorter" },
+                                 get_duration_as_string(&Duration::from_secs(expected))))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// tasks without the tag are counted together
+const UNTAGGED_CATEGORY: &'static str = "(untagged)";
+
+fn get_category_summary(task_results: &Vec<&Task<&FactfileTask>>, tag: &str) -> Vec<String> {
+    let mut categories: BTreeMap<String, (u32, u32, u32, Duration)> = BTreeMap::new();
+
+    for task in task_results.iter() {
+        let category = task.task_spec
+            .tags
+            .get(tag)
+            .cloned()
+            .unwrap_or(UNTAGGED_CATEGORY.to_string());
+        let counts = categories.entry(category).or_insert((0, 0, 0, Duration::new(0, 0)));
+        match task.state {
+            State::Success | State::SuccessNoop => counts.0 += 1,
+            State::Failed(_) => counts.1 += 1,
+            _ => counts.2 += 1,
+        }
+        if let Some(ref run_result) = task.run_result {
+            counts.3 = counts.3 + run_result.duration;
+        }
+    }
+
+    categories.iter()
+        .map(|(category, &(succeeded, failed, not_run, ref duration))| {
+            format!("{}: {} succeeded, {} failed, {} not run in {}",
+                    category,
+                    succeeded,
+                    failed,
+                    not_run,
+                    get_duration_as_string(duration))
+        })
+        .collect()
+}
+
+fn print_task_results(task_results: &Vec<&Task<&FactfileTask>>,
+                      options: &RunOptions,
+                      run_start: &chrono::DateTime<UTC>) {
+    if options.quiet {
+        for stderr in get_failed_task_stderr(task_results) {
+            print_err!("{}", stderr.trim_right());
+        }
+        return;
+    }
+
+    match options.summary_format {
+        SummaryFormat::Csv => print!("{}", get_csv_summary(task_results)),
+        SummaryFormat::Tap => {
+            print!("{}", get_tap_summary(task_results));
+            if let Some(ref tag) = options.group_by {
+                for line in get_category_summary(task_results, tag) {
+                    println!("# {}", line);
+                }
+            }
+            for warning in get_duration_warnings(task_results, options.duration_alert_percent) {
+                println!("# Warning: {}", warning);
+            }
+        }
+        SummaryFormat::Text => {
+            let (stdout_summary, stderr_summary) =
+                get_task_results_str(&get_ordered_task_results(task_results,
+                                                               options.summary_order),
+                                     options.output_on_failure,
+                                     Some((*run_start, UTC::now())));
+            print!("{}", stdout_summary);
+            if let Some(ref tag) = options.group_by {
+                println!("Tasks by '{}':", tag);
+                for line in get_category_summary(task_results, tag) {
+                    println!("  {}", line);
+                }
+            }
+            for warning in get_duration_warnings(task_results, options.duration_alert_percent) {
+                println!("{}", format!("Warning: {}", warning).yellow());
+            }
+            if !stderr_summary.trim_right().is_empty() {
+                print_err!("{}", stderr_summary.trim_right());
+            }
+        }
+    }
+}
+
+fn get_failed_task_stderr(task_results: &Vec<&Task<&FactfileTask>>) -> Vec<String> {
+    task_results.iter()
+        .filter(|t| match t.state {
+            State::Failed(_) => true,
+            _ => false,
+        })
+        .filter_map(|t| get_task_result_line_str(t, false).1)
+        .collect()
+}
+
+fn get_task_results_str(task_results: &Vec<&Task<&FactfileTask>>,
+                        output_on_failure: bool,
+                        job_times: Option<(chrono::DateTime<UTC>, chrono::DateTime<UTC>)>)
+                        -> (String, String) {
     let mut stderr = String::new();
     let mut stdout = String::new();
 
+    // the summed task time below undercounts parallel tasks, and leaves out time between tasks
+    if let Some((started, finished)) = job_times {
+        let elapsed = (finished - started).to_std().unwrap_or(Duration::new(0, 0));
+        stdout.push_str(&format!("Job started at {} and finished at {}, taking {}\n",
+                                 started,
+                                 finished,
+                                 get_duration_as_string(&elapsed).bold()));
+    }
+
     let mut total_run_time = Duration::new(0, 0);
     let mut executed = 0;
 
     for task in task_results.iter() {
-        let (task_stdout, task_stderr) = get_task_result_line_str(task);
+        let (task_stdout, task_stderr) = get_task_result_line_str(task, output_on_failure);
         stdout.push_str(&task_stdout);
 
         if let Some(task_stderr_str) = task_stderr {
@@ -275,7 +667,47 @@ fn get_task_results_str(task_results: &Vec<&Task<&FactfileTask>>) -> (String, St
     (stdout, stderr)
 }
 
-fn validate_start_task(job: &Factfile, start_task: &str) -> Result<(), &'static str> {
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum StartTaskError {
+    TaskNotFound,
+    TriggersPriorTasks,
+}
+
+impl StartTaskError {
+    // stable identifiers for tooling, the messages are for people
+    fn code(&self) -> &'static str {
+        match *self {
+            StartTaskError::TaskNotFound => "TASK_NOT_FOUND",
+            StartTaskError::TriggersPriorTasks => "TRIGGERS_PRIOR_TASKS",
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match *self {
+            StartTaskError::TaskNotFound => "the task specified could not be found",
+            StartTaskError::TriggersPriorTasks => {
+                "the job cannot be started here without triggering prior tasks"
+            }
+        }
+    }
+
+    fn exit_code(&self) -> i32 {
+        match *self {
+            StartTaskError::TaskNotFound => PROC_START_TASK_NOT_FOUND,
+            StartTaskError::TriggersPriorTasks => PROC_START_TASK_TRIGGERS_PRIOR_TASKS,
+        }
+    }
+
+    fn from_exit_code(code: i32) -> Option<StartTaskError> {
+        match code {
+            PROC_START_TASK_NOT_FOUND => Some(StartTaskError::TaskNotFound),
+            PROC_START_TASK_TRIGGERS_PRIOR_TASKS => Some(StartTaskError::TriggersPriorTasks),
+            _ => None,
+        }
+    }
+}
+
+fn validate_start_task(job: &Factfile, start_task: &str) -> Result<(), StartTaskError> {
     // A
     // / \
     // B   C
@@ -299,14 +731,66 @@ fn validate_start_task(job: &Factfile, start_task: &str) -> Result<(), &'static
             if is_good {
                 Ok(())
             } else {
-                Err("the job cannot be started here without triggering prior tasks")
+                Err(StartTaskError::TriggersPriorTasks)
             }
         }
-        Err(msg) => Err(msg),
+        Err(_) => Err(StartTaskError::TaskNotFound),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum GraphFormat {
+    Dot,
+    Edges,
+}
+
+fn get_graph_format(graph: &Option<String>) -> Result<GraphFormat, String> {
+    match *graph {
+        Some(ref name) if name == "dot" => Ok(GraphFormat::Dot),
+        Some(ref name) if name == "edges" => Ok(GraphFormat::Edges),
+        Some(ref name) => Err(format!("unknown graph '{}' (supported graphs: dot, edges)", name)),
+        None => Ok(GraphFormat::Dot),
+    }
+}
+
+fn lint_factfile(factfile: &str, env: Option<Json>) -> i32 {
+    let ff = match factotum::parser::parse(factfile, env, OverrideResultMappings::None) {
+        Ok(ff) => ff,
+        Err(msg) => {
+            println!("{}", msg.red());
+            return PROC_PARSE_ERROR;
+        }
+    };
+
+    let findings = lint::lint(&ff);
+    if findings.is_empty() {
+        println!("{}", format!("'{}' has no lint findings", factfile).green());
+        return PROC_SUCCESS;
+    }
+
+    println!("'{}' has {} lint finding(s):", factfile, findings.len());
+    for finding in findings.iter() {
+        let severity = format!("{}:", finding.severity);
+        let severity = match finding.severity {
+            lint::Severity::Error => severity.red(),
+            lint::Severity::Warning => severity.yellow(),
+            lint::Severity::Info => severity.cyan(),
+        };
+        println!("  {} {}", severity, finding.message);
+    }
+
+    // only errors fail the lint; warnings and info are for a person to judge
+    if findings.iter().any(|f| f.severity == lint::Severity::Error) {
+        PROC_PARSE_ERROR
+    } else {
+        PROC_SUCCESS
     }
 }
 
-fn dot(factfile: &str, start_from: Option<String>) -> Result<String, String> {
+fn dot(factfile: &str,
+       start_from: Option<String>,
+       format: GraphFormat)
+       -> Result<(String, String), String> {
     let ff = try!(factotum::parser::parse(factfile, None, OverrideResultMappings::None));
     if let Some(ref start) = start_from {
         match ff.can_job_run_from_task(&start) {
@@ -319,92 +803,657 @@ fn dot(factfile: &str, start_from: Option<String>) -> Result<String, String> {
         }
     }
 
-    Ok(ff.as_dotfile(start_from))
+    let graph = match format {
+        GraphFormat::Dot => ff.as_dotfile(start_from),
+        GraphFormat::Edges => ff.as_edge_list(start_from),
+    };
+    Ok((ff.name.clone(), graph))
+}
+
+fn get_output_path(template: &str,
+                   job_name: &str,
+                   run_id: &str,
+                   time: &chrono::DateTime<UTC>)
+                   -> String {
+    // job names are free text, so keep only the characters that are safe in a filename
+    let safe_job_name = job_name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+            c
+        } else {
+            '_'
+        })
+        .collect::<String>();
+
+    template.replace("{job}", &safe_job_name)
+        .replace("{uuid}", run_id)
+        .replace("{timestamp}", &time.format("%Y%m%dT%H%M%SZ").to_string())
+}
+
+fn get_exit_status(code: i32) -> &'static str {
+    match code {
+        PROC_SUCCESS => "success",
+        PROC_PARSE_ERROR => "parse_error",
+        PROC_EXEC_ERROR => "exec_error",
+        PROC_START_TASK_NOT_FOUND => "start_task_not_found",
+        PROC_START_TASK_TRIGGERS_PRIOR_TASKS => "start_task_triggers_prior_tasks",
+        _ => "other_error",
+    }
+}
+
+fn get_run_meta(run_id: &str,
+                factfile: &str,
+                factfile_sha256: &Option<String>,
+                cli_args: &Vec<String>,
+                secrets: &Vec<String>,
+                run_start: &chrono::DateTime<UTC>,
+                run_end: &chrono::DateTime<UTC>,
+                exit_code: i32)
+                -> Json {
+    let mut meta = BTreeMap::new();
+    meta.insert("runId".to_string(), run_id.to_json());
+    meta.insert("host".to_string(), gethostname_safe().ok().to_json());
+    meta.insert("startTime".to_string(), run_start.to_rfc3339().to_json());
+    meta.insert("endTime".to_string(), run_end.to_rfc3339().to_json());
+    meta.insert("args".to_string(),
+                cli_args.iter()
+                    .map(|a| mask_secrets(a, secrets))
+                    .collect::<Vec<String>>()
+                    .to_json());
+    meta.insert("factfile".to_string(), factfile.to_json());
+    meta.insert("factfileSha256".to_string(), factfile_sha256.to_json());
+    meta.insert("exitCode".to_string(), exit_code.to_json());
+    meta.insert("status".to_string(), get_exit_status(exit_code).to_json());
+    meta.insert("errorCode".to_string(),
+                StartTaskError::from_exit_code(exit_code).map(|e| e.code().to_string()).to_json());
+    Json::Object(meta)
+}
+
+// a secret can come from --env (or --matrix), or from the environment via ${NAME}
+fn get_secret_values(names: &Vec<String>, env_json: &Option<Json>) -> Vec<String> {
+    names.iter()
+        .filter_map(|name| {
+            match env_json.as_ref().and_then(|e| e.find(name)) {
+                Some(&Json::String(ref value)) => Some(value.clone()),
+                Some(value) => Some(value.to_string()),
+                None => env::var(name).ok(),
+            }
+        })
+        .filter(|value| !value.is_empty())
+        .collect()
 }
 
-fn validate(factfile: &str, env: Option<Json>) -> Result<String, String> {
+fn validate(factfile: &str,
+            env: Option<Json>,
+            allowed_executors: Option<Vec<String>>)
+            -> Result<String, String> {
     match factotum::parser::parse(factfile, env, OverrideResultMappings::None) {
-        Ok(_) => Ok(format!("'{}' is a valid Factfile!", factfile).green().to_string()),
+        Ok(ff) => {
+            if let Some(ref allowed) = allowed_executors {
+                if let Err(msg) = factotum::parser::validate_executors(&ff, allowed) {
+                    return Err(format!("'{}' is not a valid factotum factfile: {}",
+                                       factfile,
+                                       msg)
+                        .red()
+                        .to_string());
+                }
+            }
+            Ok(format!("'{}' is a valid Factfile!", factfile).green().to_string())
+        }
         Err(msg) => Err(msg.red().to_string()),
     }
 }
 
-fn parse_file_and_simulate(factfile: &str, env: Option<Json>, start_from: Option<String>) -> i32 {
+fn get_validation_report(factfile: &str,
+                         env: Option<Json>,
+                         allowed_executors: Option<Vec<String>>)
+                         -> (bool, String) {
+    let mut errors = factotum::parser::get_validation_errors(factfile, env.clone());
+
+    // as with validate, executors are only checked once the factfile is otherwise valid
+    if let Some(allowed) = allowed_executors {
+        if errors.is_empty() {
+            if let Ok(ff) = factotum::parser::parse(factfile, env, OverrideResultMappings::None) {
+                for task_group in ff.get_tasks_in_order() {
+                    for task in task_group {
+                        if !allowed.contains(&task.executor) {
+                            errors.push(ValidationError {
+                                path: None,
+                                task: Some(task.name.clone()),
+                                field: Some("executor".to_string()),
+                                message: format!("the executor '{}' isn't allowed (allowed \
+                                                  executors: {})",
+                                                 task.executor,
+                                                 allowed.join(", ")),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut report = BTreeMap::new();
+    report.insert("factfile".to_string(), factfile.to_json());
+    report.insert("valid".to_string(), errors.is_empty().to_json());
+    report.insert("errors".to_string(),
+                  Json::Array(errors.iter().map(|e| e.to_json()).collect()));
+    (errors.is_empty(), Json::Object(report).pretty().to_string())
+}
+
+fn get_executor_list(executors: &str) -> Vec<String> {
+    executors.split(",")
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+enum StrategyChoice {
+    Os,
+    Simulate,
+}
+
+fn get_strategy_choice(strategy: &Option<String>, dry_run: bool) -> Result<StrategyChoice, String> {
+    let choice = match *strategy {
+        Some(ref name) if name == "os" => StrategyChoice::Os,
+        Some(ref name) if name == "simulate" => StrategyChoice::Simulate,
+        Some(ref name) => {
+            return Err(format!("unknown strategy '{}' (supported strategies: os, simulate)",
+                               name))
+        }
+        None if dry_run => StrategyChoice::Simulate,
+        None => StrategyChoice::Os,
+    };
+
+    if dry_run && choice != StrategyChoice::Simulate {
+        Err("--dry-run can only be used with the simulate strategy".to_string())
+    } else {
+        Ok(choice)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum SummaryFormat {
+    Text,
+    Tap,
+    Csv,
+}
+
+impl Default for SummaryFormat {
+    fn default() -> Self {
+        SummaryFormat::Text
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum SummaryOrder {
+    Dag,
+    FailuresFirst,
+    FailuresLast,
+}
+
+impl Default for SummaryOrder {
+    fn default() -> Self {
+        SummaryOrder::Dag
+    }
+}
+
+fn get_summary_order(order: &Option<String>) -> Result<SummaryOrder, String> {
+    match *order {
+        Some(ref name) if name == "dag" => Ok(SummaryOrder::Dag),
+        Some(ref name) if name == "failures-first" => Ok(SummaryOrder::FailuresFirst),
+        Some(ref name) if name == "failures-last" => Ok(SummaryOrder::FailuresLast),
+        Some(ref name) => {
+            Err(format!("unknown summary order '{}' (supported orders: dag, failures-first, \
+                         failures-last)",
+                        name))
+        }
+        None => Ok(SummaryOrder::Dag),
+    }
+}
+
+// the sort is stable, so tasks keep their DAG order within the failed and not failed groups
+fn get_ordered_task_results<'a, 'b>(task_results: &Vec<&'a Task<&'b FactfileTask>>,
+                                    order: SummaryOrder)
+                                    -> Vec<&'a Task<&'b FactfileTask>> {
+    let mut ordered = task_results.clone();
+    let is_failed = |t: &&Task<&FactfileTask>| match t.state {
+        State::Failed(_) => true,
+        _ => false,
+    };
+    match order {
+        SummaryOrder::Dag => {}
+        SummaryOrder::FailuresFirst => ordered.sort_by_key(|t| !is_failed(t)),
+        SummaryOrder::FailuresLast => ordered.sort_by_key(|t| is_failed(t)),
+    }
+    ordered
+}
+
+fn wants_critical_path_graph(graph: &Option<String>) -> Result<bool, String> {
+    match *graph {
+        Some(ref name) if name == "dot-critical" => Ok(true),
+        Some(ref name) => Err(format!("unknown graph '{}' (supported graphs: dot-critical)", name)),
+        None => Ok(false),
+    }
+}
+
+// a csv or tap summary is read by other programs, so nothing else goes to stdout with it
+fn print_status(summary_format: SummaryFormat, line: &str) {
+    if summary_format == SummaryFormat::Text {
+        println!("{}", line);
+    } else {
+        print_err!("{}", line);
+    }
+}
+
+fn get_summary_format(format: &Option<String>) -> Result<SummaryFormat, String> {
+    match *format {
+        Some(ref name) if name == "text" => Ok(SummaryFormat::Text),
+        Some(ref name) if name == "tap" => Ok(SummaryFormat::Tap),
+        Some(ref name) if name == "csv" => Ok(SummaryFormat::Csv),
+        Some(ref name) => {
+            Err(format!("unknown summary format '{}' (supported formats: text, tap, csv)",
+                        name))
+        }
+        None => Ok(SummaryFormat::Text),
+    }
+}
+
+#[derive(Default, Clone)]
+struct RunOptions {
+    start_from: Option<String>,
+    webhook_url: Option<String>,
+    webhook_url_from_env: bool,
+    job_tags: Option<HashMap<String, String>>,
+    max_stdouterr_size: Option<usize>,
+    webhook_no_output: bool,
+    retry_failed: bool,
+    allowed_executors: Option<Vec<String>>,
+    output_on_failure: bool,
+    count: bool,
+    fail_on_skipped: bool,
+    fail_on_webhook_failure: bool,
+    state_file: Option<String>,
+    webhook_format: webhook::WebhookFormat,
+    shuffle: bool,
+    seed: Option<usize>,
+    history_db: Option<String>,
+    abort_file: Option<String>,
+    prom_textfile: Option<String>,
+    workdir: Option<String>,
+    run_label: Option<String>,
+    log_bundle: Option<String>,
+    no_skip_downstream: bool,
+    webhook_secret: Option<String>,
+    preflight: bool,
+    summary_format: SummaryFormat,
+    max_local_output_size: Option<usize>,
+    interactive: bool,
+    group_by: Option<String>,
+    event_socket: Option<String>,
+    dump_effective: Option<String>,
+    max_width: bool,
+    critical_path_graph: bool,
+    output: Option<String>,
+    overwrite: bool,
+    fail_threshold: Option<f64>,
+    exit_from: Option<String>,
+    tag_limits: HashMap<String, usize>,
+    webhook_job_events: bool,
+    duration_alert_percent: f64,
+    skip_tasks: Vec<String>,
+    poll_interval_ms: u64,
+    result_s3: Option<String>,
+    secrets: Vec<String>,
+    tail_task: Option<String>,
+    force: bool,
+    trace_out: Option<String>,
+    verbose: bool,
+    quiet: bool,
+    run_meta: Option<String>,
+    run_id: Option<String>,
+    max_executions: Option<usize>,
+    executor_overrides: Vec<(String, String)>,
+    events_out: Option<String>,
+    summary_order: SummaryOrder,
+    webhook_include_factfile: webhook::FactfileInclusion,
+}
+
+fn parse_file_and_simulate(factfile: &str,
+                           contents: &str,
+                           env: Option<Json>,
+                           options: RunOptions)
+                           -> i32 {
     parse_file_and_execute_with_strategy(factfile,
+                                         contents,
                                          env,
-                                         start_from,
                                          factotum::executor::execution_strategy::execute_simulation,
                                          OverrideResultMappings::All(TaskReturnCodeMapping {
                                              continue_job: vec![0],
                                              terminate_early: vec![],
                                          }),
-                                         None,
-                                         None,
-                                         None)
+                                         options,
+                                         false)
 }
 
 fn parse_file_and_execute(factfile: &str,
+                          contents: &str,
                           env: Option<Json>,
-                          start_from: Option<String>,
-                          webhook_url: Option<String>,
-                          job_tags: Option<HashMap<String, String>>,
-                          max_stdouterr_size: Option<usize>)
+                          options: RunOptions)
                           -> i32 {
     parse_file_and_execute_with_strategy(factfile,
+                                         contents,
                                          env,
-                                         start_from,
                                          factotum::executor::execution_strategy::execute_os,
                                          OverrideResultMappings::None,
-                                         webhook_url,
-                                         job_tags,
-                                         max_stdouterr_size)
+                                         options,
+                                         true)
 }
 
 fn parse_file_and_execute_with_strategy<F>(factfile: &str,
+                                           contents: &str,
                                            env: Option<Json>,
-                                           start_from: Option<String>,
                                            strategy: F,
                                            override_result_map: OverrideResultMappings,
-                                           webhook_url: Option<String>,
-                                           job_tags: Option<HashMap<String, String>>,
-                                           max_stdouterr_size: Option<usize>)
+                                           options: RunOptions,
+                                           record_run: bool)
                                            -> i32
     where F: Fn(&str, &mut Command) -> RunResult + Send + Sync + 'static + Copy
 {
+    match factotum::parser::parse_str(contents, factfile, env.clone(), override_result_map) {
+        Ok(mut job) => {
+
+            if let Err(msg) = apply_executor_overrides(&mut job, &options.executor_overrides) {
+                println!("{}", format!("Error: {}", msg).red());
+                return PROC_OTHER_ERROR;
+            }
 
-    match factotum::parser::parse(factfile, env, override_result_map) {
-        Ok(job) => {
+            if let Some(ref allowed) = options.allowed_executors {
+                if let Err(msg) = factotum::parser::validate_executors(&job, allowed) {
+                    println!("'{}' is not a valid factotum factfile: {}", factfile, msg);
+                    return PROC_PARSE_ERROR;
+                }
+            }
 
-            if let Some(ref start_task) = start_from {
-                if let Err(msg) = validate_start_task(&job, &start_task) {
-                    warn!("The job could not be started from '{}' because {}",
+            if let Some(ref start_task) = options.start_from {
+                if let Err(err) = validate_start_task(&job, &start_task) {
+                    warn!("The job could not be started from '{}' because {} ({})",
                           start_task,
-                          msg);
+                          err.message(),
+                          err.code());
                     println!("The job cannot be started from '{}' because {}",
                              start_task.cyan(),
-                             msg);
+                             err.message());
+                    return err.exit_code();
+                }
+            }
+
+            let completed_tasks = if options.retry_failed {
+                match get_previous_run_record(factfile, &job) {
+                    Ok(record) => {
+                        if record.failed_tasks().is_empty() {
+                            if options.count {
+                                println!("0");
+                                return PROC_SUCCESS;
+                            }
+                            println!("The last run of '{}' had no failed tasks - there's nothing \
+                                      to retry",
+                                     factfile.cyan());
+                            return PROC_SUCCESS;
+                        }
+                        record.succeeded_tasks()
+                    }
+                    Err(msg) => {
+                        warn!("Failed tasks could not be retried: {}", msg);
+                        println!("{}", format!("Error: {}", msg).red());
+                        return PROC_OTHER_ERROR;
+                    }
+                }
+            } else {
+                vec![]
+            };
+
+            for skip_task in options.skip_tasks.iter() {
+                if job.can_job_run_from_task(skip_task).is_err() {
+                    println!("{}",
+                             format!("Error: the task '{}' given to --skip isn't in the factfile",
+                                     skip_task)
+                                 .red());
+                    return PROC_OTHER_ERROR;
+                }
+            }
+
+            if let Err(msg) = validate_selection(&job,
+                                                 &options.start_from,
+                                                 options.retry_failed,
+                                                 &completed_tasks,
+                                                 &options.skip_tasks) {
+                warn!("The task selection is empty: {}", msg);
+                println!("{}", format!("Error: {}", msg).red());
+                return PROC_OTHER_ERROR;
+            }
+
+            if let Some(ref tail_task) = options.tail_task {
+                if job.can_job_run_from_task(tail_task).is_err() {
+                    println!("{}",
+                             format!("Error: the task '{}' given to --tail isn't in the factfile",
+                                     tail_task)
+                                 .red());
+                    return PROC_OTHER_ERROR;
+                }
+            }
+
+            if let Some(ref exit_task) = options.exit_from {
+                if job.can_job_run_from_task(exit_task).is_err() {
+                    println!("{}",
+                             format!("Error: the task '{}' given to --exit-from isn't in the \
+                                      factfile",
+                                     exit_task)
+                                 .red());
+                    return PROC_OTHER_ERROR;
+                }
+            }
+
+            let excluded_tasks = get_excluded_tasks(&job,
+                                                    options.start_from.clone(),
+                                                    &completed_tasks,
+                                                    &options.skip_tasks);
+
+            if options.count {
+                println!("{}",
+                         get_runnable_task_count(&job, options.start_from, &excluded_tasks));
+                return PROC_SUCCESS;
+            }
+
+            if options.max_width {
+                println!("{}", get_max_width(&job, options.start_from, &excluded_tasks));
+                return PROC_SUCCESS;
+            }
+
+            if let Some(ref dump_path) = options.dump_effective {
+                let secret_values = get_secret_values(&options.secrets, &env);
+                let effective = if record_run {
+                    Ok(job.as_effective_json())
+                } else {
+                    // simulations override every task's onResult, which isn't what would run
+                    factotum::parser::parse_str(contents,
+                                                factfile,
+                                                env.clone(),
+                                                OverrideResultMappings::None)
+                        .map(|ff| ff.as_effective_json())
+                };
+                let effective = effective.map(|e| mask_secrets(&e, &secret_values));
+                if let Err(msg) = effective.and_then(|e| write_to_file(dump_path, &e, true)) {
+                    println!("{}", format!("Error: {}", msg).red());
                     return PROC_OTHER_ERROR;
                 }
+                if !record_run {
+                    println!("Wrote the effective factfile to '{}'", dump_path.cyan());
+                    return PROC_SUCCESS;
+                }
+            }
+
+            if options.preflight {
+                let problems = get_preflight_problems(&job, &options);
+                if problems.is_empty() {
+                    println!("{}", "Preflight checks passed".green());
+                    return PROC_SUCCESS;
+                }
+                println!("{}",
+                         format!("Preflight checks found {} problem(s):", problems.len()).red());
+                for problem in problems.iter() {
+                    println!("  - {}", problem);
+                }
+                return PROC_OTHER_ERROR;
             }
 
-            let (maybe_updates_channel, maybe_join_handle) = if webhook_url.is_some() {
-                let url = webhook_url.unwrap();
-                let mut wh = Webhook::new(job.name.clone(), job.raw.clone(), url, job_tags, max_stdouterr_size);
+            // held until the run finishes; a lock left by a crashed run is spotted as stale
+            let _run_lock = if record_run && !options.force {
+                let lock_path = runlock::get_default_lock_path(factfile);
+                let lock = runlock::ensure_lock_dir()
+                    .and_then(|_| runlock::RunLock::acquire(&lock_path));
+                match lock {
+                    Ok(lock) => Some(lock),
+                    Err(msg) => {
+                        warn!("The job couldn't be started: {}", msg);
+                        println!("{}",
+                                 format!("Error: {} - use --force to run it anyway", msg).red());
+                        return PROC_OTHER_ERROR;
+                    }
+                }
+            } else {
+                None
+            };
+
+            let run_id = options.run_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+            let run_start = UTC::now();
+
+            let mut update_senders = vec![];
+
+            let maybe_events_file_handle = match options.events_out {
+                Some(ref template) => {
+                    let path = get_output_path(template, &job.name, &run_id, &run_start);
+                    let file = match eventsfile::create_events_file(&path) {
+                        Ok(f) => f,
+                        Err(msg) => {
+                            println!("{}", format!("Error: {}", msg).red());
+                            return PROC_OTHER_ERROR;
+                        }
+                    };
+                    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+                    update_senders.push(tx);
+                    Some(eventsfile::write_events_file(file,
+                                                       path,
+                                                       JobContext::new(job.name.clone(),
+                                                                       &job.raw,
+                                                                       options.job_tags.clone()),
+                                                       options.max_stdouterr_size
+                                                           .unwrap_or(DEFAULT_MAX_STDOUTERR_SIZE),
+                                                       !options.webhook_no_output,
+                                                       rx))
+                }
+                None => None,
+            };
+
+            let maybe_socket_handle = options.event_socket.as_ref().map(|path| {
+                let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+                update_senders.push(tx);
+                eventsocket::connect_event_socket(path.clone(),
+                                                  JobContext::new(job.name.clone(),
+                                                                  &job.raw,
+                                                                  options.job_tags.clone()),
+                                                  rx)
+            });
+
+            let maybe_join_handle = if let Some(url) = options.webhook_url {
+                let mut wh = Webhook::new(job.name.clone(),
+                                          job.raw.clone(),
+                                          url,
+                                          options.job_tags,
+                                          options.max_stdouterr_size);
+                wh.include_output = !options.webhook_no_output;
+                wh.format = options.webhook_format;
+                wh.secret = options.webhook_secret;
+                wh.redact_endpoint = options.webhook_url_from_env;
+                wh.job_events = options.webhook_job_events;
+                wh.include_factfile = options.webhook_include_factfile;
+                wh.host = gethostname_safe().ok();
                 let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
                 let join_handle =
                     wh.connect_webhook(rx, Webhook::http_post, webhook::backoff_rand_1_minute);
-                (Some(tx), Some(join_handle))
+                update_senders.push(tx);
+                Some(join_handle)
+            } else {
+                None
+            };
+
+            let maybe_updates_channel = get_updates_channel(update_senders);
+
+            let state_file = options.state_file.map(|path| {
+                runrecord::StateFile {
+                    path: get_output_path(&path, &job.name, &run_id, &run_start),
+                    factfile: factfile.to_string(),
+                }
+            });
+
+            let shuffle_seed = if options.shuffle {
+                let seed = options.seed.unwrap_or_else(|| rand::random::<usize>());
+                print_status(options.summary_format,
+                             &format!("Starting independent tasks in a random order (--seed={})",
+                                      seed));
+                Some(seed)
             } else {
-                (None, None)
+                None
+            };
+
+            let execution_options = ExecutionOptions {
+                completed_tasks: completed_tasks,
+                state_file: state_file,
+                shuffle_seed: shuffle_seed,
+                abort_file: options.abort_file.clone(),
+                workdir: options.workdir,
+                run_label: options.run_label.clone(),
+                no_skip_downstream: options.no_skip_downstream,
+                max_local_output_size: options.max_local_output_size,
+                task_approval: if options.interactive {
+                    Some(prompt_for_task_approval as TaskApprover)
+                } else {
+                    None
+                },
+                // a simulation shouldn't sit waiting for a task's start time
+                ignore_not_before: !record_run,
+                tag_limits: options.tag_limits.clone(),
+                duration_alert_percent: options.duration_alert_percent,
+                skip_tasks: options.skip_tasks.clone(),
+                poll_interval: Duration::from_millis(options.poll_interval_ms),
+                tail_task: options.tail_task.clone(),
+                verbose: options.verbose,
+                secret_values: get_secret_values(&options.secrets, &env),
+                jitter_seed: shuffle_seed.or(options.seed),
+                max_executions: options.max_executions,
             };
 
-            let job_res = factotum::executor::execute_factfile(&job,
-                                                               start_from,
-                                                               strategy,
-                                                               maybe_updates_channel);
+            if options.no_skip_downstream {
+                print_status(options.summary_format,
+                             &"Warning: tasks will run even if the tasks they depend on fail \
+                               (--no-skip-downstream)"
+                                 .yellow()
+                                 .to_string());
+            }
+
+            let start_from = options.start_from.clone();
+            let job_res = factotum::executor::execute_factfile_with_options(&job,
+                                                                            options.start_from,
+                                                                            strategy,
+                                                                            maybe_updates_channel,
+                                                                            execution_options);
 
             let mut has_errors = false;
             let mut has_early_finish = false;
+            let hit_max_executions = job_res.stopped_by == Some(StopReason::MaxExecutions);
+            let aborted_by_operator = job_res.stopped_by == Some(StopReason::Operator);
+            let was_aborted = aborted_by_operator ||
+                              job_res.stopped_by == Some(StopReason::AbortFile);
 
             let mut tasks = vec![];
 
@@ -419,49 +1468,98 @@ fn parse_file_and_execute_with_strategy<F>(factfile: &str,
                 }
             }
 
-            let normal_completion = !has_errors && !has_early_finish;
-
-            let result = if normal_completion {
-                let (stdout_summary, stderr_summary) = get_task_results_str(&tasks);
-                print!("{}", stdout_summary);
-                if !stderr_summary.trim_right().is_empty() {
-                    print_err!("{}", stderr_summary.trim_right());
+            if record_run {
+                if let Err(msg) = write_run_record(factfile, &job, &tasks) {
+                    warn!("Couldn't record the outcome of the run: {}", msg);
+                    print_status(options.summary_format,
+                                 &format!("Warning: couldn't record the outcome of this run: {}",
+                                          msg)
+                                     .yellow()
+                                     .to_string());
                 }
-                PROC_SUCCESS
-            } else if has_early_finish && !has_errors {
-                let (stdout_summary, stderr_summary) = get_task_results_str(&tasks);
-                print!("{}", stdout_summary);
-                if !stderr_summary.trim_right().is_empty() {
-                    print_err!("{}", stderr_summary.trim_right());
+
+                if let Some(ref history_db) = options.history_db {
+                    let db_path = get_output_path(history_db, &job.name, &run_id, &run_start);
+                    if let Err(msg) = history::record_run(&db_path, &run_id, &job.name, &tasks) {
+                        warn!("Couldn't add the run to the history database: {}", msg);
+                        print_status(options.summary_format,
+                                     &format!("Warning: couldn't add this run to the history \
+                                               database: {}",
+                                              msg)
+                                         .yellow()
+                                         .to_string());
+                    }
                 }
+            }
+
+            if let Some(ref label) = options.run_label {
+                print_status(options.summary_format, &format!("Run label: {}", label.cyan()));
+            }
+
+            let normal_completion = !has_errors && !has_early_finish && !was_aborted;
+
+            let result = if hit_max_executions {
+                print_task_results(&tasks, &options, &run_start);
                 let incomplete_tasks = tasks.iter()
-                    .filter(|r| !r.run_result.is_some())
+                    .filter(|r| !r.run_result.is_some() && r.state != State::Success)
                     .map(|r| format!("'{}'", r.name.cyan()))
                     .collect::<Vec<String>>()
                     .join(", ");
-                let stop_requesters = tasks.iter()
-                    .filter(|r| match r.state {
+                print_status(options.summary_format,
+                             &format!("Factotum job was stopped as it reached \
+                                       --max-executions={} - the following tasks were not run: \
+                                       {}!",
+                                      options.max_executions.unwrap_or(0),
+                                      incomplete_tasks));
+                PROC_OTHER_ERROR
+            } else if normal_completion {
+                print_task_results(&tasks, &options, &run_start);
+                PROC_SUCCESS
+            } else if was_aborted && !has_errors {
+                print_task_results(&tasks, &options, &run_start);
+                let incomplete_tasks = tasks.iter()
+                    .filter(|r| !r.run_result.is_some() && r.state != State::Success)
+                    .map(|r| format!("'{}'", r.name.cyan()))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let abort_cause = if aborted_by_operator {
+                    "by the operator".to_string()
+                } else {
+                    format!("as the abort file '{}' appeared",
+                            options.abort_file.as_ref().map(|f| f.as_str()).unwrap_or(""))
+                };
+                print_status(options.summary_format,
+                             &format!("Factotum job was aborted {} - the following tasks were \
+                                       not run: {}!",
+                                      abort_cause,
+                                      incomplete_tasks));
+                PROC_EXEC_ERROR
+            } else if has_early_finish && !has_errors {
+                print_task_results(&tasks, &options, &run_start);
+                let incomplete_tasks = tasks.iter()
+                    .filter(|r| !r.run_result.is_some() && r.state != State::Success)
+                    .map(|r| format!("'{}'", r.name.cyan()))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let stop_requesters = tasks.iter()
+                    .filter(|r| match r.state {
                         State::SuccessNoop => true,
                         _ => false,
                     })
                     .map(|r| format!("'{}'", r.name.cyan()))
                     .collect::<Vec<String>>()
                     .join(", ");
-                println!("Factotum job finished early as a task ({}) requested an early finish. \
-                          The following tasks were not run: {}.",
-                         stop_requesters,
-                         incomplete_tasks);
+                print_status(options.summary_format,
+                             &format!("Factotum job finished early as a task ({}) requested an \
+                                       early finish. The following tasks were not run: {}.",
+                                      stop_requesters,
+                                      incomplete_tasks));
                 PROC_SUCCESS
             } else {
-                let (stdout_summary, stderr_summary) = get_task_results_str(&tasks);
-                print!("{}", stdout_summary);
-
-                if !stderr_summary.trim_right().is_empty() {
-                    print_err!("{}", stderr_summary.trim_right());
-                }
+                print_task_results(&tasks, &options, &run_start);
 
                 let incomplete_tasks = tasks.iter()
-                    .filter(|r| !r.run_result.is_some())
+                    .filter(|r| !r.run_result.is_some() && r.state != State::Success)
                     .map(|r| format!("'{}'", r.name.cyan()))
                     .collect::<Vec<String>>()
                     .join(", ");
@@ -475,25 +1573,224 @@ fn parse_file_and_execute_with_strategy<F>(factfile: &str,
                     .collect::<Vec<String>>()
                     .join(", ");
 
-                println!("Factotum job executed abnormally as a task ({}) failed - the following \
-                          tasks were not run: {}!",
-                         failed_tasks,
-                         incomplete_tasks);
+                print_status(options.summary_format,
+                             &format!("Factotum job executed abnormally as a task ({}) failed - \
+                                       the following tasks were not run: {}!",
+                                      failed_tasks,
+                                      incomplete_tasks));
+
+                match options.fail_threshold {
+                    Some(threshold) if !was_aborted => {
+                        let failed_percentage = get_failed_percentage(&tasks);
+                        if failed_percentage <= threshold {
+                            print_status(options.summary_format,
+                                         &format!("{:.1}% of tasks failed, within the \
+                                                   --fail-threshold of {}% - treating this as \
+                                                   a success.",
+                                                  failed_percentage,
+                                                  threshold)
+                                             .yellow()
+                                             .to_string());
+                            PROC_SUCCESS
+                        } else {
+                            print_status(options.summary_format,
+                                         &format!("{:.1}% of tasks failed, more than the \
+                                                   --fail-threshold of {}%.",
+                                                  failed_percentage,
+                                                  threshold)
+                                             .red()
+                                             .to_string());
+                            PROC_EXEC_ERROR
+                        }
+                    }
+                    _ => PROC_EXEC_ERROR,
+                }
+            };
+
+            // tasks can be skipped by --skip, a precondition or the operator too, not just by an
+            // early finish
+            let result = if result == PROC_SUCCESS && options.fail_on_skipped &&
+                            has_skipped_tasks(&tasks) {
+                print_status(options.summary_format,
+                             &"No tasks failed, but some were skipped - treating this as a \
+                               failure (--fail-on-skipped)."
+                                 .red()
+                                 .to_string());
                 PROC_EXEC_ERROR
+            } else {
+                result
             };
 
+            if record_run {
+                if let Some(ref prom_textfile) = options.prom_textfile {
+                    let prom_path = get_output_path(prom_textfile, &job.name, &run_id, &run_start);
+                    if let Err(msg) = prometheus::write_textfile(&prom_path,
+                                                                 &job.name,
+                                                                 result == PROC_SUCCESS,
+                                                                 &tasks) {
+                        warn!("Couldn't write the Prometheus textfile: {}", msg);
+                        print_status(options.summary_format,
+                                     &format!("Warning: couldn't write the Prometheus textfile: \
+                                               {}",
+                                              msg)
+                                         .yellow()
+                                         .to_string());
+                    }
+                }
+
+                if let Some(ref log_bundle) = options.log_bundle {
+                    let bundle_path = get_output_path(log_bundle, &job.name, &run_id, &run_start);
+                    match logbundle::write_bundle(&bundle_path, &job.name, &run_id, &tasks) {
+                        Ok(_) => {
+                            print_status(options.summary_format,
+                                         &format!("Task logs were bundled into '{}'",
+                                                  bundle_path.cyan()))
+                        }
+                        Err(msg) => {
+                            warn!("Couldn't write the log bundle: {}", msg);
+                            print_status(options.summary_format,
+                                         &format!("Warning: couldn't write the log bundle: {}",
+                                                  msg)
+                                             .yellow()
+                                             .to_string());
+                        }
+                    }
+                }
+
+                if let Some(ref trace_out) = options.trace_out {
+                    let trace_path = get_output_path(trace_out, &job.name, &run_id, &run_start);
+                    match chrometrace::write_trace(&trace_path, &job.name, &tasks) {
+                        Ok(_) => {
+                            print_status(options.summary_format,
+                                         &format!("The trace was written to '{}'",
+                                                  trace_path.cyan()))
+                        }
+                        Err(msg) => {
+                            warn!("Couldn't write the trace: {}", msg);
+                            print_status(options.summary_format,
+                                         &format!("Warning: couldn't write the trace: {}", msg)
+                                             .yellow()
+                                             .to_string());
+                        }
+                    }
+                }
+
+                if let Some(ref result_url) = options.result_s3 {
+                    match resultexport::upload_result(result_url,
+                                                      &job.name,
+                                                      &run_id,
+                                                      result == PROC_SUCCESS,
+                                                      &run_start,
+                                                      &tasks) {
+                        Ok(_) => {
+                            print_status(options.summary_format, "The run summary was uploaded")
+                        }
+                        Err(msg) => {
+                            warn!("Couldn't upload the run summary: {}", msg);
+                            print_status(options.summary_format,
+                                         &format!("Warning: couldn't upload the run summary: {}",
+                                                  msg)
+                                             .yellow()
+                                             .to_string());
+                        }
+                    }
+                }
+            }
+
+            if options.critical_path_graph {
+                let durations = tasks.iter()
+                    .filter_map(|t| t.run_result.as_ref().map(|r| (t.name.clone(), r.duration)))
+                    .collect::<HashMap<String, Duration>>();
+                let graph = job.as_critical_path_dotfile(start_from, &durations);
+                match options.output {
+                    Some(ref template) => {
+                        let graph_path = get_output_path(template, &job.name, &run_id, &run_start);
+                        match write_to_file(&graph_path, &graph, options.overwrite) {
+                            Ok(_) => {
+                                println!("The critical path graph was written to '{}'",
+                                         graph_path.cyan())
+                            }
+                            Err(msg) => {
+                                println!("{}",
+                                         format!("Warning: couldn't write the critical path \
+                                                  graph: {}",
+                                                 msg)
+                                             .yellow())
+                            }
+                        }
+                    }
+                    None => println!("{}", graph),
+                }
+            }
+
+            // every emitter is joined before the exit code is settled
+            let mut webhook_failed = false;
             if maybe_join_handle.is_some() {
-                print!("Waiting for webhook to finish sending events...");
+                if options.summary_format == SummaryFormat::Text {
+                    print!("Waiting for webhook to finish sending events...");
+                } else {
+                    print_err!("Waiting for webhook to finish sending events...");
+                }
                 let j = maybe_join_handle.unwrap();
                 let webhook_res = j.join().ok().unwrap();
-                println!("{}", " done!".green());
+                if options.summary_format == SummaryFormat::Text {
+                    println!("{}", " done!".green());
+                }
 
                 if webhook_res.events_received > webhook_res.success_count {
-                    println!("{}", "Warning: some events failed to send".red());
+                    if options.fail_on_webhook_failure {
+                        print_status(options.summary_format,
+                                     &format!("Error: {} of {} events failed to send \
+                                               (--fail-on-webhook-failure)",
+                                              webhook_res.events_received -
+                                              webhook_res.success_count,
+                                              webhook_res.events_received)
+                                         .red()
+                                         .to_string());
+                        webhook_failed = true;
+                    } else {
+                        print_status(options.summary_format,
+                                     &"Warning: some events failed to send".red().to_string());
+                    }
                 }
             }
 
-            result
+            if let Some(events_file_handle) = maybe_events_file_handle {
+                let events_res = events_file_handle.join().ok().unwrap();
+                if events_res.events_received > events_res.events_written {
+                    print_status(options.summary_format,
+                                 &format!("Warning: {} of {} events couldn't be written to the \
+                                           events file",
+                                          events_res.events_received - events_res.events_written,
+                                          events_res.events_received)
+                                     .yellow()
+                                     .to_string());
+                }
+            }
+
+            if let Some(socket_handle) = maybe_socket_handle {
+                let socket_res = socket_handle.join().ok().unwrap();
+                if socket_res.events_received > socket_res.events_sent {
+                    print_status(options.summary_format,
+                                 &format!("Warning: {} of {} events couldn't be sent to the event \
+                                           socket",
+                                          socket_res.events_received - socket_res.events_sent,
+                                          socket_res.events_received)
+                                     .yellow()
+                                     .to_string());
+                }
+            }
+
+            let result = match options.exit_from {
+                Some(ref exit_task) => get_task_exit_code(&tasks, exit_task).unwrap_or(result),
+                None => result,
+            };
+
+            if webhook_failed && result == PROC_SUCCESS {
+                PROC_OTHER_ERROR
+            } else {
+                result
+            }
         } 
         Err(msg) => {
             println!("{}", msg);
@@ -502,6 +1799,419 @@ fn parse_file_and_execute_with_strategy<F>(factfile: &str,
     }
 }
 
+// the executor reports to a single channel, which is copied to every consumer
+fn get_updates_channel(mut senders: Vec<mpsc::Sender<ExecutionUpdate>>)
+                       -> Option<mpsc::Sender<ExecutionUpdate>> {
+    if senders.len() < 2 {
+        return senders.pop();
+    }
+
+    let (tx, rx) = mpsc::channel::<ExecutionUpdate>();
+    thread::spawn(move || for update in rx.iter() {
+        for sender in senders.iter() {
+            sender.send(update.clone()).ok();
+        }
+    });
+    Some(tx)
+}
+
+fn list_failed(state_file: &str) -> Result<Vec<String>, String> {
+    runrecord::read_record(state_file).map(|record| record.failed_tasks())
+}
+
+fn query_history(db_path: &str, since: &Option<String>, format: &Option<String>) -> i32 {
+    let json = match format.as_ref().map(|f| f.as_str()) {
+        None | Some("table") => false,
+        Some("json") => true,
+        Some(other) => {
+            print_err!("{}",
+                       format!("Error: unknown format '{}' for --history-query (supported \
+                                formats: table, json)",
+                               other)
+                           .red());
+            return PROC_OTHER_ERROR;
+        }
+    };
+    let since_time = match since.as_ref().map(|s| history::get_since(s)) {
+        Some(Ok(t)) => Some(t),
+        Some(Err(msg)) => {
+            print_err!("{}", format!("Error: invalid --since: {}", msg).red());
+            return PROC_OTHER_ERROR;
+        }
+        None => None,
+    };
+
+    match history::read_task_runs(db_path, &since_time) {
+        Ok(ref task_runs) if json => {
+            println!("{}", task_runs.to_json().pretty());
+            PROC_SUCCESS
+        }
+        Ok(ref task_runs) if task_runs.is_empty() => {
+            match since_time {
+                Some(t) => {
+                    println!("No task runs in '{}' since {}", db_path, t.to_rfc3339())
+                }
+                None => println!("No task runs in '{}'", db_path),
+            }
+            PROC_SUCCESS
+        }
+        Ok(task_runs) => {
+            println!("{}", history::to_history_table(&task_runs));
+            PROC_SUCCESS
+        }
+        Err(msg) => {
+            print_err!("{} {}", "Error:".red(), msg.red());
+            PROC_OTHER_ERROR
+        }
+    }
+}
+
+fn has_skipped_tasks(tasks: &Vec<&Task<&FactfileTask>>) -> bool {
+    tasks.iter().any(|t| match t.state {
+        State::Skipped(_) => true,
+        _ => false,
+    })
+}
+
+// only a task that actually ran has a return code to pass on
+fn get_task_exit_code(tasks: &Vec<&Task<&FactfileTask>>, name: &str) -> Option<i32> {
+    tasks.iter()
+        .find(|t| t.name == name)
+        .and_then(|t| t.run_result.as_ref())
+        .and_then(|r| if r.task_execution_error.is_none() {
+            Some(r.return_code)
+        } else {
+            None
+        })
+}
+
+fn get_failed_percentage(tasks: &Vec<&Task<&FactfileTask>>) -> f64 {
+    if tasks.is_empty() {
+        return 0.0;
+    }
+    let failed = tasks.iter()
+        .filter(|t| match t.state {
+            State::Failed(_) => true,
+            _ => false,
+        })
+        .count();
+    failed as f64 * 100.0 / tasks.len() as f64
+}
+
+// each selection flag is validated on its own elsewhere, this catches combinations that leave
+// nothing to run
+fn validate_selection(job: &Factfile,
+                      start_from: &Option<String>,
+                      retry_failed: bool,
+                      completed_tasks: &Vec<String>,
+                      skip_tasks: &Vec<String>)
+                      -> Result<(), String> {
+    if let (&Some(ref start_task), true) = (start_from, retry_failed) {
+        if get_runnable_task_count(job, start_from.clone(), completed_tasks) == 0 {
+            return Err(format!("--start={} and --retry-failed select no tasks together, as \
+                                '{}' and every task after it succeeded in the last run",
+                               start_task,
+                               start_task));
+        }
+    }
+    if !skip_tasks.is_empty() {
+        let excluded = get_excluded_tasks(job, start_from.clone(), completed_tasks, skip_tasks);
+        if get_runnable_task_count(job, start_from.clone(), &excluded) == 0 {
+            return Err(format!("--skip={} leaves no tasks to run, as every selected task is \
+                                skipped or depends on a skipped task",
+                               skip_tasks.join(",")));
+        }
+    }
+    Ok(())
+}
+
+// a skipped task's dependents are skipped with it
+fn get_excluded_tasks(job: &Factfile,
+                      start_from: Option<String>,
+                      completed_tasks: &Vec<String>,
+                      skip_tasks: &Vec<String>)
+                      -> Vec<String> {
+    let tasklist = factotum::executor::get_task_execution_list(job, start_from);
+    let mut excluded = completed_tasks.clone();
+    for skipped in skip_tasks.iter() {
+        excluded.push(skipped.clone());
+        excluded.extend(tasklist.get_descendants(skipped));
+    }
+    excluded.sort();
+    excluded.dedup();
+    excluded
+}
+
+// the first due time after `now`, and how many due times were missed on the way there
+fn get_next_repeat(last_due: Instant, interval: Duration, now: Instant) -> (Instant, u32) {
+    let mut next = last_due + interval;
+    let mut missed = 0;
+    while next <= now {
+        next = next + interval;
+        missed += 1;
+    }
+    (next, missed)
+}
+
+fn run_repeatedly<F>(interval: Duration, count: Option<u32>, run: F) -> i32
+    where F: Fn() -> i32
+{
+    let mut due = Instant::now();
+    let mut runs = 0;
+    let mut result = PROC_SUCCESS;
+
+    loop {
+        let run_result = run();
+        runs += 1;
+        if run_result != PROC_SUCCESS {
+            result = run_result;
+        }
+
+        if count.map(|c| runs >= c).unwrap_or(false) {
+            return result;
+        }
+
+        // runs never overlap - if a run overruns its slot, the runs that were due in the
+        // meantime are skipped
+        let (next, missed) = get_next_repeat(due, interval, Instant::now());
+        if missed > 0 {
+            warn!("The last run overran, skipping {} scheduled run(s)", missed);
+            println!("{}",
+                     format!("Warning: the last run took longer than --repeat-every, skipping \
+                              {} scheduled run(s)",
+                             missed)
+                         .yellow());
+        }
+        let now = Instant::now();
+        if next > now {
+            thread::sleep(next - now);
+        }
+        due = next;
+    }
+}
+
+fn get_task_approval(answer: &str) -> Option<TaskApproval> {
+    match answer.trim().to_lowercase().as_ref() {
+        "r" | "run" => Some(TaskApproval::Run),
+        "s" | "skip" => Some(TaskApproval::Skip),
+        "a" | "abort" => Some(TaskApproval::Abort),
+        _ => None,
+    }
+}
+
+fn prompt_for_task_approval(task_name: String, command: String) -> TaskApproval {
+    println!("Task '{}' is ready to run: {}", task_name.cyan(), command);
+    loop {
+        print!("[r]un, [s]kip or [a]bort? ");
+        io::stdout().flush().ok();
+        let mut answer = String::new();
+        match io::stdin().read_line(&mut answer) {
+            // nobody is left to answer
+            Ok(0) | Err(_) => return TaskApproval::Abort,
+            Ok(_) => {}
+        }
+        match get_task_approval(&answer) {
+            Some(approval) => return approval,
+            None => println!("{}", "Please answer r (run), s (skip) or a (abort)".yellow()),
+        }
+    }
+}
+
+// everything factotum (and any task that isn't captured) writes to stdout is discarded
+fn silence_stdout() -> Result<(), String> {
+    let devnull = try!(OpenOptions::new()
+        .write(true)
+        .open("/dev/null")
+        .map_err(|e| format!("couldn't open /dev/null: {}", e)));
+    io::stdout().flush().ok();
+    if unsafe { libc::dup2(devnull.as_raw_fd(), 1) } == -1 {
+        return Err(format!("couldn't redirect stdout: {}", io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+fn is_terminal() -> bool {
+    unsafe { libc::isatty(0) == 1 && libc::isatty(1) == 1 }
+}
+
+fn get_matrix_combinations(matrix: &str) -> Result<Vec<BTreeMap<String, String>>, String> {
+    let axes: BTreeMap<String, Vec<String>> = try!(json::decode(matrix).map_err(|err| {
+        format!("the matrix '{}' isn't a JSON object of lists of strings: {}",
+                matrix,
+                Error::description(&err))
+    }));
+
+    let mut combinations = vec![BTreeMap::new()];
+    for (name, values) in axes.iter() {
+        if values.is_empty() {
+            return Err(format!("the matrix value '{}' has no values to run with", name));
+        }
+        combinations = combinations.iter()
+            .flat_map(|c| {
+                values.iter().map(move |v| {
+                    let mut combination = c.clone();
+                    combination.insert(name.clone(), v.clone());
+                    combination
+                })
+            })
+            .collect();
+    }
+
+    Ok(combinations)
+}
+
+fn get_combination_str(combination: &BTreeMap<String, String>) -> String {
+    combination.iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// matrix values win over the same names given with --env
+fn with_matrix_values(env: &Option<Json>, combination: &BTreeMap<String, String>) -> Option<Json> {
+    let mut values = match *env {
+        Some(Json::Object(ref o)) => o.clone(),
+        _ => BTreeMap::new(),
+    };
+    for (k, v) in combination {
+        values.insert(k.clone(), Json::String(v.clone()));
+    }
+    Some(Json::Object(values))
+}
+
+fn run_matrix<F>(combinations: &Vec<BTreeMap<String, String>>, fail_fast: bool, run: F) -> i32
+    where F: Fn(&BTreeMap<String, String>) -> i32
+{
+    let mut result = PROC_SUCCESS;
+    let mut failed = vec![];
+    let mut attempted = 0;
+
+    for combination in combinations.iter() {
+        let combination_str = get_combination_str(combination);
+        println!("Running the job with {}", combination_str.cyan());
+        attempted += 1;
+
+        let run_result = run(combination);
+        if run_result != PROC_SUCCESS {
+            result = run_result;
+            failed.push(format!("({})", combination_str));
+            if fail_fast {
+                break;
+            }
+        }
+    }
+
+    let summary = format!("{}/{} matrix combinations succeeded",
+                          attempted - failed.len(),
+                          combinations.len());
+    if failed.is_empty() {
+        println!("{}", summary.green());
+    } else {
+        println!("{}", format!("{}, failed: {}", summary, failed.join(", ")).red());
+        if attempted < combinations.len() {
+            println!("{}",
+                     format!("{} combination(s) were not run (--fail-fast)",
+                             combinations.len() - attempted)
+                         .red());
+        }
+    }
+
+    result
+}
+
+// everything is checked, so all the problems can be fixed in one go
+fn get_preflight_problems(job: &Factfile, options: &RunOptions) -> Vec<String> {
+    let mut problems = vec![];
+
+    for task in job.get_tasks_in_order().iter().flat_map(|tg| tg.iter()) {
+        if !task.hosts.is_empty() {
+            // remote tasks don't use a local working directory
+            continue;
+        }
+        if let Some(dir) = factotum::executor::get_task_working_dir(task, &options.workdir) {
+            if !dir.is_dir() {
+                problems.push(format!("the working directory '{}' of the task '{}' doesn't exist",
+                                      dir.display(),
+                                      task.name));
+            }
+        }
+    }
+
+    let outputs = vec![("--state-file", &options.state_file),
+                       ("--history-db", &options.history_db),
+                       ("--prom-textfile", &options.prom_textfile),
+                       ("--log-bundle", &options.log_bundle),
+                       ("--trace-out", &options.trace_out),
+                       ("--events-out", &options.events_out),
+                       ("--run-meta", &options.run_meta)];
+    for (flag, template) in outputs {
+        if let Some(ref template) = *template {
+            let path = get_output_path(template, &job.name, "preflight", &UTC::now());
+            let parent = match Path::new(&path).parent() {
+                Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+                _ => Path::new(".").to_path_buf(),
+            };
+            if !parent.is_dir() {
+                problems.push(format!("the file '{}' for {} can't be created as the directory \
+                                       '{}' doesn't exist",
+                                      path,
+                                      flag,
+                                      parent.display()));
+            }
+        }
+    }
+
+    problems
+}
+
+fn get_runnable_task_count(job: &Factfile,
+                           start_from: Option<String>,
+                           excluded_tasks: &Vec<String>)
+                           -> usize {
+    factotum::executor::get_task_execution_list(job, start_from)
+        .tasks
+        .iter()
+        .flat_map(|task_group| task_group.iter())
+        .filter(|task| !excluded_tasks.contains(&task.name))
+        .count()
+}
+
+// tasks are started a level of the DAG at a time, so the widest level is the most that run at once
+fn get_max_width(job: &Factfile,
+                 start_from: Option<String>,
+                 excluded_tasks: &Vec<String>)
+                 -> usize {
+    factotum::executor::get_task_execution_list(job, start_from)
+        .tasks
+        .iter()
+        .map(|task_group| {
+            task_group.iter().filter(|task| !excluded_tasks.contains(&task.name)).count()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn get_previous_run_record(factfile: &str, job: &Factfile) -> Result<runrecord::RunRecord, String> {
+    let record_path = runrecord::get_default_record_path(factfile);
+    let record = try!(runrecord::read_record(&record_path)
+        .map_err(|e| format!("there is no previous run of '{}' to retry ({})", factfile, e)));
+    if record.is_same_factfile(&job.raw) {
+        Ok(record)
+    } else {
+        Err(format!("'{}' has changed since it was last run, its failed tasks can't be retried",
+                    factfile))
+    }
+}
+
+fn write_run_record(factfile: &str,
+                    job: &Factfile,
+                    tasks: &Vec<&Task<&FactfileTask>>)
+                    -> Result<(), String> {
+    try!(runrecord::ensure_record_dir());
+    let record = runrecord::RunRecord::new(factfile, &job.raw, tasks);
+    runrecord::write_record(&runrecord::get_default_record_path(factfile), &record)
+}
+
 fn write_to_file(filename: &str, contents: &str, overwrite: bool) -> Result<(), String> {
     let mut f = if overwrite {
         match OpenOptions::new()
@@ -539,34 +2249,151 @@ fn is_valid_url(url: &str) -> Result<(), String> {
     }
 }
 
+// the flag wins, and simulations never post anything so they don't pick up the env var
+fn get_env_webhook_url(flag: &Option<String>,
+                       strategy: &StrategyChoice,
+                       env_value: Option<String>)
+                       -> Option<String> {
+    match env_value {
+        Some(url) => {
+            if flag.is_none() && *strategy == StrategyChoice::Os && !url.is_empty() {
+                Some(url)
+            } else {
+                None
+            }
+        }
+        None => None,
+    }
+}
+
+fn get_executor_overrides(overrides: &Vec<String>) -> Result<Vec<(String, String)>, String> {
+    let mut parsed = vec![];
+    for spec in overrides.iter() {
+        // task names can have '=' in them, executors can't
+        let (task, executor) = match spec.rfind('=') {
+            Some(idx) if idx > 0 => (&spec[..idx], &spec[idx + 1..]),
+            _ => {
+                return Err(format!("'{}' isn't a valid --override-executor, it should be \
+                                    task=executor",
+                                   spec))
+            }
+        };
+        if !lint::RECOMMENDED_EXECUTORS.contains(&executor) {
+            return Err(format!("'{}' isn't an executor factotum can run (executors: {})",
+                               executor,
+                               lint::RECOMMENDED_EXECUTORS.join(", ")));
+        }
+        parsed.push((task.to_string(), executor.to_string()));
+    }
+    Ok(parsed)
+}
+
+// a later override of the same task wins
+fn apply_executor_overrides(job: &mut Factfile,
+                            overrides: &Vec<(String, String)>)
+                            -> Result<(), String> {
+    for &(ref name, ref executor) in overrides.iter() {
+        let task = match job.get_task_mut(name) {
+            Some(task) => task,
+            None => {
+                return Err(format!("the task '{}' given to --override-executor isn't in the \
+                                    factfile",
+                                   name))
+            }
+        };
+        if executor == "ssh" && task.hosts.is_empty() {
+            return Err(format!("the task '{}' has no hosts, so it can't use the ssh executor",
+                               name));
+        }
+        warn!("Task '{}' will use the executor '{}' instead of '{}' (--override-executor)",
+              name,
+              executor,
+              task.executor);
+        task.executor = executor.clone();
+        if executor == "shell" {
+            // with no hosts, the task runs on this machine
+            task.hosts.clear();
+        }
+    }
+    Ok(())
+}
+
 fn get_constraint_map(constraints: &Vec<String>) -> HashMap<String, String> {
     get_tag_map(constraints)
 }
 
-fn is_valid_host(host: &str) -> Result<(), String> {
+// which of a host constraint's resolved addresses have to be one of this machine's
+#[derive(Debug, PartialEq)]
+enum AddressMatch {
+    Any,
+    First,
+    All,
+}
+
+fn get_address_match(policy: Option<&str>) -> Result<AddressMatch, String> {
+    match policy {
+        None | Some("any") => Ok(AddressMatch::Any),
+        Some("first") => Ok(AddressMatch::First),
+        Some("all") => Ok(AddressMatch::All),
+        Some(other) => {
+            Err(format!("'{}' isn't a valid addresses constraint (any, first, all)", other))
+        }
+    }
+}
+
+fn is_address_match(host_addrs: &Vec<net::IpAddr>,
+                    external_addrs: &Vec<net::IpAddr>,
+                    policy: &AddressMatch)
+                    -> bool {
+    match *policy {
+        AddressMatch::Any => host_addrs.iter().any(|a| external_addrs.contains(a)),
+        AddressMatch::First => host_addrs.first().map_or(false, |a| external_addrs.contains(a)),
+        AddressMatch::All => {
+            !host_addrs.is_empty() && host_addrs.iter().all(|a| external_addrs.contains(a))
+        }
+    }
+}
+
+fn is_valid_host(host: &str,
+                 interface: Option<&str>,
+                 policy: &AddressMatch)
+                 -> Result<(), String> {
     if host == "*" {
         return Ok(());
     }
 
-    let os_hostname = try!(gethostname_safe().map_err(|e| e.to_string()));
+    // the hostname says nothing about which interface it's on, so only use it when unrestricted
+    if interface.is_none() {
+        let os_hostname = try!(gethostname_safe().map_err(|e| e.to_string()));
 
-    if host == os_hostname {
-        return Ok(());
+        if host == os_hostname {
+            return Ok(());
+        }
     }
 
-    let external_addrs = try!(get_external_addrs().map_err(|e| e.to_string()));
+    let external_addrs = try!(get_external_addrs(interface).map_err(|e| e.to_string()));
     let host_addrs = try!(dns_lookup::lookup_host(&host)
         .map_err(|_| "could not find any IPv4 addresses for the supplied hostname"));
 
-    for host_addr in host_addrs {
-        if let Ok(good_host_addr) = host_addr {
-            if external_addrs.iter().any(|external_addr| external_addr.ip() == good_host_addr) {
-                return Ok(());
-            }
-        }
+    let host_addrs = host_addrs.filter_map(|a| a.ok()).collect::<Vec<net::IpAddr>>();
+    let external_ips = external_addrs.iter().map(|a| a.ip()).collect::<Vec<net::IpAddr>>();
+    if is_address_match(&host_addrs, &external_ips, policy) {
+        return Ok(());
     }
 
-    Err("failed to match any of the interface addresses to the found host addresses".into())
+    match *policy {
+        AddressMatch::Any => {
+            Err("failed to match any of the interface addresses to the found host addresses"
+                .into())
+        }
+        AddressMatch::First => {
+            Err("failed to match the first of the found host addresses to an interface address"
+                .into())
+        }
+        AddressMatch::All => {
+            Err("failed to match all of the found host addresses to interface addresses".into())
+        }
+    }
 }
 
 extern "C" {
@@ -603,10 +2430,15 @@ fn gethostname_safe() -> Result<String, String> {
     }
 }
 
-fn get_external_addrs() -> Result<Vec<net::SocketAddr>, String> {
+fn get_external_addrs(interface: Option<&str>) -> Result<Vec<net::SocketAddr>, String> {
     let mut external_addrs = vec![];
 
     for iface in ifaces::Interface::get_all().unwrap().into_iter() {
+        if let Some(name) = interface {
+            if iface.name != name {
+                continue;
+            }
+        }
         if iface.kind == ifaces::Kind::Ipv4 {
             if let Some(addr) = iface.addr {
                 if !addr.ip().is_loopback() {
@@ -617,18 +2449,60 @@ fn get_external_addrs() -> Result<Vec<net::SocketAddr>, String> {
     }
 
     if external_addrs.len() == 0 {
-        Err("could not find any non-loopback IPv4 addresses in the network interfaces; do you \
-             have a working network interface card?"
-            .into())
+        if let Some(name) = interface {
+            Err(format!("could not find any non-loopback IPv4 addresses on the network interface \
+                         '{}'",
+                        name))
+        } else {
+            Err("could not find any non-loopback IPv4 addresses in the network interfaces; do \
+                 you have a working network interface card?"
+                .into())
+        }
     } else {
         Ok(external_addrs)
     }
 }
 
+fn get_tag_limits(args: &Vec<String>) -> Result<HashMap<String, usize>, String> {
+    let mut limits = HashMap::new();
+    for arg in args.iter() {
+        let limit = arg.rfind('=').and_then(|idx| {
+            let tag = arg[..idx].trim();
+            match arg[idx + 1..].trim().parse::<usize>() {
+                Ok(count) if count > 0 && !tag.is_empty() => Some((tag.to_string(), count)),
+                _ => None,
+            }
+        });
+        match limit {
+            Some((tag, count)) => {
+                limits.insert(tag, count);
+            }
+            None => {
+                return Err(format!("'{}' isn't a valid --max-concurrent-tag (expected \
+                                    <tag>=<count> with a count of at least 1, e.g. resource=2)",
+                                   arg))
+            }
+        }
+    }
+    Ok(limits)
+}
+
 fn get_tag_map(args: &Vec<String>) -> HashMap<String, String> {
     let mut arg_map: HashMap<String, String> = HashMap::new();
 
     for arg in args.iter() {
+        // "key=value" keeps the value as given, commas and all - it's used whenever the '='
+        // comes before any comma, so "key,a=b" is still the comma form
+        if let Some(idx) = arg.find(|c: char| c == ',' || c == '=') {
+            if arg[idx..].starts_with("=") {
+                let key = arg[..idx].trim();
+                if !key.is_empty() {
+                    arg_map.insert(key.to_string(), arg[idx + 1..].trim().to_string());
+                }
+                continue;
+            }
+        }
+
         let split = arg.split(",").collect::<Vec<&str>>();
         if split.len() >= 2 && split[0].trim().is_empty() == false {
             let key = split[0].trim().to_string();
@@ -669,6 +2543,75 @@ fn test_tag_map() {
     let mut expected_comma = HashMap::new();
     expected_comma.insert("the rain".to_string(), "first wow".to_string());
     assert_eq!(with_comma, expected_comma);
+
+    let equals = get_tag_map(&vec![" cols = a,b,,c ".to_string(),
+                                   "json={\"k\":\"v\"}".to_string(),
+                                   "blank=".to_string(),
+                                   "=nokey".to_string(),
+                                   "comma,a=b".to_string()]);
+    let mut expected_equals = HashMap::new();
+    expected_equals.insert("cols".to_string(), "a,b,,c".to_string());
+    expected_equals.insert("json".to_string(), "{\"k\":\"v\"}".to_string());
+    expected_equals.insert("blank".to_string(), "".to_string());
+    expected_equals.insert("comma".to_string(), "a=b".to_string());
+    assert_eq!(equals, expected_equals);
+}
+
+fn read_dotenv(path: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut contents = String::new();
+    try!(fs::File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut contents))
+        .map_err(|e| format!("couldn't read the dotenv file '{}': {}", path, e)));
+    parse_dotenv(&contents).map_err(|problems| {
+        format!("the dotenv file '{}' has malformed lines:\n  {}",
+                path,
+                problems.join("\n  "))
+    })
+}
+
+// KEY=value lines, optionally starting with "export" and with the value in quotes
+fn parse_dotenv(contents: &str) -> Result<BTreeMap<String, String>, Vec<String>> {
+    let mut values = BTreeMap::new();
+    let mut problems = vec![];
+
+    for (idx, raw_line) in contents.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = if line.starts_with("export ") {
+            line["export ".len()..].trim_left()
+        } else {
+            line
+        };
+
+        let (key, value) = match line.find('=') {
+            Some(eq) => (line[..eq].trim(), line[eq + 1..].trim()),
+            None => ("", ""),
+        };
+        let valid_key = !key.is_empty() && !key.starts_with(|c: char| c.is_digit(10)) &&
+                        key.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !valid_key {
+            problems.push(format!("line {}: '{}'", idx + 1, raw_line));
+            continue;
+        }
+
+        let quoted = value.len() >= 2 &&
+                     ((value.starts_with('"') && value.ends_with('"')) ||
+                      (value.starts_with('\'') && value.ends_with('\'')));
+        let value = if quoted {
+            &value[1..value.len() - 1]
+        } else {
+            value
+        };
+        values.insert(key.to_string(), value.to_string());
+    }
+
+    if problems.is_empty() {
+        Ok(values)
+    } else {
+        Err(problems)
+    }
 }
 
 fn json_str_to_btreemap(j: &str) -> Result<BTreeMap<String, String>, String> {
@@ -709,26 +2652,198 @@ fn str_to_json_bad_json() {
     }
 }
 
-fn get_log_config() -> Result<log4rs::config::Config, String> {    
-    let file_appender = match log4rs::appender::FileAppender::builder(".factotum/factotum.log").build() {
-        Ok(fa) => fa,
-        Err(e) => {
-            let cwd = env::current_dir().expect("Unable to get current working directory");
-            let expanded_path = format!("{}{}{}", cwd.display(), std::path::MAIN_SEPARATOR, ".factotum/factotum.log");
-            return Err(format!("couldn't create logfile appender to '{}'. Reason: {}", expanded_path, e.description()));
+const LOG_FILE: &'static str = ".factotum/factotum.log";
+
+struct JsonFileAppender {
+    file: fs::File,
+    run_label: Option<String>,
+}
+
+impl log4rs::Append for JsonFileAppender {
+    fn append(&mut self, record: &log::LogRecord) -> Result<(), Box<Error>> {
+        let line = get_json_log_line(&UTC::now(),
+                                     &record.level().to_string(),
+                                     record.location().module_path(),
+                                     &format!("{}", record.args()),
+                                     &self.run_label);
+        try!(writeln!(self.file, "{}", line));
+        Ok(())
+    }
+}
+
+fn get_json_log_line(timestamp: &chrono::DateTime<UTC>,
+                     level: &str,
+                     module: &str,
+                     message: &str,
+                     run_label: &Option<String>)
+                     -> String {
+    let mut line = BTreeMap::new();
+    line.insert("timestamp".to_string(),
+                Json::String(timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()));
+    line.insert("level".to_string(), Json::String(level.to_string()));
+    line.insert("module".to_string(), Json::String(module.to_string()));
+    line.insert("message".to_string(), Json::String(message.to_string()));
+    if let Some(ref label) = *run_label {
+        line.insert("runLabel".to_string(), Json::String(label.clone()));
+    }
+    Json::Object(line).to_string()
+}
+
+fn get_log_pattern(run_label: &Option<String>) -> String {
+    match *run_label {
+        // % is special to the pattern, so it's doubled up to keep it literal
+        Some(ref label) => format!("%d [%t] %l [{}] %T:%m", label.replace("%", "%%")),
+        None => "%d [%t] %l %T:%m".to_string(),
+    }
+}
+
+fn get_log_appender(json_log: bool,
+                    run_label: &Option<String>)
+                    -> Result<Box<log4rs::Append>, String> {
+    let appender: Result<Box<log4rs::Append>, String> = if json_log {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(LOG_FILE)
+            .map(|f| {
+                Box::new(JsonFileAppender {
+                    file: f,
+                    run_label: run_label.clone(),
+                }) as Box<log4rs::Append>
+            })
+            .map_err(|e| e.description().to_string())
+    } else {
+        let pattern = try!(log4rs::pattern::PatternLayout::new(&get_log_pattern(run_label))
+            .map_err(|e| format!("invalid log pattern: {}", e)));
+        log4rs::appender::FileAppender::builder(LOG_FILE)
+            .pattern(pattern)
+            .build()
+            .map(|fa| Box::new(fa) as Box<log4rs::Append>)
+            .map_err(|e| e.description().to_string())
+    };
+
+    appender.map_err(|e| {
+        let cwd = env::current_dir().expect("Unable to get current working directory");
+        let expanded_path = format!("{}{}{}", cwd.display(), std::path::MAIN_SEPARATOR, LOG_FILE);
+        format!("couldn't create logfile appender to '{}'. Reason: {}", expanded_path, e)
+    })
+}
+
+const SYSLOG_SOCKET: &'static str = "/dev/log";
+
+struct SyslogAppender {
+    socket: UnixDatagram,
+    facility: u32,
+    run_label: Option<String>,
+}
+
+impl log4rs::Append for SyslogAppender {
+    fn append(&mut self, record: &log::LogRecord) -> Result<(), Box<Error>> {
+        let line = get_syslog_line(self.facility,
+                                   record.level(),
+                                   &Local::now(),
+                                   record.location().module_path(),
+                                   &format!("{}", record.args()),
+                                   &self.run_label);
+        try!(self.socket.send(line.as_bytes()));
+        Ok(())
+    }
+}
+
+fn get_syslog_facility(name: &str) -> Result<u32, String> {
+    match name {
+        "user" => Ok(1),
+        "daemon" => Ok(3),
+        _ => {
+            let local = if name.starts_with("local") {
+                name[5..].parse::<u32>().ok()
+            } else {
+                None
+            };
+            match local {
+                Some(n) if n <= 7 => Ok(16 + n),
+                _ => {
+                    Err(format!("'{}' isn't a syslog facility factotum can use - try user, \
+                                 daemon or local0 to local7",
+                                name))
+                }
+            }
         }
+    }
+}
+
+// the traditional (RFC 3164) format, which every syslog daemon reading /dev/log understands
+fn get_syslog_line(facility: u32,
+                   level: log::LogLevel,
+                   timestamp: &chrono::DateTime<Local>,
+                   module: &str,
+                   message: &str,
+                   run_label: &Option<String>)
+                   -> String {
+    let severity = match level {
+        log::LogLevel::Error => 3,
+        log::LogLevel::Warn => 4,
+        log::LogLevel::Info => 6,
+        log::LogLevel::Debug | log::LogLevel::Trace => 7,
+    };
+    let label = match *run_label {
+        Some(ref label) => format!("[{}] ", label),
+        None => String::new(),
     };
+    format!("<{}>{} factotum[{}]: {} {}{}:{}",
+            facility * 8 + severity,
+            timestamp.format("%b %e %H:%M:%S"),
+            unsafe { libc::getpid() },
+            level,
+            label,
+            module,
+            message)
+}
+
+fn get_syslog_appender(facility: u32,
+                       run_label: &Option<String>)
+                       -> Result<Box<log4rs::Append>, String> {
+    UnixDatagram::unbound()
+        .and_then(|socket| socket.connect(SYSLOG_SOCKET).map(|_| socket))
+        .map(|socket| {
+            Box::new(SyslogAppender {
+                socket: socket,
+                facility: facility,
+                run_label: run_label.clone(),
+            }) as Box<log4rs::Append>
+        })
+        .map_err(|e| format!("couldn't connect to syslog at '{}'. Reason: {}", SYSLOG_SOCKET, e))
+}
+
+fn get_log_config(json_log: bool,
+                  run_label: &Option<String>,
+                  syslog_facility: Option<u32>)
+                  -> Result<log4rs::config::Config, String> {
+    let file_appender = try!(get_log_appender(json_log, run_label));
 
-    let root = log4rs::config::Root::builder(log::LogLevelFilter::Info)
+    let mut root = log4rs::config::Root::builder(log::LogLevelFilter::Info)
         .appender("file".to_string());
+    let mut appenders = vec![log4rs::config::Appender::builder("file".to_string(),
+                                                               file_appender).build()];
+
+    if let Some(facility) = syslog_facility {
+        let syslog_appender = try!(get_syslog_appender(facility, run_label));
+        root = root.appender("syslog".to_string());
+        appenders.push(log4rs::config::Appender::builder("syslog".to_string(), syslog_appender)
+            .build());
+    }
 
-    log4rs::config::Config::builder(root.build())
-        .appender(log4rs::config::Appender::builder("file".to_string(),
-                                                    Box::new(file_appender)).build())
-        .build().map_err(|e| format!("error setting logging. Reason: {}", e.description()))
+    let mut config = log4rs::config::Config::builder(root.build());
+    for appender in appenders {
+        config = config.appender(appender);
+    }
+    config.build().map_err(|e| format!("error setting logging. Reason: {}", e.description()))
 }
 
-fn init_logger() -> Result<(), String> {
+fn init_logger(json_log: bool,
+               run_label: &Option<String>,
+               syslog_facility: Option<u32>)
+               -> Result<(), String> {
     match fs::create_dir(".factotum") {
         Ok(_) => (),
         Err(e) => match e.kind() {
@@ -740,7 +2855,7 @@ fn init_logger() -> Result<(), String> {
             }
         }
     };
-    let log_config = try!(get_log_config());
+    let log_config = try!(get_log_config(json_log, run_label, syslog_facility));
     log4rs::init_config(log_config).map_err(|e| format!("couldn't initialize log configuration. Reason: {}", e.description()))
 }
 
@@ -749,11 +2864,6 @@ fn main() {
 }
 
 fn factotum() -> i32 {
-    if let Err(log) = init_logger() {
-        println!("Log initialization error: {}", log);
-        return PROC_OTHER_ERROR;
-    }
-
     let args: Args = match Docopt::new(USAGE).and_then(|d| d.decode()) {
         Ok(a) => a,
         Err(e) => {
@@ -762,12 +2872,51 @@ fn factotum() -> i32 {
         }
     };
 
+    let syslog_facility = if args.flag_syslog {
+        let name = args.flag_syslog_facility.clone().unwrap_or("user".to_string());
+        match get_syslog_facility(&name) {
+            Ok(facility) => Some(facility),
+            Err(msg) => {
+                println!("{}", format!("Error: {}", msg).red());
+                return PROC_OTHER_ERROR;
+            }
+        }
+    } else if args.flag_syslog_facility.is_some() {
+        println!("{}", "Error: --syslog-facility can only be used with --syslog".red());
+        return PROC_OTHER_ERROR;
+    } else {
+        None
+    };
+
+    if let Err(log) = init_logger(args.flag_log_json, &args.flag_run_label, syslog_facility) {
+        println!("Log initialization error: {}", log);
+        return PROC_OTHER_ERROR;
+    }
+
     let tag_map = if let Some(tags) = args.flag_tag {
         Some(get_tag_map(&tags))
     } else {
         None
     };
 
+    let dotenv = match args.flag_dotenv {
+        Some(ref path) => {
+            match read_dotenv(path) {
+                Ok(values) => values,
+                Err(msg) => {
+                    println!("{}", format!("Error: {}", msg).red());
+                    return PROC_OTHER_ERROR;
+                }
+            }
+        }
+        None => BTreeMap::new(),
+    };
+
+    // set in the environment so ${VAR} references (and tasks) see them over the OS's values
+    for (key, value) in dotenv.iter() {
+        env::set_var(key, value);
+    }
+
     // Environment should always be present as tags can populate the env
     let env_str: String = if let Some(c) = args.flag_env {
         c
@@ -778,6 +2927,10 @@ fn factotum() -> i32 {
     let env_json: Option<Json> = {
         match json_str_to_btreemap(&env_str) {
             Ok(mut a) => {
+                for (key, value) in dotenv.iter() {
+                    a.entry(key.clone()).or_insert(value.clone());
+                }
+
                 if let Some(tm) = tag_map.as_ref() {
                     for (key, value) in tm {
                         let tag_key = format!("tag:{}", key.to_string());
@@ -802,7 +2955,9 @@ fn factotum() -> i32 {
         }
     };
 
-    if args.flag_no_colour {
+    // a spreadsheet would show the colour codes as text
+    let csv_summary = args.flag_summary_format.as_ref().map_or(false, |f| f == "csv");
+    if args.flag_no_colour || csv_summary {
         env::set_var("CLICOLOR", "0");
     }
 
@@ -811,12 +2966,64 @@ fn factotum() -> i32 {
         return PROC_SUCCESS;
     }
 
+    if let Some(ref state_file) = args.flag_list_failed {
+        return match list_failed(state_file) {
+            Ok(failed) => {
+                for task in failed.iter() {
+                    println!("{}", task);
+                }
+                if failed.is_empty() {
+                    PROC_SUCCESS
+                } else {
+                    PROC_EXEC_ERROR
+                }
+            }
+            Err(msg) => {
+                print_err!("{} {}", "Error:".red(), msg.red());
+                PROC_OTHER_ERROR
+            }
+        };
+    }
+
+    if let Some(ref db_path) = args.flag_history_query {
+        return query_history(db_path, &args.flag_since, &args.flag_format);
+    }
+
+    if let Some(ref url) = args.flag_result_s3 {
+        if let Err(msg) = is_valid_url(url) {
+            println!("{}", format!("Error: --result-s3 isn't a valid URL: {}", msg).red());
+            return PROC_OTHER_ERROR;
+        }
+    }
+
     if args.flag_dry_run && args.flag_webhook.is_some() {
         println!("{}",
                  "Error: --webhook cannot be used with the --dry-run option".red());
         return PROC_OTHER_ERROR;
     }
 
+    let strategy = match get_strategy_choice(&args.flag_strategy, args.flag_dry_run) {
+        Ok(s) => s,
+        Err(msg) => {
+            println!("{}", format!("Error: {}", msg).red());
+            return PROC_OTHER_ERROR;
+        }
+    };
+
+    if let Some(ref workdir) = args.flag_workdir {
+        if !Path::new(workdir).is_dir() {
+            println!("{}",
+                     format!("Error: the working directory '{}' doesn't exist", workdir).red());
+            return PROC_OTHER_ERROR;
+        }
+    }
+
+    if strategy == StrategyChoice::Simulate && args.flag_webhook.is_some() {
+        println!("{}",
+                 "Error: --webhook cannot be used with the simulate strategy".red());
+        return PROC_OTHER_ERROR;
+    }
+
     if let Some(ref wh) = args.flag_webhook {
         if let Err(msg) = is_valid_url(&wh) {
             println!("{}",
@@ -828,48 +3035,381 @@ fn factotum() -> i32 {
         }
     }
 
+    let env_webhook_url = get_env_webhook_url(&args.flag_webhook,
+                                              &strategy,
+                                              env::var(WEBHOOK_URL_ENV_VAR).ok());
+
+    if let Some(ref wh) = env_webhook_url {
+        if let Err(msg) = is_valid_url(wh) {
+            println!("{}",
+                     format!("Error: the webhook URL in {} is invalid. Reason: {}",
+                             WEBHOOK_URL_ENV_VAR,
+                             msg)
+                         .red());
+            return PROC_OTHER_ERROR;
+        }
+    }
+
+    let summary_order = match get_summary_order(&args.flag_summary_order) {
+        Ok(o) => o,
+        Err(msg) => {
+            println!("{}", format!("Error: {}", msg).red());
+            return PROC_OTHER_ERROR;
+        }
+    };
+
+    let override_executor_args = args.flag_override_executor.clone().unwrap_or(vec![]);
+    let executor_overrides = match get_executor_overrides(&override_executor_args) {
+        Ok(o) => o,
+        Err(msg) => {
+            println!("{}", format!("Error: {}", msg).red());
+            return PROC_OTHER_ERROR;
+        }
+    };
+
+    let summary_format = match get_summary_format(&args.flag_summary_format) {
+        Ok(f) => f,
+        Err(msg) => {
+            println!("{}", format!("Error: {}", msg).red());
+            return PROC_OTHER_ERROR;
+        }
+    };
+
+    // dot has its own graph formats
+    let critical_path_graph = match wants_critical_path_graph(&args.flag_graph) {
+        Ok(g) => g,
+        Err(msg) => {
+            if args.cmd_run {
+                println!("{}", format!("Error: {}", msg).red());
+                return PROC_OTHER_ERROR;
+            }
+            false
+        }
+    };
+
+    if args.flag_poll_interval_ms == Some(0) {
+        println!("{}", "Error: --poll-interval-ms must be at least 1".red());
+        return PROC_OTHER_ERROR;
+    }
+
+    if args.flag_duration_alert.map_or(false, |percent| percent < 0.0) {
+        println!("{}", "Error: --duration-alert must be a percentage of at least 0".red());
+        return PROC_OTHER_ERROR;
+    }
+
+    if let Some(threshold) = args.flag_fail_threshold {
+        if threshold < 0.0 || threshold > 100.0 {
+            println!("{}",
+                     "Error: --fail-threshold must be a percentage between 0 and 100".red());
+            return PROC_OTHER_ERROR;
+        }
+    }
+
+    let tag_limit_args = args.flag_max_concurrent_tag.clone().unwrap_or(vec![]);
+    let tag_limits = match get_tag_limits(&tag_limit_args) {
+        Ok(limits) => limits,
+        Err(msg) => {
+            println!("{}", format!("Error: {}", msg).red());
+            return PROC_OTHER_ERROR;
+        }
+    };
+
+    let webhook_format = match args.flag_webhook_format {
+        Some(ref format) => {
+            match webhook::get_webhook_format(format) {
+                Ok(f) => f,
+                Err(msg) => {
+                    println!("{}", format!("Error: {}", msg).red());
+                    return PROC_OTHER_ERROR;
+                }
+            }
+        }
+        None => webhook::WebhookFormat::default(),
+    };
+
+    if args.flag_webhook_job_events && webhook_format == webhook::WebhookFormat::Slack {
+        println!("{}",
+                 "Error: --webhook-job-events can't be used with the slack webhook format".red());
+        return PROC_OTHER_ERROR;
+    }
+
+    let webhook_include_factfile = match args.flag_webhook_include_factfile {
+        Some(ref mode) => {
+            if !args.flag_webhook_job_events {
+                println!("{}",
+                         "Error: --webhook-include-factfile needs --webhook-job-events".red());
+                return PROC_OTHER_ERROR;
+            }
+            match webhook::get_factfile_inclusion(mode) {
+                Ok(i) => i,
+                Err(msg) => {
+                    println!("{}", format!("Error: {}", msg).red());
+                    return PROC_OTHER_ERROR;
+                }
+            }
+        }
+        None => webhook::FactfileInclusion::default(),
+    };
+
     if args.cmd_run {
         if let Some(constraints) = args.flag_constraint {
             let c_map = get_constraint_map(&constraints);
 
             if let Some(host_value) = c_map.get(CONSTRAINT_HOST) {
-                if let Err(msg) = is_valid_host(host_value) {
+                let interface = c_map.get(CONSTRAINT_INTERFACE).map(|i| i.as_ref());
+                let policy = match get_address_match(c_map.get(CONSTRAINT_ADDRESSES)
+                    .map(|p| p.as_ref())) {
+                    Ok(p) => p,
+                    Err(msg) => {
+                        println!("{}", format!("Error: {}", msg).red());
+                        return PROC_OTHER_ERROR;
+                    }
+                };
+                if let Err(msg) = is_valid_host(host_value, interface, &policy) {
+                    println!("{}",
+                             format!("Warn: the specifed host constraint \"{}\" did not match, \
+                                      no tasks have been executed. Reason: {}",
+                                     host_value,
+                                     msg)
+                                 .yellow());
+                    return PROC_SUCCESS;
+                }
+            }
+        }
+
+        let run_options = RunOptions {
+            start_from: args.flag_start,
+            webhook_url_from_env: env_webhook_url.is_some(),
+            webhook_url: args.flag_webhook.or(env_webhook_url),
+            job_tags: tag_map,
+            max_stdouterr_size: args.flag_max_stdouterr_size,
+            webhook_no_output: args.flag_webhook_no_output,
+            retry_failed: args.flag_retry_failed,
+            allowed_executors: args.flag_allow_executors.map(|e| get_executor_list(&e)),
+            output_on_failure: args.flag_output_on_failure,
+            count: args.flag_count,
+            fail_on_skipped: args.flag_fail_on_skipped,
+            fail_on_webhook_failure: args.flag_fail_on_webhook_failure,
+            state_file: args.flag_state_file,
+            webhook_format: webhook_format,
+            shuffle: args.flag_shuffle,
+            seed: args.flag_seed,
+            history_db: args.flag_history_db,
+            abort_file: args.flag_abort_file,
+            prom_textfile: args.flag_prom_textfile,
+            workdir: args.flag_workdir,
+            run_label: args.flag_run_label.clone(),
+            log_bundle: args.flag_log_bundle,
+            no_skip_downstream: args.flag_no_skip_downstream,
+            // the secret never goes in the log
+            webhook_secret: args.flag_webhook_secret
+                .or_else(|| env::var(WEBHOOK_SECRET_ENV_VAR).ok()),
+            preflight: args.flag_preflight,
+            summary_format: summary_format,
+            max_local_output_size: args.flag_max_local_output_size,
+            interactive: args.flag_interactive,
+            group_by: args.flag_group_by,
+            event_socket: args.flag_event_socket,
+            dump_effective: args.flag_dump_effective,
+            max_width: args.flag_max_width,
+            critical_path_graph: critical_path_graph,
+            output: args.flag_output,
+            overwrite: args.flag_overwrite,
+            fail_threshold: args.flag_fail_threshold,
+            exit_from: args.flag_exit_from,
+            tag_limits: tag_limits,
+            webhook_job_events: args.flag_webhook_job_events,
+            duration_alert_percent: args.flag_duration_alert
+                .unwrap_or(DEFAULT_DURATION_ALERT_PERCENT),
+            skip_tasks: args.flag_skip.clone().unwrap_or(vec![]),
+            poll_interval_ms: args.flag_poll_interval_ms.unwrap_or(DEFAULT_POLL_INTERVAL_MS),
+            result_s3: args.flag_result_s3.clone(),
+            secrets: args.flag_secret.clone().unwrap_or(vec![]),
+            tail_task: args.flag_tail.clone(),
+            force: args.flag_force,
+            trace_out: args.flag_trace_out.clone(),
+            verbose: args.flag_verbose,
+            quiet: args.flag_quiet,
+            run_meta: args.flag_run_meta,
+            run_id: None,
+            max_executions: args.flag_max_executions,
+            executor_overrides: executor_overrides,
+            events_out: args.flag_events_out,
+            summary_order: summary_order,
+            webhook_include_factfile: webhook_include_factfile,
+        };
+
+        if args.flag_interactive && !is_terminal() {
+            println!("{}",
+                     "Error: --interactive needs a terminal to ask for approval on".red());
+            return PROC_OTHER_ERROR;
+        }
+
+        if args.flag_quiet {
+            if args.flag_interactive || args.flag_verbose || args.flag_tail.is_some() {
+                println!("{}",
+                         "Error: --quiet can't be used with --interactive, --verbose or --tail"
+                             .red());
+                return PROC_OTHER_ERROR;
+            }
+            if let Err(msg) = silence_stdout() {
+                println!("{}", format!("Error: {}", msg).red());
+                return PROC_OTHER_ERROR;
+            }
+        }
+
+        let matrix = match args.flag_matrix {
+            Some(ref m) => {
+                match get_matrix_combinations(m) {
+                    Ok(combinations) => Some(combinations),
+                    Err(msg) => {
+                        println!("{}", format!("Error: {}", msg).red());
+                        return PROC_OTHER_ERROR;
+                    }
+                }
+            }
+            None if args.flag_fail_fast => {
+                println!("{}", "Error: --fail-fast can only be used with --matrix".red());
+                return PROC_OTHER_ERROR;
+            }
+            None => None,
+        };
+
+        let run_with_env = |env: Option<Json>| {
+            // the id is decided here, so the metadata has it even if the run never starts
+            let mut options = run_options.clone();
+            options.run_id = Some(Uuid::new_v4().to_string());
+            let run_start = UTC::now();
+            let secret_values = get_secret_values(&options.secrets, &env);
+
+            // read once, so the metadata hashes exactly what was run
+            let contents = factotum::parser::read_factfile(&args.arg_factfile);
+            let result = match (&contents, &strategy) {
+                (&Ok(ref c), &StrategyChoice::Os) => {
+                    parse_file_and_execute(&args.arg_factfile, c, env.clone(), options.clone())
+                }
+                (&Ok(ref c), &StrategyChoice::Simulate) => {
+                    parse_file_and_simulate(&args.arg_factfile, c, env.clone(), options.clone())
+                }
+                (&Err(ref msg), _) => {
+                    // a factfile that can't be read (or fetched) is a different failure to one
+                    // that's invalid
+                    println!("{}", msg.red());
+                    PROC_OTHER_ERROR
+                }
+            };
+
+            if let Some(ref meta_template) = options.run_meta {
+                let contents = contents.ok();
+                let job = contents.as_ref().and_then(|c| {
+                    factotum::parser::parse_str(c,
+                                                &args.arg_factfile,
+                                                env.clone(),
+                                                OverrideResultMappings::None)
+                        .ok()
+                });
+                // the parsed job's raw factfile takes in its includes, the file alone doesn't
+                let factfile_hash = match job {
+                    Some(ref job) => Some(runrecord::hash_factfile(&job.raw)),
+                    None => contents.as_ref().map(|c| runrecord::hash_factfile(c)),
+                };
+                let job_name = job.map_or("unknown".to_string(), |job| job.name);
+                let run_id = options.run_id.as_ref().unwrap();
+                let meta_path = get_output_path(meta_template, &job_name, run_id, &run_start);
+                let mut secrets = secret_values;
+                secrets.extend(options.webhook_secret.clone());
+                let meta = get_run_meta(run_id,
+                                        &args.arg_factfile,
+                                        &factfile_hash,
+                                        &env::args().collect(),
+                                        &secrets,
+                                        &run_start,
+                                        &UTC::now(),
+                                        result);
+                if let Err(msg) = write_to_file(&meta_path, &meta.pretty().to_string(), true) {
+                    warn!("Couldn't write the run metadata: {}", msg);
                     println!("{}",
-                             format!("Warn: the specifed host constraint \"{}\" did not match, \
-                                      no tasks have been executed. Reason: {}",
-                                     host_value,
-                                     msg)
+                             format!("Warning: couldn't write the run metadata: {}", msg)
                                  .yellow());
-                    return PROC_SUCCESS;
                 }
             }
-        }
+            result
+        };
 
-        if !args.flag_dry_run {
-            parse_file_and_execute(&args.arg_factfile,
-                                   env_json,
-                                   args.flag_start,
-                                   args.flag_webhook,
-                                   tag_map,
-                                   args.flag_max_stdouterr_size)
-        } else {
-            parse_file_and_simulate(&args.arg_factfile, env_json, args.flag_start)
+        let run = || match matrix {
+            Some(ref combinations) => {
+                run_matrix(combinations,
+                           args.flag_fail_fast,
+                           |c| run_with_env(with_matrix_values(&env_json, c)))
+            }
+            None => run_with_env(env_json.clone()),
+        };
+
+        match (args.flag_repeat_every, args.flag_repeat_count) {
+            (Some(0), _) => {
+                println!("{}", "Error: --repeat-every must be at least 1 second".red());
+                PROC_OTHER_ERROR
+            }
+            (Some(seconds), count) => {
+                run_repeatedly(Duration::from_secs(seconds), count, run)
+            }
+            (None, Some(_)) => {
+                println!("{}",
+                         "Error: --repeat-count can only be used with --repeat-every".red());
+                PROC_OTHER_ERROR
+            }
+            (None, None) => run(),
         }
     } else if args.cmd_validate {
-        match validate(&args.arg_factfile, env_json) {
-            Ok(msg) => {
-                println!("{}", msg);
-                PROC_SUCCESS
+        let allowed_executors = args.flag_allow_executors.map(|e| get_executor_list(&e));
+        match args.flag_format {
+            Some(ref format) if format == "json" => {
+                let (valid, report) =
+                    get_validation_report(&args.arg_factfile, env_json, allowed_executors);
+                println!("{}", report);
+                if valid {
+                    PROC_SUCCESS
+                } else {
+                    PROC_PARSE_ERROR
+                }
             }
-            Err(msg) => {
-                println!("{}", msg);
-                PROC_PARSE_ERROR
+            Some(ref format) if format != "text" => {
+                println!("{}",
+                         format!("Error: unknown format '{}' (supported formats: text, json)",
+                                 format)
+                             .red());
+                PROC_OTHER_ERROR
+            }
+            _ => {
+                match validate(&args.arg_factfile, env_json, allowed_executors) {
+                    Ok(msg) => {
+                        println!("{}", msg);
+                        PROC_SUCCESS
+                    }
+                    Err(msg) => {
+                        println!("{}", msg);
+                        PROC_PARSE_ERROR
+                    }
+                }
             }
         }
+    } else if args.cmd_lint {
+        lint_factfile(&args.arg_factfile, env_json)
     } else if args.cmd_dot {
-        match dot(&args.arg_factfile, args.flag_start) {
-            Ok(dot) => {
-                if let Some(output_file) = args.flag_output {
+        let graph_format = match get_graph_format(&args.flag_graph) {
+            Ok(format) => format,
+            Err(msg) => {
+                print_err!("{} {}", "Error:".red(), msg.red());
+                return PROC_OTHER_ERROR;
+            }
+        };
+        match dot(&args.arg_factfile, args.flag_start, graph_format) {
+            Ok((job_name, dot)) => {
+                if let Some(output_template) = args.flag_output {
+                    let output_file = get_output_path(&output_template,
+                                                      &job_name,
+                                                      &Uuid::new_v4().to_string(),
+                                                      &UTC::now());
                     match write_to_file(&output_file, &dot, args.flag_overwrite) {
                         Ok(_) => {
                             println!("{}", "File written successfully".green());
@@ -960,7 +3500,7 @@ fn test_write_to_file() {
 #[test]
 fn validate_ok_factfile_good() {
     let test_file_path = "./tests/resources/example_ok.factfile";
-    let is_valid = validate(test_file_path, None);
+    let is_valid = validate(test_file_path, None, None);
     let expected: String = format!("'{}' is a valid Factfile!", test_file_path).green().to_string();
     assert_eq!(is_valid, Ok(expected));
 }
@@ -968,7 +3508,7 @@ fn validate_ok_factfile_good() {
 #[test]
 fn validate_ok_factfile_bad() {
     let test_file_path = "./tests/resources/invalid_json.factfile";
-    let is_valid = validate(test_file_path, None);
+    let is_valid = validate(test_file_path, None, None);
     match is_valid {
         Ok(_) => panic!("Validation returning valid for invalid file"),
         Err(msg) => {
@@ -978,12 +3518,523 @@ fn validate_ok_factfile_bad() {
     }
 }
 
+#[test]
+fn validate_ok_factfile_disallowed_executor() {
+    let test_file_path = "./tests/resources/example_ok.factfile";
+    let allowed = Some(vec!["noop".to_string()]);
+    match validate(test_file_path, None, allowed) {
+        Ok(_) => panic!("Validation returning valid for a disallowed executor"),
+        Err(msg) => {
+            assert!(msg.contains("the task 'EmrEtlRunner' uses the executor 'shell', which isn't \
+                                  allowed"))
+        }
+    }
+
+    let allowed_shell = Some(vec!["noop".to_string(), "shell".to_string()]);
+    assert!(validate(test_file_path, None, allowed_shell).is_ok());
+}
+
+#[test]
+fn test_get_strategy_choice() {
+    assert_eq!(get_strategy_choice(&None, false), Ok(StrategyChoice::Os));
+    assert_eq!(get_strategy_choice(&None, true), Ok(StrategyChoice::Simulate));
+    assert_eq!(get_strategy_choice(&Some("os".to_string()), false),
+               Ok(StrategyChoice::Os));
+    assert_eq!(get_strategy_choice(&Some("simulate".to_string()), false),
+               Ok(StrategyChoice::Simulate));
+    assert_eq!(get_strategy_choice(&Some("simulate".to_string()), true),
+               Ok(StrategyChoice::Simulate));
+    assert_eq!(get_strategy_choice(&Some("os".to_string()), true),
+               Err("--dry-run can only be used with the simulate strategy".to_string()));
+    assert_eq!(get_strategy_choice(&Some("ssh".to_string()), false),
+               Err("unknown strategy 'ssh' (supported strategies: os, simulate)".to_string()));
+}
+
+#[test]
+fn get_env_webhook_url_only_used_without_flag() {
+    let env_url = Some("https://example.com/hook".to_string());
+    assert_eq!(get_env_webhook_url(&None, &StrategyChoice::Os, env_url.clone()),
+               env_url);
+    assert_eq!(get_env_webhook_url(&Some("https://other.com".to_string()),
+                                   &StrategyChoice::Os,
+                                   env_url.clone()),
+               None);
+    assert_eq!(get_env_webhook_url(&None, &StrategyChoice::Simulate, env_url), None);
+    assert_eq!(get_env_webhook_url(&None, &StrategyChoice::Os, Some("".to_string())),
+               None);
+    assert_eq!(get_env_webhook_url(&None, &StrategyChoice::Os, None), None);
+}
+
+#[test]
+fn test_get_output_path() {
+    use chrono::TimeZone;
+
+    let time = UTC.ymd(2017, 3, 1).and_hms(12, 30, 5);
+    assert_eq!(get_output_path("result-{job}-{uuid}.json", "My First DAG", "abc-123", &time),
+               "result-My_First_DAG-abc-123.json");
+    assert_eq!(get_output_path("out/{timestamp}.dot", "x/y", "abc", &time),
+               "out/20170301T123005Z.dot");
+    assert_eq!(get_output_path("plain.dot", "job", "abc", &time), "plain.dot");
+}
+
+#[test]
+fn test_list_failed() {
+    use factotum::factfile::Task as FactfileTask;
+
+    let spec = FactfileTask { name: "a".to_string(), ..Default::default() };
+    let mut good = Task::new("good", &spec);
+    good.state = State::Success;
+    let mut bad = Task::new("bad", &spec);
+    bad.state = State::Failed("exited with 1".to_string());
+    let mut skipped = Task::new("skipped", &spec);
+    skipped.state = State::Skipped("the task 'bad' failed".to_string());
+    let record = runrecord::RunRecord::new("test.factfile", "{}", &vec![&good, &bad, &skipped]);
+
+    let path = env::temp_dir().join("factotum-list-failed-test.json");
+    let path_str = path.to_str().unwrap();
+    runrecord::write_record(path_str, &record).unwrap();
+    assert_eq!(list_failed(path_str), Ok(vec!["bad".to_string()]));
+    fs::remove_file(&path).unwrap();
+
+    assert!(list_failed(path_str).is_err());
+}
+
+#[test]
+fn test_query_history_rejects_bad_input() {
+    let missing = "/factotum/does/not/exist/history.db";
+    assert_eq!(query_history(missing, &None, &None), PROC_OTHER_ERROR);
+    assert_eq!(query_history(missing, &None, &Some("csv".to_string())),
+               PROC_OTHER_ERROR);
+    assert_eq!(query_history(missing, &Some("yesterday".to_string()), &None),
+               PROC_OTHER_ERROR);
+}
+
+#[test]
+fn test_has_skipped_tasks() {
+    use factotum::factfile::Task as FactfileTask;
+
+    let spec = FactfileTask { name: "a".to_string(), ..Default::default() };
+    let mut done = Task::new("a", &spec);
+    done.state = State::Success;
+    let mut noop = Task::new("b", &spec);
+    noop.state = State::SuccessNoop;
+    assert!(!has_skipped_tasks(&vec![&done, &noop]));
+
+    let mut skipped = Task::new("c", &spec);
+    skipped.state = State::Skipped("the task 'b' requested early termination".to_string());
+    assert!(has_skipped_tasks(&vec![&done, &noop, &skipped]));
+}
+
+#[test]
+fn test_fail_on_skipped_covers_tasks_skipped_from_the_command_line() {
+    let factfile = "tests/resources/example_ok.factfile";
+    let contents = factotum::parser::read_factfile(factfile).unwrap();
+    let skip_last = || {
+        RunOptions { skip_tasks: vec!["SQL Runner".to_string()], ..Default::default() }
+    };
+
+    assert_eq!(parse_file_and_simulate(factfile, &contents, None, skip_last()),
+               PROC_SUCCESS);
+    assert_eq!(parse_file_and_simulate(factfile,
+                                       &contents,
+                                       None,
+                                       RunOptions { fail_on_skipped: true, ..skip_last() }),
+               PROC_EXEC_ERROR);
+}
+
+#[test]
+fn test_get_task_exit_code() {
+    use factotum::executor::execution_strategy::RunResult;
+    use factotum::factfile::Task as FactfileTask;
+
+    let spec = FactfileTask { name: "a".to_string(), ..Default::default() };
+    let mut ran = Task::new("ran", &spec);
+    ran.run_result = Some(RunResult { return_code: 7, ..Default::default() });
+    let mut broken = Task::new("broken", &spec);
+    broken.run_result = Some(RunResult {
+        return_code: -1,
+        task_execution_error: Some("Error executing process".to_string()),
+        ..Default::default()
+    });
+    let skipped = Task::new("skipped", &spec);
+    let tasks = vec![&ran, &broken, &skipped];
+
+    assert_eq!(get_task_exit_code(&tasks, "ran"), Some(7));
+    assert_eq!(get_task_exit_code(&tasks, "broken"), None);
+    assert_eq!(get_task_exit_code(&tasks, "skipped"), None);
+    assert_eq!(get_task_exit_code(&tasks, "missing"), None);
+}
+
+#[test]
+fn test_get_failed_percentage() {
+    use factotum::factfile::Task as FactfileTask;
+
+    let spec = FactfileTask { name: "a".to_string(), ..Default::default() };
+    let mut done = Task::new("a", &spec);
+    done.state = State::Success;
+    let mut failed = Task::new("b", &spec);
+    failed.state = State::Failed("the task exited with a return code of 1".to_string());
+    let mut skipped = Task::new("c", &spec);
+    skipped.state = State::Skipped("the task 'b' failed".to_string());
+    let mut other_done = Task::new("d", &spec);
+    other_done.state = State::Success;
+
+    assert_eq!(get_failed_percentage(&vec![]), 0.0);
+    assert_eq!(get_failed_percentage(&vec![&done, &other_done]), 0.0);
+    assert_eq!(get_failed_percentage(&vec![&done, &failed, &skipped, &other_done]), 25.0);
+}
+
+#[test]
+fn test_get_runnable_task_count() {
+    let job = factotum::parser::parse("./tests/resources/example_ok.factfile",
+                                      None,
+                                      OverrideResultMappings::None)
+        .unwrap();
+
+    assert_eq!(get_runnable_task_count(&job, None, &vec![]), 3);
+    assert_eq!(get_runnable_task_count(&job, Some("StorageLoader".to_string()), &vec![]),
+               2);
+    let excluded = get_excluded_tasks(&job, None, &vec![], &vec!["StorageLoader".to_string()]);
+    assert_eq!(get_runnable_task_count(&job, None, &excluded), 1);
+    assert_eq!(get_runnable_task_count(&job, None, &vec!["EmrEtlRunner".to_string()]),
+               2);
+}
+
+#[test]
+fn test_mask_secrets() {
+    env::set_var("FACTOTUM_TEST_SECRET_TOKEN", "t0ken");
+    let env_json = Some(Json::from_str("{\"password\": \"hunter\\\"2\", \"port\": 5432, \
+                                         \"empty\": \"\"}")
+        .unwrap());
+    let secrets = get_secret_values(&vec!["password".to_string(),
+                                          "port".to_string(),
+                                          "empty".to_string(),
+                                          "FACTOTUM_TEST_SECRET_TOKEN".to_string(),
+                                          "FACTOTUM_TEST_NOT_SET".to_string()],
+                                     &env_json);
+    assert_eq!(secrets, vec!["hunter\"2", "5432", "t0ken"]);
+
+    let effective = "{\"arguments\": [\"--password=hunter\\\"2\", \"--port\", \"5432\", \
+                     \"t0ken\"]}";
+    assert_eq!(mask_secrets(effective, &secrets),
+               "{\"arguments\": [\"--password=***\", \"--port\", \"***\", \"***\"]}");
+    // an empty --webhook-secret is added to these as it is
+    assert_eq!(mask_secrets("--port 5432", &vec!["".to_string(), "5432".to_string()]),
+               "--port ***");
+}
+
+#[test]
+fn test_get_graph_format() {
+    assert_eq!(get_graph_format(&None), Ok(GraphFormat::Dot));
+    assert_eq!(get_graph_format(&Some("dot".to_string())), Ok(GraphFormat::Dot));
+    assert_eq!(get_graph_format(&Some("edges".to_string())), Ok(GraphFormat::Edges));
+    assert_eq!(get_graph_format(&Some("dot-critical".to_string())),
+               Err("unknown graph 'dot-critical' (supported graphs: dot, edges)".to_string()));
+}
+
+#[test]
+fn test_wants_critical_path_graph() {
+    assert_eq!(wants_critical_path_graph(&None), Ok(false));
+    assert_eq!(wants_critical_path_graph(&Some("dot-critical".to_string())), Ok(true));
+    assert_eq!(wants_critical_path_graph(&Some("svg".to_string())),
+               Err("unknown graph 'svg' (supported graphs: dot-critical)".to_string()));
+}
+
+#[test]
+fn test_get_validation_report() {
+    let factfile = "./tests/resources/example_ok.factfile";
+
+    let (valid, report) = get_validation_report(factfile, None, None);
+    assert!(valid);
+    assert_eq!(Json::from_str(&report).unwrap().find("errors"),
+               Some(&Json::Array(vec![])));
+
+    let (valid, report) = get_validation_report(factfile, None, Some(vec!["noop".to_string()]));
+    assert!(!valid);
+    let report = Json::from_str(&report).unwrap();
+    assert_eq!(report.find("valid"), Some(&Json::Boolean(false)));
+    let errors = report.find("errors").and_then(|e| e.as_array()).unwrap();
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].find("task"), Some(&"EmrEtlRunner".to_json()));
+    assert_eq!(errors[0].find("field"), Some(&"executor".to_json()));
+}
+
+#[test]
+fn test_parse_dotenv() {
+    let contents = "# shared settings\n\nREGION=eu-west-1\nexport BUCKET = \"my bucket\"\n\
+                    EMPTY=\nQUOTE='it''s'\n";
+    let values = parse_dotenv(contents).unwrap();
+    assert_eq!(values.get("REGION"), Some(&"eu-west-1".to_string()));
+    assert_eq!(values.get("BUCKET"), Some(&"my bucket".to_string()));
+    assert_eq!(values.get("EMPTY"), Some(&"".to_string()));
+    assert_eq!(values.get("QUOTE"), Some(&"it''s".to_string()));
+    assert_eq!(values.len(), 4);
+
+    assert_eq!(parse_dotenv("OK=1\nnot a setting\n=value\n1ST=x\n"),
+               Err(vec!["line 2: 'not a setting'".to_string(),
+                        "line 3: '=value'".to_string(),
+                        "line 4: '1ST=x'".to_string()]));
+}
+
+#[test]
+fn test_get_task_results_str_job_times() {
+    use chrono::TimeZone;
+
+    let started = UTC.ymd(2017, 3, 1).and_hms(12, 30, 5);
+    let finished = UTC.ymd(2017, 3, 1).and_hms(15, 31, 6);
+    let (stdout, _) = get_task_results_str(&vec![], false, Some((started, finished)));
+
+    assert_eq!(stdout,
+               format!("Job started at {} and finished at {}, taking {}\n{}",
+                       started,
+                       finished,
+                       "3h, 1m, 1s".bold(),
+                       "0/0 tasks run in 0.0s\n".green()));
+}
+
+#[test]
+fn test_get_tag_limits() {
+    let limits = get_tag_limits(&vec!["resource=2".to_string(), "resource:db = 1".to_string()])
+        .unwrap();
+    assert_eq!(limits.get("resource"), Some(&2));
+    assert_eq!(limits.get("resource:db"), Some(&1));
+    assert_eq!(limits.len(), 2);
+
+    for bad in ["resource", "resource=0", "=2", "resource=lots"].iter() {
+        assert!(get_tag_limits(&vec![bad.to_string()]).is_err());
+    }
+}
+
+#[test]
+fn test_get_max_width() {
+    let mut job = Factfile::new("N/A", "wide");
+    for &(name, ref deps) in [("extract", vec![]),
+                              ("enrich", vec!["extract"]),
+                              ("shred", vec!["extract"]),
+                              ("dedupe", vec!["extract"]),
+                              ("load", vec!["enrich", "shred", "dedupe"])]
+        .iter() {
+        job.add_task_obj(&FactfileTask {
+            name: name.to_string(),
+            depends_on: deps.iter().map(|d: &&str| d.to_string()).collect(),
+            ..Default::default()
+        });
+    }
+
+    assert_eq!(get_max_width(&job, None, &vec![]), 3);
+    assert_eq!(get_max_width(&job, None, &vec!["enrich".to_string()]), 2);
+    assert_eq!(get_max_width(&job, Some("load".to_string()), &vec![]), 1);
+    assert_eq!(get_max_width(&Factfile::new("N/A", "empty"), None, &vec![]), 0);
+
+    // skipping a task skips everything downstream of it too
+    let excluded = get_excluded_tasks(&job, None, &vec![], &vec!["enrich".to_string()]);
+    assert_eq!(excluded, vec!["enrich".to_string(), "load".to_string()]);
+    assert_eq!(get_max_width(&job, None, &excluded), 2);
+    let excluded = get_excluded_tasks(&job, None, &vec![], &vec!["extract".to_string()]);
+    assert_eq!(get_max_width(&job, None, &excluded), 0);
+}
+
+#[test]
+fn test_validate_selection() {
+    let job = factotum::parser::parse("./tests/resources/example_ok.factfile",
+                                      None,
+                                      OverrideResultMappings::None)
+        .unwrap();
+    let start = Some("StorageLoader".to_string());
+    let all_but_first = vec!["StorageLoader".to_string(), "SQL Runner".to_string()];
+
+    assert_eq!(validate_selection(&job, &start, false, &vec![], &vec![]), Ok(()));
+    assert_eq!(validate_selection(&job, &None, true, &all_but_first, &vec![]), Ok(()));
+    assert_eq!(validate_selection(&job,
+                                  &start,
+                                  true,
+                                  &vec!["StorageLoader".to_string()],
+                                  &vec![]),
+               Ok(()));
+    assert_eq!(validate_selection(&job, &None, false, &vec![], &vec!["SQL Runner".to_string()]),
+               Ok(()));
+    assert_eq!(validate_selection(&job, &start, false, &vec![], &vec!["StorageLoader".to_string()]),
+               Err("--skip=StorageLoader leaves no tasks to run, as every selected task is \
+                    skipped or depends on a skipped task"
+                   .to_string()));
+    assert_eq!(validate_selection(&job, &start, true, &all_but_first, &vec![]),
+               Err("--start=StorageLoader and --retry-failed select no tasks together, as \
+                    'StorageLoader' and every task after it succeeded in the last run"
+                   .to_string()));
+}
+
+#[test]
+fn test_get_next_repeat() {
+    let start = Instant::now();
+    let interval = Duration::from_secs(10);
+
+    assert_eq!(get_next_repeat(start, interval, start + Duration::from_secs(3)),
+               (start + Duration::from_secs(10), 0));
+    assert_eq!(get_next_repeat(start, interval, start + Duration::from_secs(10)),
+               (start + Duration::from_secs(20), 1));
+    assert_eq!(get_next_repeat(start, interval, start + Duration::from_secs(35)),
+               (start + Duration::from_secs(40), 3));
+}
+
+#[test]
+fn test_run_repeatedly_stops_after_count() {
+    use std::cell::Cell;
+
+    let runs = Cell::new(0);
+    let result = run_repeatedly(Duration::from_millis(1), Some(3), || {
+        runs.set(runs.get() + 1);
+        if runs.get() == 2 { PROC_EXEC_ERROR } else { PROC_SUCCESS }
+    });
+
+    assert_eq!(runs.get(), 3);
+    assert_eq!(result, PROC_EXEC_ERROR);
+}
+
+#[test]
+fn test_get_preflight_problems() {
+    let job = factotum::parser::parse("./tests/resources/example_ok.factfile",
+                                      None,
+                                      OverrideResultMappings::None)
+        .unwrap();
+
+    assert_eq!(get_preflight_problems(&job, &RunOptions::default()),
+               Vec::<String>::new());
+
+    let options = RunOptions {
+        workdir: Some("/factotum/does/not/exist".to_string()),
+        state_file: Some("state.json".to_string()),
+        history_db: Some("/factotum/does/not/exist/history.db".to_string()),
+        ..Default::default()
+    };
+    let problems = get_preflight_problems(&job, &options);
+    assert_eq!(problems.len(), 4);
+    assert_eq!(problems[0],
+               "the working directory '/factotum/does/not/exist' of the task 'EmrEtlRunner' \
+                doesn't exist");
+    assert_eq!(problems[3],
+               "the file '/factotum/does/not/exist/history.db' for --history-db can't be created \
+                as the directory '/factotum/does/not/exist' doesn't exist");
+}
+
+#[test]
+fn test_get_executor_list() {
+    assert_eq!(get_executor_list("shell, noop,,"),
+               vec!["shell".to_string(), "noop".to_string()]);
+    assert_eq!(get_executor_list(""), Vec::<String>::new());
+}
+
+#[test]
+fn test_executor_overrides() {
+    let overrides = get_executor_overrides(&vec!["load=ssh".to_string(),
+                                                 "a=b=shell".to_string(),
+                                                 "load=shell".to_string()])
+        .unwrap();
+    assert_eq!(overrides,
+               vec![("load".to_string(), "ssh".to_string()),
+                    ("a=b".to_string(), "shell".to_string()),
+                    ("load".to_string(), "shell".to_string())]);
+    assert!(get_executor_overrides(&vec!["load=noop".to_string()]).is_err());
+    assert!(get_executor_overrides(&vec!["shell".to_string()]).is_err());
+
+    let mut job = Factfile::new("N/A", "test");
+    job.add_task_obj(&FactfileTask {
+        name: "load".to_string(),
+        executor: "ssh".to_string(),
+        hosts: vec!["worker-1".to_string()],
+        ..Default::default()
+    });
+    job.add_task("report", &vec!["load"], "shell", "", &vec![], &vec![], &vec![0]);
+
+    apply_executor_overrides(&mut job, &overrides[2..].to_vec()).unwrap();
+    let load = job.get_task_mut("load").unwrap().clone();
+    assert_eq!(load.executor, "shell");
+    assert!(load.hosts.is_empty());
+
+    let to_ssh = vec![("report".to_string(), "ssh".to_string())];
+    assert_eq!(apply_executor_overrides(&mut job, &to_ssh),
+               Err("the task 'report' has no hosts, so it can't use the ssh executor".to_string()));
+    let missing = vec![("extract".to_string(), "shell".to_string())];
+    assert!(apply_executor_overrides(&mut job, &missing).is_err());
+}
+
 #[test]
 fn have_valid_config() {
     fs::create_dir(".factotum").ok();
-    if let Err(errs) = get_log_config() {
+    if let Err(errs) = get_log_config(false, &None, None) {
+        panic!("config not building correctly! {:?}", errs);
+    }
+    if let Err(errs) = get_log_config(true, &None, None) {
+        panic!("json config not building correctly! {:?}", errs);
+    }
+    if let Err(errs) = get_log_config(false, &Some("build-42".to_string()), None) {
         panic!("config not building correctly! {:?}", errs);
     }
+    if let Err(errs) = get_log_config(true, &Some("build-42".to_string()), None) {
+        panic!("json config not building correctly! {:?}", errs);
+    }
+}
+
+#[test]
+fn test_get_syslog_facility() {
+    assert_eq!(get_syslog_facility("user"), Ok(1));
+    assert_eq!(get_syslog_facility("daemon"), Ok(3));
+    assert_eq!(get_syslog_facility("local0"), Ok(16));
+    assert_eq!(get_syslog_facility("local7"), Ok(23));
+    assert!(get_syslog_facility("local8").is_err());
+    assert!(get_syslog_facility("7").is_err());
+    assert_eq!(get_syslog_facility("kern"),
+               Err("'kern' isn't a syslog facility factotum can use - try user, daemon or \
+                    local0 to local7"
+                   .to_string()));
+}
+
+#[test]
+fn syslog_line_has_priority_and_tag() {
+    use chrono::TimeZone;
+    let dt = Local.ymd(2016, 1, 2).and_hms(3, 4, 5);
+    let pid = unsafe { libc::getpid() };
+
+    assert_eq!(get_syslog_line(1, log::LogLevel::Warn, &dt, "factotum", "uh oh", &None),
+               format!("<12>Jan  2 03:04:05 factotum[{}]: WARN factotum:uh oh", pid));
+    assert_eq!(get_syslog_line(16,
+                               log::LogLevel::Info,
+                               &dt,
+                               "factotum::executor",
+                               "started",
+                               &Some("build-42".to_string())),
+               format!("<134>Jan  2 03:04:05 factotum[{}]: INFO [build-42] \
+                        factotum::executor:started",
+                       pid));
+}
+
+#[test]
+fn json_log_line_has_fields() {
+    let dt = UTC::now();
+    let line = get_json_log_line(&dt, "INFO", "factotum::parser", "reading \"file\"", &None);
+    let parsed = Json::from_str(&line).unwrap();
+    let obj = parsed.as_object().unwrap();
+
+    assert_eq!(obj.get("timestamp").unwrap().as_string().unwrap(),
+               dt.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string());
+    assert_eq!(obj.get("level").unwrap().as_string().unwrap(), "INFO");
+    assert_eq!(obj.get("module").unwrap().as_string().unwrap(),
+               "factotum::parser");
+    assert_eq!(obj.get("message").unwrap().as_string().unwrap(),
+               "reading \"file\"");
+    assert!(obj.get("runLabel").is_none());
+    assert!(!line.contains("\n"));
+
+    let labelled = get_json_log_line(&dt, "INFO", "factotum", "hi", &Some("build-42".to_string()));
+    let parsed = Json::from_str(&labelled).unwrap();
+    assert_eq!(parsed.as_object().unwrap().get("runLabel").unwrap().as_string().unwrap(),
+               "build-42");
+}
+
+#[test]
+fn test_get_log_pattern() {
+    assert_eq!(get_log_pattern(&None), "%d [%t] %l %T:%m");
+    assert_eq!(get_log_pattern(&Some("100%".to_string())),
+               "%d [%t] %l [100%%] %T:%m");
 }
 
 #[test]
@@ -1033,6 +4084,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         run_result: Some(RunResult {
             duration: Duration::from_secs(20),
@@ -1040,18 +4092,20 @@ fn test_get_task_result_line_str() {
             stdout: Some(String::from("hello world")),
             stderr: None,
             return_code: 0,
+            ..Default::default()
         }),
     };
 
-    let expected = format!("Task '{}' was started at {}\nTask '{}' stdout:\n{}\n{}{}{}\n",
-                           "hello world".cyan(),
-                           dt,
-                           "hello world".cyan(),
-                           "hello world".bold(),
-                           "Task '".green(),
-                           "hello world".cyan(),
-                           "': succeeded after 20.0s".green());
-    let (result_stdout, result_stderr) = get_task_result_line_str(&sample_task);
+    let expected =
+        format!("Task '{}' was started at {}\nTask '{}' stdout (11 bytes):\n{}\n{}{}{}\n",
+                "hello world".cyan(),
+                dt,
+                "hello world".cyan(),
+                "hello world".bold(),
+                "Task '".green(),
+                "hello world".cyan(),
+                "': succeeded after 20.0s".green());
+    let (result_stdout, result_stderr) = get_task_result_line_str(&sample_task, false);
     assert_eq!(result_stdout, expected);
     assert_eq!(result_stderr, None);
 
@@ -1072,6 +4126,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         run_result: Some(RunResult {
             duration: Duration::from_secs(20),
@@ -1079,15 +4134,17 @@ fn test_get_task_result_line_str() {
             stdout: Some(String::from("hello world")),
             stderr: Some(String::from("There's errors")),
             return_code: 0,
+            ..Default::default()
         }),
     };
 
-    assert_eq!(format!("Task '{}' stderr:\n{}\n",
+    assert_eq!(format!("Task '{}' stderr (14 bytes):\n{}\n",
                        sample_task.name.cyan(),
                        "There's errors".red()),
-               get_task_result_line_str(&sample_task_stdout).1.unwrap());
-    assert_eq!(get_task_result_line_str(&sample_task_stdout).0,
-               format!("Task '{}' was started at {}\nTask '{}' stdout:\n{}\n{}{}{}\n",
+               get_task_result_line_str(&sample_task_stdout, false).1.unwrap());
+    assert_eq!(get_task_result_line_str(&sample_task_stdout, false).0,
+               format!("Task '{}' was started at {}\nTask '{}' stdout (11 bytes):\n{}\n\
+                        {}{}{}\n",
                        "hello world".cyan(),
                        dt,
                        "hello world".cyan(),
@@ -1112,14 +4169,15 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         state: State::Skipped("for some reason".to_string()),
         run_result: None,
     };
 
     assert_eq!(format!("Task '{}': skipped!\n", "skip".cyan()),
-               get_task_result_line_str(&task_skipped).0);
-    assert_eq!(None, get_task_result_line_str(&task_skipped).1);
+               get_task_result_line_str(&task_skipped, false).0);
+    assert_eq!(None, get_task_result_line_str(&task_skipped, false).1);
 
     let task_init_fail = Task::<&FactfileTask> {
         name: String::from("init fail"),
@@ -1136,6 +4194,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         run_result: None,
     };
@@ -1143,8 +4202,8 @@ fn test_get_task_result_line_str() {
     assert_eq!(format!("Task '{}': {}!\n",
                        "init fail".cyan(),
                        "Factotum could not start the task".red()),
-               get_task_result_line_str(&task_init_fail).0);
-    assert_eq!(None, get_task_result_line_str(&task_init_fail).1);
+               get_task_result_line_str(&task_init_fail, false).0);
+    assert_eq!(None, get_task_result_line_str(&task_init_fail, false).1);
 
     let task_failure = Task::<&FactfileTask> {
         name: String::from("fails"),
@@ -1161,6 +4220,7 @@ fn test_get_task_result_line_str() {
                 terminate_job: vec![],
                 continue_job: vec![],
             },
+            ..Default::default()
         },
         run_result: Some(RunResult {
             duration: Duration::from_secs(20),
@@ -1168,11 +4228,12 @@ fn test_get_task_result_line_str() {
             stdout: Some(String::from("hello world")),
             stderr: Some(String::from("There's errors")),
             return_code: 0,
+            ..Default::default()
         }),
     };
 
     let expected_failed =
-        format!("Task '{}' was started at {}\nTask '{}' stdout:\n{}\n{}{}{}\n",
+        format!("Task '{}' was started at {}\nTask '{}' stdout (11 bytes):\n{}\n{}{}{}\n",
                 "fails".cyan(),
                 dt,
                 "fails".cyan(),
@@ -1180,13 +4241,36 @@ fn test_get_task_result_line_str() {
                 "Task '".red(),
                 "fails".cyan(),
                 "': couldn't be started. Reason: The task exited with something unexpected".red());
-    let (stdout_failed, stderr_failed) = get_task_result_line_str(&task_failure);
+    let (stdout_failed, stderr_failed) = get_task_result_line_str(&task_failure, false);
     assert_eq!(expected_failed, stdout_failed);
-    assert_eq!(format!("Task '{}' stderr:\n{}\n",
+    assert_eq!(format!("Task '{}' stderr (14 bytes):\n{}\n",
                        "fails".cyan(),
                        "There's errors".red()),
                stderr_failed.unwrap());
 
+    // only failures show output with output_on_failure
+    let expected_quiet = format!("Task '{}' was started at {}\n{}{}{}\n",
+                                 "hello world".cyan(),
+                                 dt,
+                                 "Task '".green(),
+                                 "hello world".cyan(),
+                                 "': succeeded after 20.0s".green());
+    assert_eq!((expected_quiet, None),
+               get_task_result_line_str(&sample_task, true));
+    assert_eq!(get_task_result_line_str(&task_failure, false),
+               get_task_result_line_str(&task_failure, true));
+
+    // tasks run on a remote host say where they ran
+    let mut task_on_host = sample_task.clone();
+    if let Some(ref mut res) = task_on_host.run_result {
+        res.host = Some("worker-1".to_string());
+    }
+    assert!(get_task_result_line_str(&task_on_host, false)
+        .0
+        .starts_with(&format!("Task '{}' was started at {} on 'worker-1'\n",
+                              "hello world".cyan(),
+                              dt)));
+
 }
 
 #[test]
@@ -1207,6 +4291,7 @@ fn test_get_task_results_str_summary() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_one = Task::<&FactfileTask> {
@@ -1221,6 +4306,7 @@ fn test_get_task_results_str_summary() {
             stdout: Some(String::from("hello world")),
             stderr: Some(String::from("Mistake")),
             return_code: 0,
+            ..Default::default()
         }),
     };
 
@@ -1235,6 +4321,7 @@ fn test_get_task_results_str_summary() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_two = Task::<&FactfileTask> {
@@ -1249,11 +4336,12 @@ fn test_get_task_results_str_summary() {
             stdout: Some(String::from("hello world")),
             stderr: Some(String::from("Mistake")),
             return_code: 0,
+            ..Default::default()
         }),
     };
 
     let mut tasks: Vec<&Task<&FactfileTask>> = vec![];
-    let (stdout, stderr) = get_task_results_str(&tasks);
+    let (stdout, stderr) = get_task_results_str(&tasks, false, None);
     let expected: String = format!("{}", "0/0 tasks run in 0.0s\n".green());
 
     assert_eq!(stdout, expected);
@@ -1261,8 +4349,8 @@ fn test_get_task_results_str_summary() {
 
     tasks.push(&task_one);
 
-    let (one_task_stdout, one_task_stderr) = get_task_results_str(&tasks);
-    let (first_task_stdout, first_task_stderr) = get_task_result_line_str(&tasks[0]);
+    let (one_task_stdout, one_task_stderr) = get_task_results_str(&tasks, false, None);
+    let (first_task_stdout, first_task_stderr) = get_task_result_line_str(&tasks[0], false);
     let expected_one_task = format!("{}{}",
                                     first_task_stdout,
                                     "1/1 tasks run in 20.0s\n".green());
@@ -1273,8 +4361,8 @@ fn test_get_task_results_str_summary() {
 
     tasks.push(&task_two);
 
-    let (two_task_stdout, two_task_stderr) = get_task_results_str(&tasks);
-    let (task_two_stdout, task_two_stderr) = get_task_result_line_str(&tasks[1]);
+    let (two_task_stdout, two_task_stderr) = get_task_results_str(&tasks, false, None);
+    let (task_two_stdout, task_two_stderr) = get_task_result_line_str(&tasks[1], false);
     let expected_two_task = format!("{}{}{}",
                                     first_task_stdout,
                                     task_two_stdout,
@@ -1285,12 +4373,91 @@ fn test_get_task_results_str_summary() {
 
 }
 
+#[test]
+fn test_get_failed_task_stderr_only_has_failures() {
+    use chrono::UTC;
+    use factotum::executor::execution_strategy::RunResult;
+    use factotum::factfile::Task as FactfileTask;
+
+    let spec = FactfileTask { name: "a".to_string(), ..Default::default() };
+    let result = RunResult {
+        duration: Duration::from_secs(1),
+        stderr: Some(String::from("Mistake")),
+        return_code: 1,
+        ..Default::default()
+    };
+
+    let failed = Task::<&FactfileTask> {
+        name: String::from("failed"),
+        state: State::Failed("the task exited with a value not specified in continue_job - 1"
+            .to_string()),
+        task_spec: &spec,
+        run_started: Some(UTC::now()),
+        run_result: Some(result.clone()),
+    };
+    let succeeded = Task::<&FactfileTask> {
+        name: String::from("succeeded"),
+        state: State::Success,
+        task_spec: &spec,
+        run_started: Some(UTC::now()),
+        run_result: Some(result),
+    };
+
+    let stderr = get_failed_task_stderr(&vec![&succeeded, &failed]);
+    assert_eq!(stderr.len(), 1);
+    assert!(stderr[0].contains(&format!("{}", "failed".cyan())));
+    assert!(stderr[0].contains("Mistake"));
+
+    assert!(get_failed_task_stderr(&vec![&succeeded]).is_empty());
+}
+
+#[test]
+fn test_get_run_meta_masks_secrets() {
+    let start = UTC::now();
+    let args = vec!["factotum".to_string(),
+                    "run".to_string(),
+                    "no-such.factfile".to_string(),
+                    "--env={\"password\": \"hunter2\"}".to_string()];
+    let meta = get_run_meta("a-run-id",
+                            "no-such.factfile",
+                            &None,
+                            &args,
+                            &vec!["hunter2".to_string()],
+                            &start,
+                            &start,
+                            PROC_OTHER_ERROR);
+
+    assert_eq!(meta.find("runId").unwrap().as_string(), Some("a-run-id"));
+    assert_eq!(meta.find("args").unwrap().as_array().unwrap()[3],
+               Json::String("--env={\"password\": \"***\"}".to_string()));
+    assert_eq!(meta.find("factfileSha256").unwrap(), &Json::Null);
+    assert_eq!(meta.find("exitCode").unwrap().as_i64(), Some(3));
+    assert_eq!(meta.find("status").unwrap().as_string(), Some("other_error"));
+    assert_eq!(meta.find("errorCode").unwrap(), &Json::Null);
+
+    let meta = get_run_meta("a-run-id",
+                            "no-such.factfile",
+                            &None,
+                            &args,
+                            &vec![],
+                            &start,
+                            &start,
+                            PROC_START_TASK_TRIGGERS_PRIOR_TASKS);
+    assert_eq!(meta.find("errorCode").unwrap().as_string(),
+               Some("TRIGGERS_PRIOR_TASKS"));
+}
+
 #[test]
 fn test_start_task_validation_not_present() {
     let mut factfile = Factfile::new("N/A", "test");
 
     match validate_start_task(&factfile, "something") {
-        Err(r) => assert_eq!(r, "the task specified could not be found"),
+        Err(r) => {
+            assert_eq!(r, StartTaskError::TaskNotFound);
+            assert_eq!(r.message(), "the task specified could not be found");
+            assert_eq!(r.code(), "TASK_NOT_FOUND");
+            assert_eq!(r.exit_code(), PROC_START_TASK_NOT_FOUND);
+        }
         _ => unreachable!("validation did not fail"),
     }
 
@@ -1317,6 +4484,7 @@ fn test_start_task_cycles() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_b = Task {
@@ -1329,6 +4497,7 @@ fn test_start_task_cycles() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_c = Task {
@@ -1341,6 +4510,7 @@ fn test_start_task_cycles() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     let task_d = Task {
@@ -1353,6 +4523,7 @@ fn test_start_task_cycles() {
             terminate_job: vec![],
             continue_job: vec![],
         },
+        ..Default::default()
     };
 
     factfile.add_task_obj(&task_a);
@@ -1362,8 +4533,11 @@ fn test_start_task_cycles() {
 
     match validate_start_task(&factfile, "c") {
         Err(r) => {
-            assert_eq!(r,
-                       "the job cannot be started here without triggering prior tasks")
+            assert_eq!(r, StartTaskError::TriggersPriorTasks);
+            assert_eq!(r.message(),
+                       "the job cannot be started here without triggering prior tasks");
+            assert_eq!(r.code(), "TRIGGERS_PRIOR_TASKS");
+            assert_eq!(r.exit_code(), PROC_START_TASK_TRIGGERS_PRIOR_TASKS);
         }
         _ => unreachable!("the task validated when it shouldn't have"),
     }
@@ -1381,7 +4555,7 @@ fn test_gethostname_safe() {
 
 #[test]
 fn test_get_external_addrs() {
-    let external_addrs = get_external_addrs();
+    let external_addrs = get_external_addrs(None);
     if let Ok(ok_external_addrs) = external_addrs {
         assert!(ok_external_addrs.len() > 0);
     } else {
@@ -1391,13 +4565,333 @@ fn test_get_external_addrs() {
 
 #[test]
 fn test_is_valid_host() {
-    is_valid_host("*").expect("must be Ok() for wildcard");
+    is_valid_host("*", None, &AddressMatch::Any).expect("must be Ok() for wildcard");
+    is_valid_host("*", Some("no-such-interface"), &AddressMatch::Any)
+        .expect("must be Ok() for wildcard");
 
     // Test each external addr is_valid_host
-    let external_addrs = get_external_addrs()
+    let external_addrs = get_external_addrs(None)
         .expect("get_external_addrs() must return a Ok(Vec<net::SocketAddr>) that is non-empty");
     for external_addr in external_addrs {
         let ip_str = external_addr.ip().to_string();
-        is_valid_host(&ip_str).expect(&format!("must be Ok() for IP {}", &ip_str));
+        is_valid_host(&ip_str, None, &AddressMatch::Any)
+            .expect(&format!("must be Ok() for IP {}", &ip_str));
+        is_valid_host(&ip_str, None, &AddressMatch::All)
+            .expect(&format!("must be Ok() for IP {}", &ip_str));
+        assert!(is_valid_host(&ip_str, Some("no-such-interface"), &AddressMatch::Any).is_err());
+    }
+}
+
+#[test]
+fn test_is_address_match_policies() {
+    let local: net::IpAddr = "10.0.0.1".parse().unwrap();
+    let other: net::IpAddr = "10.0.0.2".parse().unwrap();
+    let external = vec![local];
+
+    let local_first = vec![local, other];
+    assert!(is_address_match(&local_first, &external, &AddressMatch::Any));
+    assert!(is_address_match(&local_first, &external, &AddressMatch::First));
+    assert!(!is_address_match(&local_first, &external, &AddressMatch::All));
+
+    let other_first = vec![other, local];
+    assert!(is_address_match(&other_first, &external, &AddressMatch::Any));
+    assert!(!is_address_match(&other_first, &external, &AddressMatch::First));
+
+    assert!(!is_address_match(&vec![], &external, &AddressMatch::All));
+
+    assert_eq!(get_address_match(None), Ok(AddressMatch::Any));
+    assert_eq!(get_address_match(Some("all")), Ok(AddressMatch::All));
+    assert!(get_address_match(Some("random")).is_err());
+}
+
+#[test]
+fn test_get_external_addrs_unknown_interface() {
+    match get_external_addrs(Some("no-such-interface")) {
+        Ok(_) => panic!("get_external_addrs() must fail for an interface that doesn't exist"),
+        Err(msg) => {
+            assert_eq!(msg,
+                       "could not find any non-loopback IPv4 addresses on the network interface \
+                        'no-such-interface'")
+        }
     }
 }
+
+#[test]
+fn test_get_summary_format() {
+    assert_eq!(get_summary_format(&None), Ok(SummaryFormat::Text));
+    assert_eq!(get_summary_format(&Some("text".to_string())), Ok(SummaryFormat::Text));
+    assert_eq!(get_summary_format(&Some("tap".to_string())), Ok(SummaryFormat::Tap));
+    assert_eq!(get_summary_format(&Some("csv".to_string())), Ok(SummaryFormat::Csv));
+    assert_eq!(get_summary_format(&Some("junit".to_string())),
+               Err("unknown summary format 'junit' (supported formats: text, tap, csv)"
+                   .to_string()));
+}
+
+#[test]
+fn test_get_ordered_task_results() {
+    let spec = FactfileTask { name: "spec".to_string(), ..Default::default() };
+    let mut a = Task::<&FactfileTask>::new("a", &spec);
+    a.state = State::Success;
+    let mut b = Task::<&FactfileTask>::new("b", &spec);
+    b.state = State::Failed("it broke".to_string());
+    let mut c = Task::<&FactfileTask>::new("c", &spec);
+    c.state = State::Skipped("the task 'b' failed".to_string());
+    let mut d = Task::<&FactfileTask>::new("d", &spec);
+    d.state = State::Failed("it broke too".to_string());
+    let tasks = vec![&a, &b, &c, &d];
+
+    let names = |order| {
+        get_ordered_task_results(&tasks, order)
+            .iter()
+            .map(|t| t.name.clone())
+            .collect::<Vec<String>>()
+    };
+    assert_eq!(names(SummaryOrder::Dag), vec!["a", "b", "c", "d"]);
+    assert_eq!(names(SummaryOrder::FailuresFirst), vec!["b", "d", "a", "c"]);
+    assert_eq!(names(SummaryOrder::FailuresLast), vec!["a", "c", "b", "d"]);
+
+    assert_eq!(get_summary_order(&None), Ok(SummaryOrder::Dag));
+    assert_eq!(get_summary_order(&Some("failures-last".to_string())),
+               Ok(SummaryOrder::FailuresLast));
+    assert!(get_summary_order(&Some("alphabetical".to_string())).is_err());
+}
+
+#[test]
+fn test_get_csv_summary() {
+    use factotum::executor::execution_strategy::RunResult;
+
+    let spec = FactfileTask { name: "spec".to_string(), ..Default::default() };
+    let started = UTC::now();
+
+    let failed = Task::<&FactfileTask> {
+        name: String::from("load, then \"check\""),
+        state: State::Failed("the task exited with a value not specified in continue_job - 1"
+            .to_string()),
+        task_spec: &spec,
+        run_started: Some(started),
+        run_result: Some(RunResult {
+            duration: Duration::from_millis(1500),
+            return_code: 1,
+            ..Default::default()
+        }),
+    };
+
+    let skipped = Task::<&FactfileTask> {
+        name: String::from("report"),
+        state: State::Skipped("the task 'load' failed".to_string()),
+        task_spec: &spec,
+        run_started: None,
+        run_result: None,
+    };
+
+    let ended = started + chrono::Duration::milliseconds(1500);
+    assert_eq!(get_csv_summary(&vec![&failed, &skipped]),
+               format!("task,state,return_code,start_time,end_time,duration_seconds\n\
+                        \"load, then \"\"check\"\"\",FAILED,1,{},{},1.500\n\
+                        report,SKIPPED,,,,\n",
+                       started.to_rfc3339(),
+                       ended.to_rfc3339()));
+}
+
+#[test]
+fn test_get_tap_summary() {
+    use factotum::executor::execution_strategy::RunResult;
+
+    let spec = FactfileTask { name: "spec".to_string(), ..Default::default() };
+
+    let passed = Task::<&FactfileTask> {
+        name: String::from("passed"),
+        state: State::Success,
+        task_spec: &spec,
+        run_started: Some(UTC::now()),
+        run_result: Some(RunResult {
+            duration: Duration::from_secs(2),
+            return_code: 0,
+            ..Default::default()
+        }),
+    };
+
+    let failed = Task::<&FactfileTask> {
+        name: String::from("failed #1"),
+        state: State::Failed("the task exited with a value not specified in continue_job - 1"
+            .to_string()),
+        task_spec: &spec,
+        run_started: Some(UTC::now()),
+        run_result: Some(RunResult {
+            duration: Duration::from_secs(1),
+            return_code: 1,
+            ..Default::default()
+        }),
+    };
+
+    let skipped = Task::<&FactfileTask> {
+        name: String::from("skipped"),
+        state: State::Skipped("the task 'failed #1' failed".to_string()),
+        task_spec: &spec,
+        run_started: None,
+        run_result: None,
+    };
+
+    let tasks = vec![&passed, &failed, &skipped];
+
+    assert_eq!(get_tap_summary(&tasks),
+               "TAP version 13\n\
+                1..3\n\
+                ok 1 - passed # 2.0s\n\
+                not ok 2 - failed \\#1\n  \
+                ---\n  \
+                message: \"the task exited with a value not specified in continue_job - 1\"\n  \
+                returnCode: 1\n  \
+                stdoutBytes: 0\n  \
+                stderrBytes: 0\n  \
+                duration: \"1.0s\"\n  \
+                ...\n\
+                ok 3 - skipped # SKIP the task 'failed #1' failed\n");
+}
+
+#[test]
+fn test_get_matrix_combinations() {
+    let combinations = get_matrix_combinations("{\"region\":[\"us\",\"eu\"],\"ds\":[\"a\",\"b\"]}")
+        .unwrap();
+    let combination_strs = combinations.iter().map(get_combination_str).collect::<Vec<String>>();
+    assert_eq!(combination_strs,
+               vec!["ds=a, region=us", "ds=a, region=eu", "ds=b, region=us", "ds=b, region=eu"]);
+
+    assert_eq!(get_matrix_combinations("{\"region\":[]}"),
+               Err("the matrix value 'region' has no values to run with".to_string()));
+    assert!(get_matrix_combinations("{\"region\":\"us\"}").is_err());
+}
+
+#[test]
+fn test_with_matrix_values() {
+    let env = str_to_json("{\"region\":\"ap\",\"user\":\"ed\"}").ok();
+    let mut combination = BTreeMap::new();
+    combination.insert("region".to_string(), "us".to_string());
+
+    assert_eq!(with_matrix_values(&env, &combination),
+               str_to_json("{\"region\":\"us\",\"user\":\"ed\"}").ok());
+}
+
+#[test]
+fn test_run_matrix_fail_fast() {
+    use std::cell::Cell;
+
+    let combinations = get_matrix_combinations("{\"ds\":[\"a\",\"b\",\"c\"]}").unwrap();
+    let runs = Cell::new(0);
+    let fail_on_b = |c: &BTreeMap<String, String>| {
+        runs.set(runs.get() + 1);
+        if c["ds"] == "b" { PROC_EXEC_ERROR } else { PROC_SUCCESS }
+    };
+
+    assert_eq!(run_matrix(&combinations, false, &fail_on_b), PROC_EXEC_ERROR);
+    assert_eq!(runs.get(), 3);
+
+    runs.set(0);
+    assert_eq!(run_matrix(&combinations, true, &fail_on_b), PROC_EXEC_ERROR);
+    assert_eq!(runs.get(), 2);
+}
+
+#[test]
+fn test_get_task_approval() {
+    assert_eq!(get_task_approval("r\n"), Some(TaskApproval::Run));
+    assert_eq!(get_task_approval("Skip\n"), Some(TaskApproval::Skip));
+    assert_eq!(get_task_approval(" a "), Some(TaskApproval::Abort));
+    assert_eq!(get_task_approval("maybe\n"), None);
+}
+
+#[test]
+fn test_get_duration_warnings() {
+    use factotum::executor::execution_strategy::RunResult;
+
+    let expected = FactfileTask { expected_duration_seconds: Some(60), ..Default::default() };
+    let unexpected = FactfileTask::default();
+    let task = |name: &str, spec, secs| {
+        Task::<&FactfileTask> {
+            name: name.to_string(),
+            state: State::Success,
+            task_spec: spec,
+            run_started: Some(UTC::now()),
+            run_result: Some(RunResult {
+                duration: Duration::from_secs(secs),
+                ..Default::default()
+            }),
+        }
+    };
+    let tasks = vec![task("slow", &expected, 150),
+                     task("fast", &expected, 20),
+                     task("steady", &expected, 75),
+                     task("unknown", &unexpected, 600)];
+
+    assert_eq!(get_duration_warnings(&tasks.iter().collect(), 50.0),
+               vec!["'slow' took 2m, 30s, 150% longer than its expected 1m, 0s",
+                    "'fast' took 20.0s, 67% shorter than its expected 1m, 0s"]);
+    assert_eq!(get_duration_warnings(&tasks.iter().collect(), 200.0), Vec::<String>::new());
+}
+
+#[test]
+fn test_get_category_summary() {
+    use factotum::executor::execution_strategy::RunResult;
+
+    let mut ingest = FactfileTask { name: "ingest".to_string(), ..Default::default() };
+    ingest.tags.insert("category".to_string(), "ingest".to_string());
+    let mut load = FactfileTask { name: "load".to_string(), ..Default::default() };
+    load.tags.insert("category".to_string(), "load".to_string());
+    let untagged = FactfileTask { name: "untagged".to_string(), ..Default::default() };
+
+    let ran_for = |secs| {
+        Some(RunResult { duration: Duration::from_secs(secs), ..Default::default() })
+    };
+    let tasks = vec![Task::<&FactfileTask> {
+                         name: "ingest one".to_string(),
+                         state: State::Success,
+                         task_spec: &ingest,
+                         run_started: Some(UTC::now()),
+                         run_result: ran_for(2),
+                     },
+                     Task::<&FactfileTask> {
+                         name: "ingest two".to_string(),
+                         state: State::Failed("it broke".to_string()),
+                         task_spec: &ingest,
+                         run_started: Some(UTC::now()),
+                         run_result: ran_for(3),
+                     },
+                     Task::<&FactfileTask> {
+                         name: "load".to_string(),
+                         state: State::Skipped("the task 'ingest two' failed".to_string()),
+                         task_spec: &load,
+                         run_started: None,
+                         run_result: None,
+                     },
+                     Task::<&FactfileTask> {
+                         name: "untagged".to_string(),
+                         state: State::Success,
+                         task_spec: &untagged,
+                         run_started: Some(UTC::now()),
+                         run_result: ran_for(1),
+                     }];
+
+    assert_eq!(get_category_summary(&tasks.iter().collect(), "category"),
+               vec!["(untagged): 1 succeeded, 0 failed, 0 not run in 1.0s",
+                    "ingest: 1 succeeded, 1 failed, 0 not run in 5.0s",
+                    "load: 0 succeeded, 0 failed, 1 not run in 0.0s"]);
+}
+
+#[test]
+fn test_get_updates_channel_copies_to_every_consumer() {
+    use factotum::executor::{ExecutionState, JobTransition, Transition};
+
+    assert!(get_updates_channel(vec![]).is_none());
+
+    let (tx_one, rx_one) = mpsc::channel::<ExecutionUpdate>();
+    let (tx_two, rx_two) = mpsc::channel::<ExecutionUpdate>();
+    let updates = get_updates_channel(vec![tx_one, tx_two]).unwrap();
+
+    let update = ExecutionUpdate::new(ExecutionState::Started,
+                                      vec![],
+                                      Transition::Job(JobTransition::new(None,
+                                                                         ExecutionState::Started)));
+    updates.send(update.clone()).unwrap();
+
+    assert_eq!(rx_one.recv().unwrap(), update);
+    assert_eq!(rx_two.recv().unwrap(), update);
+}